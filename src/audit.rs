@@ -0,0 +1,359 @@
+//! # audit
+//!
+//! The `audit` module checks the fetched crates against the RustSec advisory database, read
+//! through the OSV API, for `--audit`. Each advisory is rated from its CVSS vector, and the
+//! report colors it by [`Severity`]: red for critical and high, yellow for medium and dim for
+//! low and informational advisories, such as unmaintained crates.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::audit::{Advisory, AdvisoryDatabase, MemoryAdvisories, Severity};
+//!
+//! let mut database = MemoryAdvisories::new();
+//! database.add(Advisory {
+//!     id: "RUSTSEC-2020-0071".to_string(),
+//!     crate_name: "time".to_string(),
+//!     version: "0.1.43".to_string(),
+//!     title: "Potential segfault in the time crate".to_string(),
+//!     severity: Severity::from_cvss("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:N/I:N/A:H"),
+//! });
+//!
+//! let advisories = database.advisories("time", "0.1.43").unwrap();
+//! assert_eq!(advisories[0].severity, Some(Severity::Medium));
+//! assert!(database.advisories("time", "0.3.36").unwrap().is_empty());
+//! ```
+
+use reqwest::blocking::Client as HttpClient;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The endpoint of the OSV API listing the advisories affecting a package version.
+const OSV_QUERY_URL: &str = "https://api.osv.dev/v1/query";
+
+/// How severe an advisory is, from the qualitative rating of its CVSS base score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Not a vulnerability, e.g. an unmaintained or unsound crate, or a score of 0.
+    Informational,
+    /// A score from 0.1 to 3.9.
+    Low,
+    /// A score from 4.0 to 6.9.
+    Medium,
+    /// A score from 7.0 to 8.9.
+    High,
+    /// A score from 9.0 to 10.0.
+    Critical,
+}
+
+impl Severity {
+    /// Rates a CVSS 3.0 or 3.1 vector by its base score, `None` if the vector cannot be read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::audit::Severity;
+    ///
+    /// let rate = |metrics: &str| Severity::from_cvss(&format!("CVSS:3.1/{}", metrics));
+    /// assert_eq!(rate("AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"), Some(Severity::Critical));
+    /// assert_eq!(rate("AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:H/A:N"), Some(Severity::High));
+    /// assert_eq!(rate("AV:L/AC:L/PR:L/UI:N/S:U/C:N/I:N/A:H"), Some(Severity::Medium));
+    /// assert_eq!(rate("AV:N/AC:H/PR:N/UI:N/S:U/C:L/I:N/A:N"), Some(Severity::Low));
+    /// assert_eq!(rate("AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N"), Some(Severity::Informational));
+    /// assert_eq!(Severity::from_cvss("CVSS:2.0/AV:N"), None);
+    /// ```
+    pub fn from_cvss(vector: &str) -> Option<Severity> {
+        let score = cvss_base_score(vector)?;
+        Some(match score {
+            score if score >= 9.0 => Severity::Critical,
+            score if score >= 7.0 => Severity::High,
+            score if score >= 4.0 => Severity::Medium,
+            score if score > 0.0 => Severity::Low,
+            _ => Severity::Informational,
+        })
+    }
+
+    /// Returns the ANSI code the advisories of this severity are written in.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::audit::Severity;
+    ///
+    /// assert_eq!(Severity::Critical.color_code(), Severity::High.color_code());
+    /// assert_eq!(Severity::Medium.color_code(), "33");
+    /// assert_eq!(Severity::Informational.color_code(), "2");
+    /// ```
+    pub fn color_code(self) -> &'static str {
+        match self {
+            Severity::Critical | Severity::High => "31",
+            Severity::Medium => "33",
+            Severity::Low | Severity::Informational => "2",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Informational => "informational",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+/// Computes the base score of a CVSS 3.x vector, following the formulas of the specification.
+fn cvss_base_score(vector: &str) -> Option<f64> {
+    let mut parts = vector.split('/');
+    if !matches!(parts.next()?, "CVSS:3.0" | "CVSS:3.1") {
+        return None;
+    }
+    let metrics: HashMap<&str, &str> = parts.filter_map(|part| part.split_once(':')).collect();
+    let metric = |name: &str| metrics.get(name).copied();
+
+    let changed = match metric("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return None,
+    };
+    let attack_vector = match metric("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let attack_complexity = match metric("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let privileges = match (metric("PR")?, changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let user_interaction = match metric("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+    let impact_of = |name: &str| match metric(name)? {
+        "H" => Some(0.56),
+        "L" => Some(0.22),
+        "N" => Some(0.0),
+        _ => None,
+    };
+    let (confidentiality, integrity, availability) =
+        (impact_of("C")?, impact_of("I")?, impact_of("A")?);
+
+    let base = 1.0 - (1.0 - confidentiality) * (1.0 - integrity) * (1.0 - availability);
+    let impact = if changed {
+        7.52 * (base - 0.029) - 3.25 * (base - 0.02f64).powi(15)
+    } else {
+        6.42 * base
+    };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 8.22 * attack_vector * attack_complexity * privileges * user_interaction;
+    let score = if changed {
+        1.08 * (impact + exploitability)
+    } else {
+        impact + exploitability
+    };
+    Some(round_up(score.min(10.0)))
+}
+
+/// Rounds a score up to one decimal, as the CVSS specification does to avoid floating point
+/// artifacts, e.g. 4.000001 being rounded to 4.1.
+fn round_up(score: f64) -> f64 {
+    let scaled = (score * 100_000.0).round() as u64;
+    if scaled.is_multiple_of(10_000) {
+        scaled as f64 / 100_000.0
+    } else {
+        (scaled / 10_000 + 1) as f64 / 10.0
+    }
+}
+
+/// An advisory affecting the resolved version of a crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Advisory {
+    /// The RustSec identifier of the advisory, e.g. `RUSTSEC-2020-0071`.
+    pub id: String,
+    /// The name of the affected crate.
+    pub crate_name: String,
+    /// The affected version, the one resolved in the tree.
+    pub version: String,
+    /// The one-line summary of the advisory.
+    pub title: String,
+    /// The severity rated from the CVSS vector of the advisory, `None` if it has none.
+    pub severity: Option<Severity>,
+}
+
+/// An error raised while querying an advisory database.
+#[derive(Debug)]
+pub struct AuditError(reqwest::Error);
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the advisory database could not be queried: {}", self.0)
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+/// A source of security advisories, such as `OsvDatabase`.
+pub trait AdvisoryDatabase {
+    /// Lists the advisories affecting the given version of a crate.
+    fn advisories(&self, crate_name: &str, version: &str) -> Result<Vec<Advisory>, AuditError>;
+}
+
+/// The RustSec advisories served by the OSV API.
+#[derive(Debug, Clone)]
+pub struct OsvDatabase {
+    client: HttpClient,
+}
+
+/// The advisories of the OSV API matching a query.
+#[derive(Deserialize)]
+struct OsvResponse {
+    #[serde(default)]
+    vulns: Vec<OsvVulnerability>,
+}
+
+#[derive(Deserialize)]
+struct OsvVulnerability {
+    id: String,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    severity: Vec<OsvSeverity>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+}
+
+#[derive(Deserialize)]
+struct OsvSeverity {
+    score: String,
+}
+
+#[derive(Deserialize)]
+struct OsvAffected {
+    #[serde(default)]
+    database_specific: Option<RustSecDetails>,
+}
+
+/// The RustSec fields of an affected package.
+#[derive(Deserialize)]
+struct RustSecDetails {
+    /// The kind of informational advisory, e.g. `unmaintained`, if it is one.
+    #[serde(default)]
+    informational: Option<String>,
+}
+
+impl OsvVulnerability {
+    /// Converts the vulnerability into an advisory on the given crate version.
+    fn into_advisory(self, crate_name: &str, version: &str) -> Advisory {
+        let informational = self
+            .affected
+            .iter()
+            .filter_map(|affected| affected.database_specific.as_ref())
+            .any(|details| details.informational.is_some());
+        let severity = if informational {
+            Some(Severity::Informational)
+        } else {
+            self.severity
+                .iter()
+                .find_map(|severity| Severity::from_cvss(&severity.score))
+        };
+        Advisory {
+            id: self.id,
+            crate_name: crate_name.to_string(),
+            version: version.to_string(),
+            title: self.summary.unwrap_or_default(),
+            severity,
+        }
+    }
+}
+
+impl OsvDatabase {
+    /// Creates a client of the OSV API sending the given user agent.
+    ///
+    /// # Returns
+    ///
+    /// The client, or an error if the user agent is not a valid header value.
+    pub fn new(user_agent: &str) -> Result<Self, reqwest::header::InvalidHeaderValue> {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_str(user_agent)?);
+        Ok(OsvDatabase {
+            client: HttpClient::builder()
+                .default_headers(headers)
+                .build()
+                .expect("a client without TLS options builds"),
+        })
+    }
+}
+
+impl AdvisoryDatabase for OsvDatabase {
+    fn advisories(&self, crate_name: &str, version: &str) -> Result<Vec<Advisory>, AuditError> {
+        let query = serde_json::json!({
+            "package": { "name": crate_name, "ecosystem": "crates.io" },
+            "version": version,
+        });
+        let response: OsvResponse = self
+            .client
+            .post(OSV_QUERY_URL)
+            .json(&query)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(AuditError)?;
+
+        // The GitHub advisories mirrored by OSV repeat the RustSec ones under another id
+        Ok(response
+            .vulns
+            .into_iter()
+            .filter(|vulnerability| vulnerability.id.starts_with("RUSTSEC-"))
+            .map(|vulnerability| vulnerability.into_advisory(crate_name, version))
+            .collect())
+    }
+}
+
+/// An in-memory advisory database, useful for tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryAdvisories {
+    advisories: Vec<Advisory>,
+}
+
+impl MemoryAdvisories {
+    /// Creates a new, empty MemoryAdvisories instance.
+    pub fn new() -> Self {
+        MemoryAdvisories::default()
+    }
+
+    /// Adds an advisory, affecting the crate version it names.
+    pub fn add(&mut self, advisory: Advisory) {
+        self.advisories.push(advisory);
+    }
+}
+
+impl AdvisoryDatabase for MemoryAdvisories {
+    fn advisories(&self, crate_name: &str, version: &str) -> Result<Vec<Advisory>, AuditError> {
+        Ok(self
+            .advisories
+            .iter()
+            .filter(|advisory| advisory.crate_name == crate_name && advisory.version == version)
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,292 @@
+//! # cache
+//!
+//! The `cache` module lets `fetch_package_info` reuse the metadata of crates it fetched before.
+//! A published crate version never changes, so the dependencies it declares are cached keyed by
+//! crate name and version, behind the [`MetadataCache`] trait. The metadata of a crate, such as
+//! its latest version, changes with every release, so backends may also cache it for a while
+//! before asking the registry again. Embedders running `depth` inside
+//! a larger service can plug in their own backend (Redis, an in-process map, ...) through
+//! [`FetchOptions::cache`](crate::package::FetchOptions::cache); `depth` provides the no-op
+//! [`NoCache`], used by default, and the file based [`DiskCache`].
+//!
+//! What is cached are the answers of the registry, a [`CrateInfo`] and the [`DependencyInfo`]s
+//! of a version, rather than the resolved `Package`. A `Package` depends on the options of the
+//! fetch, such as the enabled features, the dependency kinds and the filters, while the answers
+//! of the registry do not, so the same entries serve every later run, whatever its options.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::cache::MetadataCache;
+//! use depth::dependency_graph::DependencyGraph;
+//! use depth::package::FetchOptions;
+//! use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+//! use std::collections::HashMap;
+//! use std::sync::{Arc, Mutex};
+//!
+//! // An in-process backend, counting the dependencies served from it
+//! #[derive(Debug, Default)]
+//! struct InProcessCache {
+//!     entries: Mutex<HashMap<(String, String), Vec<DependencyInfo>>>,
+//!     hits: Mutex<usize>,
+//! }
+//!
+//! impl MetadataCache for InProcessCache {
+//!     fn get(&self, crate_name: &str, version: &str) -> Option<Vec<DependencyInfo>> {
+//!         let key = (crate_name.to_string(), version.to_string());
+//!         let entry = self.entries.lock().unwrap().get(&key).cloned();
+//!         *self.hits.lock().unwrap() += entry.is_some() as usize;
+//!         entry
+//!     }
+//!
+//!     fn put(&self, crate_name: &str, version: &str, dependencies: &[DependencyInfo]) {
+//!         let key = (crate_name.to_string(), version.to_string());
+//!         self.entries.lock().unwrap().insert(key, dependencies.to_vec());
+//!     }
+//! }
+//!
+//! let mut registry = MemoryRegistry::new();
+//! registry.add_crate(CrateInfo::new("app", "1.0.0"), vec![DependencyInfo::new("serde", "^1")]);
+//! registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![]);
+//!
+//! let cache = Arc::new(InProcessCache::default());
+//! let options = FetchOptions {
+//!     cache: cache.clone(),
+//!     ..FetchOptions::default()
+//! };
+//! for _ in 0..2 {
+//!     let mut graph = DependencyGraph::new();
+//!     graph.fetch_dependency_tree_with_registry("app", 3, &options, &registry).unwrap();
+//!     assert!(graph.package("serde").is_some());
+//! }
+//!
+//! // The second fetch read the dependencies of both crates from the cache
+//! assert_eq!(*cache.hits.lock().unwrap(), 2);
+//! ```
+
+use crate::registry::{CrateInfo, DependencyInfo};
+use directories::ProjectDirs;
+use log::warn;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long the `depth` binary trusts cached crate metadata by default, in seconds.
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 60 * 60;
+
+/// A store for the dependencies declared by crate versions, consulted before the registry.
+///
+/// Caches are shared behind an `Arc` and may be used from several threads, so both methods take
+/// `&self`. A cache is best effort: failing to read an entry is a miss, and failing to store
+/// one must not fail the fetch.
+pub trait MetadataCache: fmt::Debug + Send + Sync {
+    /// Returns the cached dependencies of a crate version, if any.
+    fn get(&self, crate_name: &str, version: &str) -> Option<Vec<DependencyInfo>>;
+
+    /// Stores the dependencies of a crate version.
+    fn put(&self, crate_name: &str, version: &str, dependencies: &[DependencyInfo]);
+
+    /// Returns the cached metadata of a crate, if any and still fresh. Backends not caching
+    /// crate metadata return `None`, so the registry is always asked.
+    fn get_crate(&self, _crate_name: &str) -> Option<CrateInfo> {
+        None
+    }
+
+    /// Stores the metadata of a crate, as last returned by the registry.
+    fn put_crate(&self, _info: &CrateInfo) {}
+}
+
+/// A cache keeping its entries in memory for the lifetime of a fetch, in front of another cache
+/// it reads and writes through to.
+///
+/// Concurrent fetches store the crates they prefetch here, whatever the cache of the options.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::cache::{MemoryCache, MetadataCache, NoCache};
+/// use depth::registry::CrateInfo;
+/// use std::sync::Arc;
+///
+/// let cache = MemoryCache::new(Arc::new(NoCache));
+/// cache.put_crate(&CrateInfo::new("serde", "1.0.0"));
+/// assert_eq!(cache.get_crate("serde").unwrap().max_version, "1.0.0");
+/// assert!(cache.get("serde", "1.0.0").is_none());
+/// ```
+#[derive(Debug)]
+pub struct MemoryCache {
+    crates: Mutex<HashMap<String, CrateInfo>>,
+    dependencies: Mutex<HashMap<(String, String), Vec<DependencyInfo>>>,
+    inner: Arc<dyn MetadataCache>,
+}
+
+impl MemoryCache {
+    /// Creates a new, empty MemoryCache instance in front of the given cache.
+    pub fn new(inner: Arc<dyn MetadataCache>) -> Self {
+        MemoryCache {
+            crates: Mutex::new(HashMap::new()),
+            dependencies: Mutex::new(HashMap::new()),
+            inner,
+        }
+    }
+}
+
+impl MetadataCache for MemoryCache {
+    fn get(&self, crate_name: &str, version: &str) -> Option<Vec<DependencyInfo>> {
+        let key = (crate_name.to_string(), version.to_string());
+        let cached = self.dependencies.lock().unwrap().get(&key).cloned();
+        cached.or_else(|| self.inner.get(crate_name, version))
+    }
+
+    fn put(&self, crate_name: &str, version: &str, dependencies: &[DependencyInfo]) {
+        let key = (crate_name.to_string(), version.to_string());
+        self.dependencies
+            .lock()
+            .unwrap()
+            .insert(key, dependencies.to_vec());
+        self.inner.put(crate_name, version, dependencies);
+    }
+
+    fn get_crate(&self, crate_name: &str) -> Option<CrateInfo> {
+        let cached = self.crates.lock().unwrap().get(crate_name).cloned();
+        cached.or_else(|| self.inner.get_crate(crate_name))
+    }
+
+    fn put_crate(&self, info: &CrateInfo) {
+        self.crates
+            .lock()
+            .unwrap()
+            .insert(info.name.clone(), info.clone());
+        self.inner.put_crate(info);
+    }
+}
+
+/// Returns the directory the `depth` binary caches crate metadata in, the cache directory of
+/// the platform as given by `directories`: `$XDG_CACHE_HOME/depth` or `~/.cache/depth` on
+/// Linux, `~/Library/Caches/depth` on macOS and `%LOCALAPPDATA%\depth\cache` on Windows.
+///
+/// Returns `None` when the home directory cannot be found.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "depth").map(|dirs| dirs.cache_dir().to_path_buf())
+}
+
+/// A cache that never stores anything, so every crate is fetched from the registry.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCache;
+
+impl MetadataCache for NoCache {
+    fn get(&self, _crate_name: &str, _version: &str) -> Option<Vec<DependencyInfo>> {
+        None
+    }
+
+    fn put(&self, _crate_name: &str, _version: &str, _dependencies: &[DependencyInfo]) {}
+}
+
+/// A cache storing each crate version as a JSON file, at `<dir>/<crate>/<version>.json`, and
+/// the metadata of each crate at `<dir>/<crate>/crate.json`.
+///
+/// Crate versions never go stale. Crate metadata is only served while the file is younger than
+/// the time to live, if one is set, so new releases are eventually picked up.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::cache::{DiskCache, MetadataCache};
+/// use depth::registry::{CrateInfo, DependencyInfo};
+/// use std::time::Duration;
+///
+/// let dir = std::env::temp_dir().join("depth-disk-cache-doctest");
+/// let cache = DiskCache::new(&dir);
+/// cache.put("serde", "1.0.0", &[DependencyInfo::new("serde_derive", "^1")]);
+///
+/// let dependencies = cache.get("serde", "1.0.0").unwrap();
+/// assert_eq!(dependencies[0].name, "serde_derive");
+/// assert!(cache.get("serde", "2.0.0").is_none());
+///
+/// cache.put_crate(&CrateInfo::new("serde", "1.0.0"));
+/// assert_eq!(cache.get_crate("serde").unwrap().max_version, "1.0.0");
+/// // Any entry is older than a time to live of zero
+/// assert!(DiskCache::new(&dir).with_ttl(Duration::ZERO).get_crate("serde").is_none());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    /// How long crate metadata is served for, forever if `None`.
+    ttl: Option<Duration>,
+}
+
+impl DiskCache {
+    /// Creates a new DiskCache instance storing its entries under the given directory, which is
+    /// created on the first write.
+    pub fn new(dir: &Path) -> Self {
+        DiskCache {
+            dir: dir.to_path_buf(),
+            ttl: None,
+        }
+    }
+
+    /// Only serves crate metadata cached less than the given time ago.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the path of the entry of a crate version.
+    fn entry_path(&self, crate_name: &str, version: &str) -> PathBuf {
+        self.dir.join(crate_name).join(format!("{}.json", version))
+    }
+
+    /// Returns the path of the metadata of a crate.
+    fn crate_path(&self, crate_name: &str) -> PathBuf {
+        self.dir.join(crate_name).join("crate.json")
+    }
+
+    /// Writes an entry, warning instead of failing when it cannot be written.
+    fn write(&self, crate_name: &str, path: &Path, content: serde_json::Result<String>) {
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, content?));
+        if let Err(err) = written {
+            warn!(
+                "failed to cache '{}' at '{}': {}",
+                crate_name,
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
+impl MetadataCache for DiskCache {
+    fn get(&self, crate_name: &str, version: &str) -> Option<Vec<DependencyInfo>> {
+        let content = fs::read_to_string(self.entry_path(crate_name, version)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn put(&self, crate_name: &str, version: &str, dependencies: &[DependencyInfo]) {
+        let path = self.entry_path(crate_name, version);
+        self.write(crate_name, &path, serde_json::to_string(dependencies));
+    }
+
+    fn get_crate(&self, crate_name: &str) -> Option<CrateInfo> {
+        let path = self.crate_path(crate_name);
+        if let Some(ttl) = self.ttl {
+            let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+            if age >= ttl {
+                return None;
+            }
+        }
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn put_crate(&self, info: &CrateInfo) {
+        let path = self.crate_path(&info.name);
+        self.write(&info.name, &path, serde_json::to_string(info));
+    }
+}
@@ -1,20 +1,470 @@
-use clap::Parser;
+use crate::cache::DEFAULT_CACHE_TTL_SECS;
+use crate::config::Config;
+use crate::dependency_graph::{ColorScheme, EdgeDirection, LinkStyle, SortOrder};
+use crate::package::DEFAULT_MAX_NODES;
+use crate::registry::{parse_registry_url, DEFAULT_RETRIES, DEFAULT_USER_AGENT, MIN_RATE_LIMIT_MS};
+use crate::semver_util::parse_version_req;
+use crate::OutputFormat;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Parser, ValueEnum};
+use reqwest::Url;
+use semver::VersionReq;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(
     author = "Mahmoud Harmouch",
     version = "0.0.4",
     about = "Visualize crate.io dependencies as a Tree",
-    name = "Visualize Deps Tree"
+    name = "Visualize Deps Tree",
+    disable_version_flag = true
 )]
 pub struct Cli {
-    /// Sets the package to display.
-    #[arg(short = 'c', long = "crate")]
-    pub crate_: String,
-    /// Sets the levels to display.
+    /// Prints the version of depth.
+    #[arg(short = 'V', action = clap::ArgAction::Version)]
+    pub print_version: Option<bool>,
+    /// Sets the package to display. Repeated or comma-separated, the trees of several packages
+    /// are displayed together, the crates they share being fetched once.
+    #[arg(
+        short = 'c',
+        long = "crate",
+        value_delimiter = ',',
+        required_unless_present_any = ["manifest_url", "manifest_path", "list_formats", "update_preview"]
+    )]
+    pub crate_: Vec<String>,
+    /// Fetches the tree of this version of the crate instead of its latest version, either an
+    /// exact version such as 1.0.100 or a requirement such as ^0.9.
+    #[arg(
+        short = 'v',
+        long = "version",
+        value_name = "VERSION",
+        requires = "crate_",
+        conflicts_with_all = ["reverse", "resolve_only"],
+        value_parser = parse_version_req
+    )]
+    pub version: Option<VersionReq>,
+    /// Prints the crates added, removed and changed in version by moving the crate from
+    /// --version, or its latest version, to this version.
+    #[arg(
+        long = "diff-version",
+        value_name = "VERSION",
+        requires = "crate_",
+        conflicts_with_all = ["reverse", "resolve_only", "compare_default"],
+        value_parser = parse_version_req
+    )]
+    pub diff_version: Option<VersionReq>,
+    /// Builds the tree from the Cargo.toml at this URL instead of a published crate.
+    #[arg(long = "manifest-url", value_name = "URL", conflicts_with = "crate_")]
+    pub manifest_url: Option<String>,
+    /// Builds the tree from this local Cargo.toml instead of a published crate.
+    #[arg(
+        long = "manifest-path",
+        value_name = "PATH",
+        conflicts_with_all = ["crate_", "manifest_url"]
+    )]
+    pub manifest_path: Option<PathBuf>,
+    /// Only lists the direct dependencies of --manifest-path, without any network access.
+    #[arg(long = "offline", requires = "manifest_path", default_value_t = false)]
+    pub offline: bool,
+    /// Fails on the first dependency that cannot be fetched, instead of listing it below the tree.
+    #[arg(long = "strict", default_value_t = false)]
+    pub strict: bool,
+    /// Prints the crates depending on the crate instead of its dependencies, --levels deep.
+    #[arg(
+        short = 'r',
+        long = "reverse",
+        requires = "crate_",
+        conflicts_with_all = ["compare_default", "format"],
+        default_value_t = false
+    )]
+    pub reverse: bool,
+    /// Sets the levels of dependencies to display: 0 for the crate alone, 1 for its direct
+    /// dependencies, 2 for theirs too, and so on.
     #[arg(short = 'l', long = "levels", default_value_t = 1)]
     pub levels: usize,
+    /// Display the whole tree, every level of dependencies, instead of --levels. Each crate is
+    /// only fetched once, however many crates depend on it.
+    #[arg(long = "full", default_value_t = false, conflicts_with = "levels")]
+    pub full: bool,
     /// Scan optional dependencies only.
     #[arg(short = 'o', long = "optional", default_value_t = false)]
     pub optional: bool,
+    /// Enables these comma separated features of the crate on top of its default features,
+    /// listing the optional dependencies they enable.
+    #[arg(
+        long = "features",
+        value_name = "FEATURES",
+        value_delimiter = ',',
+        conflicts_with_all = ["manifest_url", "manifest_path"]
+    )]
+    pub features: Option<Vec<String>>,
+    /// Prints the crates added and removed by --features compared to the default features only.
+    #[arg(
+        long = "compare-default",
+        requires = "features",
+        default_value_t = false
+    )]
+    pub compare_default: bool,
+    /// Treats crates whose names start with this prefix as internal, not fetching them (repeatable).
+    #[arg(long = "internal-prefix", value_name = "PREFIX")]
+    pub internal_prefix: Vec<String>,
+    /// Leaves the internal crates, the sysroot crates and the ones matching --internal-prefix,
+    /// out of the tree instead of flagging them as [internal].
+    #[arg(long = "hide-internal", default_value_t = false)]
+    pub hide_internal: bool,
+    /// Only fetch crates whose names match this glob (repeatable). Other crates are shown as pruned leaves.
+    /// Crates also matching --exclude are excluded.
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// Never fetch crates whose names match this glob, e.g. `serde*`, nor their dependencies,
+    /// leaving them out of the graph (repeatable). Takes precedence over --include.
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+    /// Keep the excluded crates in the graph as collapsed leaves instead of leaving them out.
+    #[arg(
+        long = "exclude-collapse",
+        default_value_t = false,
+        requires = "exclude"
+    )]
+    pub exclude_collapse: bool,
+    /// Follows at most this many dependencies of each crate, non-optional normal dependencies
+    /// first, the crates left out being counted next to their parent.
+    #[arg(
+        long = "max-fanout",
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_fanout: Option<usize>,
+    /// Stops fetching once the graph holds this many crates, marking the crates left out as
+    /// truncated, so that a misjudged --full crawl stays contained.
+    #[arg(
+        long = "max-nodes",
+        value_name = "N",
+        default_value_t = DEFAULT_MAX_NODES,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_nodes: usize,
+    /// List the dev-dependencies of the root crate, drawn dashed in the dot format.
+    #[arg(long = "include-dev", default_value_t = false)]
+    pub include_dev: bool,
+    /// List the build-dependencies, drawn dotted in the dot format.
+    #[arg(long = "include-build", default_value_t = false)]
+    pub include_build: bool,
+    /// List the dev-dependencies of every crate, not only of the root, implying --include-dev.
+    /// Cargo never builds the dev-dependencies of dependencies, and listing them can make the
+    /// tree a lot larger.
+    #[arg(long = "dev-recursive", default_value_t = false)]
+    pub dev_recursive: bool,
+    /// Sets the levels of normal dependencies to display, defaults to --levels.
+    #[arg(long = "normal-depth", value_name = "N")]
+    pub normal_depth: Option<usize>,
+    /// Sets the levels of build-dependencies to display, implying --include-build. Defaults to
+    /// --levels with --include-build.
+    #[arg(long = "build-depth", value_name = "N")]
+    pub build_depth: Option<usize>,
+    /// Sets the levels of dev-dependencies to display, implying --include-dev. Defaults to
+    /// --levels with --include-dev.
+    #[arg(long = "dev-depth", value_name = "N")]
+    pub dev_depth: Option<usize>,
+    /// Print every supported output format with a one-line description and exit.
+    #[arg(long = "list-formats", default_value_t = false)]
+    pub list_formats: bool,
+    /// Sets the output format.
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = OutputFormat::Tree)]
+    pub format: OutputFormat,
+    /// Write the rendered tree or graph to this file instead of stdout, e.g. the image of the svg
+    /// format.
+    #[arg(long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+    /// Explore the fetched tree interactively: the arrow keys move, Enter expands or collapses
+    /// a crate, / searches a crate by name and q quits.
+    #[arg(long = "tui", default_value_t = false, conflicts_with_all = ["format", "output"])]
+    pub tui: bool,
+    /// Print the N crates that took the longest to fetch.
+    #[arg(long = "timings", value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+    pub timings: Option<usize>,
+    /// Print chains of crates with a single dependency on one line (cargo-tree format only).
+    #[arg(long = "collapse-chains", default_value_t = false)]
+    pub collapse_chains: bool,
+    /// Sets the order of each crate's dependencies in the trees and the dot, svg and graphml
+    /// formats. Every order but insertion is the same across runs.
+    #[arg(long = "sort", value_enum, default_value_t = SortOrder::Name)]
+    pub sort: SortOrder,
+    /// Annotate each crate with the size of its published tarball (cargo-tree format only).
+    #[arg(long = "show-size", default_value_t = false)]
+    pub show_size: bool,
+    /// List the features of each crate under it, with the optional dependencies they enable.
+    #[arg(long = "show-features", default_value_t = false)]
+    pub show_features: bool,
+    /// Leave the link of each crate out of the default tree.
+    #[arg(long = "compact", default_value_t = false)]
+    pub compact: bool,
+    /// Fits the lines of the default tree to this many columns, shortening long links, then
+    /// long lines, with `…`. Without a value, fits them to the terminal.
+    #[arg(
+        long = "max-width",
+        value_name = "COLUMNS",
+        num_args = 0..=1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_width: Option<Option<usize>>,
+    /// Report the summed tarball size of the fetched crates.
+    #[arg(long = "total-size", default_value_t = false)]
+    pub total_size: bool,
+    /// Flag crates whose latest version was published by someone else than the previous one.
+    #[arg(long = "owner-changes", default_value_t = false)]
+    pub owner_changes: bool,
+    /// Annotate each crate with the number of crates depending on it (one request per crate).
+    #[arg(long = "show-rdeps", default_value_t = false)]
+    pub show_rdeps: bool,
+    /// Annotate each crate with its number of downloads on crates.io.
+    #[arg(long = "show-downloads", default_value_t = false)]
+    pub show_downloads: bool,
+    /// Sets the links printed next to crates.
+    #[arg(long = "link", value_enum, default_value_t = LinkStyle::Homepage)]
+    pub link: LinkStyle,
+    /// Sets the colors of the levels of the default tree.
+    #[arg(long = "color-scheme", value_enum, default_value_t = ColorScheme::Dark)]
+    pub color_scheme: ColorScheme,
+    /// Print the tree without colors. Colors are also left out when stdout is not a terminal or
+    /// the NO_COLOR environment variable is set.
+    #[arg(long = "no-color", default_value_t = false)]
+    pub no_color: bool,
+    /// Sets the direction of the edges of the dot and mermaid formats.
+    #[arg(long = "edge-style", value_enum, default_value_t = EdgeDirection::DependsOn)]
+    pub edge_style: EdgeDirection,
+    /// Labels the edges of the dot and mermaid formats with their direction.
+    #[arg(long = "edge-labels", default_value_t = false)]
+    pub edge_labels: bool,
+    /// Crawl without printing the tree, then print the crawl's throughput.
+    #[arg(long = "benchmark", default_value_t = false)]
+    pub benchmark: bool,
+    /// Only fetches the crate and its direct dependencies, then prints an estimate of the
+    /// requests the crawl would send instead of the tree, warning when it is very large.
+    #[arg(
+        long = "dry-run",
+        requires = "crate_",
+        conflicts_with_all = ["benchmark", "hash", "tui"],
+        default_value_t = false
+    )]
+    pub dry_run: bool,
+    /// Prints a SHA-256 of the resolved crates and versions instead of the tree, which only
+    /// changes when the resolved dependency set does.
+    #[arg(long = "hash", conflicts_with = "benchmark", default_value_t = false)]
+    pub hash: bool,
+    /// Leaves the root crate out of the tree, the exports and the counts, only covering its
+    /// dependencies. Only applies to a single --crate.
+    #[arg(long = "no-root", default_value_t = false)]
+    pub no_root: bool,
+    /// Sets the user agent sent to Crates.io, which should name a way to contact you.
+    #[arg(
+        long = "user-agent",
+        env = "DEPTH_USER_AGENT",
+        default_value = DEFAULT_USER_AGENT
+    )]
+    pub user_agent: String,
+    /// Sets the delay between two requests to Crates.io, at least one second as it asks.
+    #[arg(
+        long = "rate-limit-ms",
+        value_name = "MS",
+        default_value_t = MIN_RATE_LIMIT_MS,
+        value_parser = clap::value_parser!(u64).range(MIN_RATE_LIMIT_MS..)
+    )]
+    pub rate_limit_ms: u64,
+    /// Sends a request failing on a network or server error again up to this many times,
+    /// waiting a growing, randomized delay before each retry.
+    #[arg(long = "retries", value_name = "N", default_value_t = DEFAULT_RETRIES)]
+    pub retries: u32,
+    /// Fetches crates from the registry serving the Crates.io web API at this URL, such as a
+    /// private mirror, instead of Crates.io.
+    #[arg(
+        long = "registry",
+        value_name = "URL",
+        env = "DEPTH_REGISTRY",
+        value_parser = parse_registry_url
+    )]
+    pub registry: Option<Url>,
+    /// Fetches up to this many sibling crates concurrently, still sending one request per rate limit interval.
+    #[arg(
+        short = 'j',
+        long = "jobs",
+        value_name = "N",
+        default_value_t = 4,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub jobs: usize,
+    /// Fetches every crate from crates.io, without reading or writing the on-disk cache.
+    #[arg(long = "no-cache", default_value_t = false)]
+    pub no_cache: bool,
+    /// Serves the cached latest version of crates for this many seconds before asking crates.io again.
+    #[arg(
+        long = "cache-ttl",
+        value_name = "SECONDS",
+        default_value_t = DEFAULT_CACHE_TTL_SECS,
+        conflicts_with = "no_cache"
+    )]
+    pub cache_ttl: u64,
+    /// Pins crates to the versions in this lock file, and adds the newly resolved ones to it.
+    #[arg(long = "lock", value_name = "FILE")]
+    pub lock: Option<PathBuf>,
+    /// Ignores the pinned versions and resolves every crate again, rewriting the lock file.
+    #[arg(long = "update-lock", requires = "lock", default_value_t = false)]
+    pub update_lock: bool,
+    /// Prints the pinned crates whose latest version differs, old -> new, and exits.
+    #[arg(
+        long = "update-preview",
+        requires = "lock",
+        conflicts_with = "update_lock",
+        default_value_t = false
+    )]
+    pub update_preview: bool,
+    /// Flag crates that likely run a build script, i.e. declare build-dependencies (heuristic).
+    #[arg(long = "flag-build-scripts", default_value_t = false)]
+    pub flag_build_scripts: bool,
+    /// Report the distinct owners across the crates, and who owns the most (one request per crate).
+    #[arg(long = "contributors", default_value_t = false)]
+    pub contributors: bool,
+    /// Report the editions of the crates, the newest their rust-version allows unless a manifest
+    /// declares one, flagging edition 2015.
+    #[arg(long = "editions", default_value_t = false)]
+    pub editions: bool,
+    /// Check the fetched crates against the RustSec advisory database (one request per crate),
+    /// coloring the advisories by severity.
+    #[arg(long = "audit", default_value_t = false)]
+    pub audit: bool,
+    /// Report the N crates most other crates of the tree depend on (10 if N is omitted).
+    #[arg(
+        long = "most-shared",
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "10"
+    )]
+    pub most_shared: Option<usize>,
+    /// Do not print the crate, edge and depth counts after the tree, the progress of the fetch
+    /// nor warnings. Errors are still printed.
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        default_value_t = false,
+        conflicts_with = "verbose"
+    )]
+    pub quiet: bool,
+    /// Log each fetched crate and how long it took to stderr, repeat to also log every crate
+    /// served from the ones already fetched. `-v` is taken by --version.
+    #[arg(long = "verbose", action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Report the dependency cycles of the tree, each printed as `a -> b -> a`.
+    #[arg(long = "show-cycles", default_value_t = false)]
+    pub show_cycles: bool,
+    /// Report the longest dependency chain of the tree, printed as `root -> a -> b`, with its
+    /// number of levels.
+    #[arg(long = "longest-path", default_value_t = false)]
+    pub longest_path: bool,
+    /// Report the crates built in more than one version, with the crates requiring each version.
+    #[arg(long = "show-duplicates", default_value_t = false)]
+    pub show_duplicates: bool,
+    /// Print the latest version of the crate and exit, without fetching its dependencies.
+    #[arg(
+        long = "resolve-only",
+        conflicts_with = "manifest_url",
+        default_value_t = false
+    )]
+    pub resolve_only: bool,
+    /// Report the direct dependencies without which this crate would disappear from the tree.
+    #[arg(long = "blame", value_name = "CRATE")]
+    pub blame: Option<String>,
+    /// Only print the crates on the paths from the root to this crate.
+    #[arg(long = "focus", value_name = "CRATE")]
+    pub focus: Option<String>,
+    /// Print only the leaf crates of the tree, as a flat sorted list.
+    #[arg(long = "leaves-only", default_value_t = false)]
+    pub leaves_only: bool,
+    /// Reads settings, such as display aliases of crates and default options, from this TOML
+    /// file. Defaults to .depth.toml in the current directory, or else in the home directory.
+    /// Flags passed on the command line override the defaults of the file.
+    #[arg(long = "config", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Writes the raw JSON responses of crates.io to this directory, for debugging (can take tens of MiB).
+    #[arg(long = "dump-api", value_name = "DIR")]
+    pub dump_api: Option<PathBuf>,
+    /// Prints the notes of this TOML or JSON file, mapping crate names to notes, next to the crates.
+    #[arg(long = "notes", value_name = "FILE")]
+    pub notes: Option<PathBuf>,
+    /// Warns, and asks for confirmation in a terminal, above this many levels unless --max-nodes
+    /// is given.
+    #[arg(long = "warn-levels", value_name = "N", default_value_t = 6)]
+    pub warn_levels: usize,
+    /// Skips the confirmation asked before fetching many levels.
+    #[arg(short = 'y', long = "yes", alias = "force", default_value_t = false)]
+    pub yes: bool,
+}
+
+impl Cli {
+    /// Applies the defaults of a configuration file to the options not passed on the command
+    /// line, so the command line overrides the file, which overrides the built-in defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration file.
+    /// * `matches` - The matches the options were parsed from, telling which were passed.
+    ///
+    /// # Returns
+    ///
+    /// A Result, with an error if the file names an unknown format or color scheme, or a rate
+    /// limit below the one of crates.io.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use clap::{CommandFactory, FromArgMatches};
+    /// use depth::cli::Cli;
+    /// use depth::config::Config;
+    /// use depth::OutputFormat;
+    ///
+    /// let config: Config = "levels = 3\nformat = \"json\"".parse().unwrap();
+    /// let matches = Cli::command()
+    ///     .try_get_matches_from(["depth", "-c", "serde", "-l", "2"])
+    ///     .unwrap();
+    /// let mut args = Cli::from_arg_matches(&matches).unwrap();
+    /// args.apply_config(&config, &matches).unwrap();
+    ///
+    /// assert_eq!(args.levels, 2);
+    /// assert_eq!(args.format, OutputFormat::Json);
+    /// ```
+    pub fn apply_config(&mut self, config: &Config, matches: &ArgMatches) -> Result<(), String> {
+        // A value of the environment counts as passed, only the built-in defaults give way
+        let unset = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                None | Some(ValueSource::DefaultValue)
+            )
+        };
+        if let (Some(levels), true) = (config.levels, unset("levels") && !self.full) {
+            self.levels = levels;
+        }
+        if let (Some(optional), true) = (config.optional, unset("optional")) {
+            self.optional = optional;
+        }
+        if let (Some(format), true) = (&config.format, unset("format")) {
+            self.format = OutputFormat::from_str(format, true)
+                .map_err(|_| format!("unknown format '{}'", format))?;
+        }
+        if let (Some(color), true) = (&config.color, unset("color_scheme")) {
+            self.color_scheme = ColorScheme::from_str(color, true)
+                .map_err(|_| format!("unknown color scheme '{}'", color))?;
+        }
+        if let (Some(rate_limit_ms), true) = (config.rate_limit_ms, unset("rate_limit_ms")) {
+            if rate_limit_ms < MIN_RATE_LIMIT_MS {
+                return Err(format!(
+                    "rate-limit-ms must be at least {}",
+                    MIN_RATE_LIMIT_MS
+                ));
+            }
+            self.rate_limit_ms = rate_limit_ms;
+        }
+        if let (Some(exclude), true) = (&config.exclude, unset("exclude")) {
+            self.exclude = exclude.clone();
+        }
+        Ok(())
+    }
 }
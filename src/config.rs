@@ -0,0 +1,154 @@
+//! # config
+//!
+//! The `config` module reads the TOML configuration file passed with `--config`, or else the
+//! `.depth.toml` file found in the current directory or, failing that, the home directory. It
+//! holds the display aliases of crates, which the tree and graph renderers print in place of the
+//! real crate names to fit large diagrams (crates are still fetched and reported by their real
+//! names), the prefixes of internal crates, which are never fetched, and defaults for the
+//! options typed on every run.
+//!
+//! Options are resolved with the command line first, then the configuration file, then the
+//! built-in defaults: a default of the file only applies when its flag is not passed.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::config::Config;
+//!
+//! let config: Config = r#"
+//!     internal-prefixes = ["acme-"]
+//!     levels = 2
+//!     format = "json"
+//!     color = "light"
+//!     rate-limit-ms = 2000
+//!     exclude = ["windows-*"]
+//!
+//!     [aliases]
+//!     proc-macro2 = "pm2"
+//! "#
+//! .parse()
+//! .unwrap();
+//!
+//! assert_eq!(config.aliases["proc-macro2"], "pm2");
+//! assert_eq!(config.internal_prefixes, vec!["acme-"]);
+//! assert_eq!(config.levels, Some(2));
+//! assert_eq!(config.optional, None);
+//! assert_eq!(config.exclude, Some(vec!["windows-*".to_string()]));
+//! ```
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The name of the configuration file looked up when `--config` is not passed.
+pub const CONFIG_FILE_NAME: &str = ".depth.toml";
+
+/// The settings read from a configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Config {
+    /// Display aliases keyed by real crate name, e.g. `proc-macro2 = "pm2"`.
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    /// Prefixes of the names of internal crates, which are not fetched from crates.io, e.g.
+    /// `internal-prefixes = ["acme-"]`.
+    #[serde(default, rename = "internal-prefixes")]
+    pub internal_prefixes: Vec<String>,
+    /// The default of `--levels`.
+    pub levels: Option<usize>,
+    /// The default of `--optional`.
+    pub optional: Option<bool>,
+    /// The default of `--format`, e.g. `format = "json"`.
+    pub format: Option<String>,
+    /// The default of `--color-scheme`, e.g. `color = "light"`, or `"none"` for no colors.
+    pub color: Option<String>,
+    /// The default of `--rate-limit-ms`.
+    #[serde(rename = "rate-limit-ms")]
+    pub rate_limit_ms: Option<u64>,
+    /// The default of `--exclude`, replaced as a whole by the patterns passed on the command
+    /// line.
+    pub exclude: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Reads a configuration file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the TOML configuration file.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the configuration or an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read config '{}': {}", path.display(), err))?;
+        let config = content
+            .parse()
+            .map_err(|err| format!("invalid config '{}': {}", path.display(), err))?;
+        Ok(config)
+    }
+
+    /// Finds the configuration file used when `--config` is not passed: `.depth.toml` in the
+    /// current directory, or else in the home directory.
+    ///
+    /// # Returns
+    ///
+    /// The path of the file, or None if neither directory has one.
+    pub fn discover() -> Option<PathBuf> {
+        let home = env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+            .filter(|home| !home.is_empty())
+            .map(PathBuf::from);
+        Self::discover_in(env::current_dir().ok().as_deref(), home.as_deref())
+    }
+
+    /// Finds `.depth.toml` in the first of the directories having one.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory searched first, usually the current directory.
+    /// * `home` - The directory searched next, usually the home directory.
+    ///
+    /// # Returns
+    ///
+    /// The path of the file, or None if neither directory has one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::config::{Config, CONFIG_FILE_NAME};
+    /// use std::fs;
+    ///
+    /// let home = std::env::temp_dir().join("depth-config-discover-home");
+    /// let project = std::env::temp_dir().join("depth-config-discover-project");
+    /// fs::create_dir_all(&home).unwrap();
+    /// fs::create_dir_all(&project).unwrap();
+    /// fs::write(home.join(CONFIG_FILE_NAME), "levels = 2").unwrap();
+    /// let _ = fs::remove_file(project.join(CONFIG_FILE_NAME));
+    ///
+    /// let found = Config::discover_in(Some(&project), Some(&home));
+    /// assert_eq!(found, Some(home.join(CONFIG_FILE_NAME)));
+    ///
+    /// fs::write(project.join(CONFIG_FILE_NAME), "levels = 3").unwrap();
+    /// let found = Config::discover_in(Some(&project), Some(&home));
+    /// assert_eq!(found, Some(project.join(CONFIG_FILE_NAME)));
+    /// ```
+    pub fn discover_in(dir: Option<&Path>, home: Option<&Path>) -> Option<PathBuf> {
+        [dir, home]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+}
+
+impl FromStr for Config {
+    type Err = toml::de::Error;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        toml::from_str(content)
+    }
+}
@@ -9,23 +9,24 @@
 //!
 //! Get started with the `dependency_graph` module by following these simple steps:
 //!
-//! 1. Import the necessary types and functions into your code:
+//! 1. Import the necessary types into your code:
 //!
 //! ```rust
-//! use depth::package::{fetch_package_info, Package};
 //! use depth::dependency_graph::DependencyGraph;
-//! use crates_io_api::SyncClient;
+//! use depth::package::{FetchOptions, Package};
 //! ```
 //!
 //! 2. Create an instance of `DependencyGraph` and use it to fetch and visualize dependency trees:
 //!
-//! ```rust
+//! ```rust,no_run
 //! use depth::dependency_graph::DependencyGraph;
-//! use depth::package::Package;
+//! use depth::package::FetchOptions;
 //!
-//! let package = Package::new("".to_string(), "".to_string(), vec![("name".to_string(), "version".to_string())], false);
 //! let mut graph = DependencyGraph::new();
-//! graph.fetch_dependency_tree("your_package_name", 2, false);
+//! let package = graph
+//!     .fetch_dependency_tree_with_options("serde", 2, &FetchOptions::default())
+//!     .unwrap()
+//!     .expect("serde is published on crates.io");
 //! graph.print_dependencies_at_level(&package, 0, 2);
 //! ```
 //!
@@ -41,52 +42,151 @@
 //!
 //! ## Fetching Dependency Tree
 //!
-//! Use the `fetch_dependency_tree` method to fetch and build the dependency tree for a specific package:
+//! Use the `fetch_dependency_tree_with_options` method to fetch and build the dependency tree
+//! of a package from Crates.io, `FetchOptions::default()` following every non-optional
+//! dependency. `fetch_dependency_tree` takes the few settings most callers change instead:
 //!
-//! ```rust
+//! ```rust,no_run
 //! use depth::dependency_graph::DependencyGraph;
+//! use depth::package::FetchOptions;
+//! use depth::registry::DEFAULT_USER_AGENT;
+//!
+//! let mut graph = DependencyGraph::new();
+//! graph
+//!     .fetch_dependency_tree_with_options("serde", 2, &FetchOptions::default())
+//!     .unwrap();
 //!
+//! // Optional dependencies only, with another user agent
 //! let mut graph = DependencyGraph::new();
-//! graph.fetch_dependency_tree("your_package_name", 2, false);
+//! graph
+//!     .fetch_dependency_tree("serde", 2, true, DEFAULT_USER_AGENT)
+//!     .unwrap();
 //! ```
 //!
 //! ## Visualizing Dependencies
 //!
-//! Utilize the `print_dependencies_at_level` method to print dependencies at a specified depth in the dependency tree:
+//! Utilize the `write_dependencies_at_level` method to write dependencies up to a specified depth
+//! in the dependency tree, or `print_dependencies_at_level` to print them to stdout:
 //!
 //! ```rust
-//! use depth::package::{fetch_package_info, Package};
-//! use depth::dependency_graph::DependencyGraph;
+//! use depth::dependency_graph::{ColorScheme, DependencyGraph};
+//! use depth::package::Package;
 //!
-//! let package = Package::new("".to_string(), "".to_string(), vec![("name".to_string(), "version".to_string())], false);
+//! let dependencies = vec![("serde".to_string(), "^1".to_string())];
+//! let package = Package::new("app".to_string(), "".to_string(), dependencies, false);
 //! let mut graph = DependencyGraph::new();
-//! graph.print_dependencies_at_level(&package, 0, 2);
+//! graph.set_color_scheme(ColorScheme::None);
+//! graph.add_package_to_graph(&package);
+//!
+//! let mut output = Vec::new();
+//! graph.write_dependencies_at_level(&package, 0, 2, &mut output).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     " ├── app - ()\n    ├── serde - (^1)\n"
+//! );
 //! ```
 //!
 //! # Examples
 //!
+//! Trees can be fetched from any `CrateRegistry`, such as an in-memory registry in tests:
+//!
 //! ```rust
-//! use depth::package::Package;
 //! use depth::dependency_graph::DependencyGraph;
-//! use crates_io_api::SyncClient;
+//! use depth::package::FetchOptions;
+//! use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+//!
+//! let mut registry = MemoryRegistry::new();
+//! registry.add_crate(
+//!     CrateInfo::new("app", "1.0.0"),
+//!     vec![DependencyInfo::new("serde", "^1")],
+//! );
+//! registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![]);
 //!
 //! let mut graph = DependencyGraph::new();
-//! graph.fetch_dependency_tree("your_package_name", 2, false);
-//! // Additional functionality with the dependency graph...
+//! let app = graph
+//!     .fetch_dependency_tree_with_registry("app", 2, &FetchOptions::default(), &registry)
+//!     .unwrap()
+//!     .unwrap();
+//! assert_eq!(app.dependencies, vec![("serde".to_string(), "^1".to_string())]);
+//! assert_eq!(graph.package("serde").unwrap().version, "1.0.0");
 //! ```
 
-use crate::package::{fetch_package_info, Package};
-use crates_io_api::SyncClient;
+#[cfg(feature = "async")]
+use crate::cache::MemoryCache;
+use crate::error::DepthError;
+#[cfg(feature = "async")]
+use crate::package::prefetch_tree_async;
+use crate::package::{
+    fetch_children, fetch_package_info, format_size, FetchOptions, Package, LARGE_CRATE_SIZE,
+};
+#[cfg(feature = "async")]
+use crate::registry::AsyncClientPool;
+use crate::registry::{
+    edit_distance, resolve_version, ClientOptions, ClientPool, CrateError, CrateRegistry,
+    DependencyKind,
+};
+use crate::text::{display_width, truncate_to_width};
+use log::warn;
+use petgraph::algo::{all_simple_paths, has_path_connecting, tarjan_scc};
 use petgraph::dot::{Config, Dot};
-use petgraph::graph::{DiGraph, NodeIndex};
-use petgraph::visit::Dfs;
-use std::collections::{HashMap, HashSet};
+use petgraph::graph::{DiGraph, EdgeReference, NodeIndex};
+use petgraph::visit::{Dfs, DfsPostOrder, EdgeRef, Reversed};
+use petgraph::Direction::Incoming;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+#[cfg(feature = "async")]
+use std::mem;
+#[cfg(feature = "async")]
+use std::panic;
+#[cfg(feature = "async")]
+use std::sync::Arc;
 
 /// A struct representing a dependency graph.
 #[derive(Debug)]
 pub struct DependencyGraph {
-    /// The underlying directed graph.
-    graph: DiGraph<(String, String), &'static str>,
+    /// The underlying directed graph, with one node per crate labeled with its name and its
+    /// homepage, or its version requirement until it is fetched, and each edge labeled with the
+    /// kind of the dependency.
+    graph: DiGraph<(String, String), DependencyKind>,
+    /// The node of each crate of the graph, keyed by name.
+    nodes: HashMap<String, NodeIndex>,
+    /// Crates that could not be fetched (private or gone), with the reason.
+    unavailable: BTreeMap<String, String>,
+    /// The structured errors of the crates that could not be fetched, keyed by name.
+    errors: BTreeMap<String, CrateError>,
+    /// The packages fetched into the graph, keyed by name.
+    packages: HashMap<String, Package>,
+    /// The names printed in place of the real crate names by the renderers.
+    aliases: HashMap<String, String>,
+    /// Short flags printed next to crates by the renderers, e.g. `owner changed`.
+    markers: HashMap<String, Vec<String>>,
+    /// The links printed next to crates by the renderers.
+    link_style: LinkStyle,
+    /// The colors of the levels of the default tree.
+    color_scheme: ColorScheme,
+    /// The order of the dependencies of each crate in the default tree and the exports.
+    sort_order: SortOrder,
+    /// Whether the default tree lists the features of each crate under it.
+    show_features: bool,
+    /// Whether the default tree leaves out the link of each crate.
+    compact: bool,
+    /// The number of columns the lines of the default tree are fitted to, if limited.
+    max_width: Option<usize>,
+    /// How often packages were looked up while fetching, and how often from the fetched ones.
+    fetch_stats: FetchStats,
+    /// The options of the last fetch into the graph, reused when expanding a node.
+    fetch_options: FetchOptions,
+    /// The root crate left out of the renderers and analyses, see `hide_root`.
+    hidden_root: Option<String>,
+    /// The crates being fetched, from the root to the current one, to detect cycles.
+    fetch_path: Vec<String>,
+    /// Whether a fetch stopped at `FetchOptions::max_nodes`, leaving crates unfetched.
+    truncated: bool,
 }
 
 impl Default for DependencyGraph {
@@ -100,16 +200,66 @@ impl DependencyGraph {
     pub fn new() -> Self {
         DependencyGraph {
             graph: DiGraph::new(),
+            nodes: HashMap::new(),
+            unavailable: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            packages: HashMap::new(),
+            aliases: HashMap::new(),
+            markers: HashMap::new(),
+            link_style: LinkStyle::default(),
+            color_scheme: ColorScheme::default(),
+            sort_order: SortOrder::default(),
+            show_features: false,
+            compact: false,
+            max_width: None,
+            fetch_stats: FetchStats::default(),
+            fetch_options: FetchOptions::default(),
+            hidden_root: None,
+            fetch_path: Vec::new(),
+            truncated: false,
+        }
+    }
+
+    /// Creates a graph of the given crates, each with the names of its dependencies, without
+    /// fetching anything. The dependencies are added with an empty requirement, and do not
+    /// need to be listed as crates themselves.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    ///
+    /// let graph = DependencyGraph::from_edges(&[("app", &["tokio", "serde"]), ("tokio", &["mio"])]);
+    /// assert!(graph.is_reachable("app", "mio"));
+    /// assert_eq!(graph.package("app").unwrap().dependencies[0].0, "tokio");
+    /// assert!(graph.package("mio").is_none());
+    /// ```
+    pub fn from_edges(edges: &[(&str, &[&str])]) -> Self {
+        let mut graph = DependencyGraph::new();
+        for (name, dependencies) in edges {
+            let dependencies = dependencies
+                .iter()
+                .map(|dependency| (dependency.to_string(), "".to_string()))
+                .collect();
+            graph.add_package_to_graph(&Package::new(
+                name.to_string(),
+                "".to_string(),
+                dependencies,
+                false,
+            ));
         }
+        graph
     }
 
-    /// Fetches the dependency tree for a given package.
+    /// Fetches the dependency tree for a given package, with the default options otherwise.
+    /// See `fetch_dependency_tree_with_options` to set any other option.
     ///
     /// # Arguments
     ///
     /// * `package_name` - The name of the package to fetch.
     /// * `depth` - The maximum depth to fetch dependencies.
     /// * `optional` - A boolean to scan optional dependencies only.
+    /// * `user_agent` - The user agent sent to Crates.io, e.g. `DEFAULT_USER_AGENT`.
     ///
     /// # Returns
     ///
@@ -120,21 +270,288 @@ impl DependencyGraph {
         package_name: &str,
         depth: usize,
         optional: bool,
-    ) -> Result<Option<Package>, Box<dyn std::error::Error>> {
-        let mut visited_packages = HashMap::new();
-        let client = SyncClient::new(
-            "my-user-agent (my-contact@domain.com)",
-            std::time::Duration::from_millis(1000),
-        )
-        .unwrap();
-        fetch_package_info(
-            &(package_name.to_string(), "".to_string()),
-            &mut visited_packages,
-            self,
-            &client,
+        user_agent: &str,
+    ) -> Result<Option<Package>, DepthError> {
+        let options = FetchOptions {
+            client: ClientOptions {
+                user_agent: user_agent.to_string(),
+                ..ClientOptions::default()
+            },
+            ..FetchOptions::new(optional)
+        };
+        self.fetch_dependency_tree_with_options(package_name, depth, &options)
+    }
+
+    /// Fetches the dependency tree for a given package using the given fetch options.
+    ///
+    /// # Arguments
+    ///
+    /// * `package_name` - The name of the package to fetch.
+    /// * `depth` - The maximum depth to fetch dependencies.
+    /// * `options` - The options controlling which dependencies are listed and followed.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(package))` if the package is fetched successfully,
+    /// `Ok(None)` if the package does not exist, and `Err` on an error.
+    pub fn fetch_dependency_tree_with_options(
+        &mut self,
+        package_name: &str,
+        depth: usize,
+        options: &FetchOptions,
+    ) -> Result<Option<Package>, DepthError> {
+        let client = ClientPool::new(&options.client, options.jobs)
+            .map_err(|err| DepthError::Client(err.to_string()))?;
+        self.fetch_dependency_tree_with_registry(package_name, depth, options, &client)
+    }
+
+    /// Fetches the dependency tree for a given package using the given fetch options, sending
+    /// the requests of independent crates concurrently on tokio instead of blocking on each one.
+    ///
+    /// The crates of the tree are first fetched by up to `options.jobs` concurrent tasks, still
+    /// starting at most one request per rate limit interval. The graph is then built from the
+    /// fetched metadata on a blocking thread, exactly as `fetch_dependency_tree_with_options`
+    /// builds it, only requesting what the concurrent fetch missed and reporting its failures.
+    /// It must be called within a tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `package_name` - The name of the package to fetch.
+    /// * `depth` - The maximum depth to fetch dependencies.
+    /// * `options` - The options controlling which dependencies are listed and followed.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(package))` if the package is fetched successfully,
+    /// `Ok(None)` if the package does not exist, and `Err` on an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    ///
+    /// let runtime = tokio::runtime::Builder::new_current_thread()
+    ///     .enable_all()
+    ///     .build()
+    ///     .unwrap();
+    /// let options = FetchOptions {
+    ///     jobs: 8,
+    ///     ..FetchOptions::default()
+    /// };
+    /// let mut graph = DependencyGraph::new();
+    /// let tokio = runtime
+    ///     .block_on(graph.fetch_dependency_tree_async("tokio", 3, &options))
+    ///     .unwrap()
+    ///     .expect("tokio is published on crates.io");
+    /// graph.print_dependencies_at_level(&tokio, 0, 3);
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn fetch_dependency_tree_async(
+        &mut self,
+        package_name: &str,
+        depth: usize,
+        options: &FetchOptions,
+    ) -> Result<Option<Package>, DepthError> {
+        let client = AsyncClientPool::new(&options.client, options.jobs)
+            .map_err(|err| DepthError::Client(err.to_string()))?;
+        let options = Arc::new(FetchOptions {
+            cache: Arc::new(MemoryCache::new(options.cache.clone())),
+            ..options.clone()
+        });
+        prefetch_tree_async(package_name, depth, Arc::new(client), options.clone()).await;
+
+        // The blocking client cannot be used on the runtime's threads
+        let mut graph = mem::take(self);
+        let package_name = package_name.to_string();
+        let (graph, fetched) = tokio::task::spawn_blocking(move || {
+            let fetched = graph.fetch_dependency_tree_with_options(&package_name, depth, &options);
+            (graph, fetched)
+        })
+        .await
+        .unwrap_or_else(|err| panic::resume_unwind(err.into_panic()));
+        *self = graph;
+        fetched
+    }
+
+    /// Fetches the dependency tree for a given package from the given crate registry.
+    ///
+    /// # Arguments
+    ///
+    /// * `package_name` - The name of the package to fetch.
+    /// * `depth` - The maximum depth to fetch dependencies.
+    /// * `options` - The options controlling which dependencies are listed and followed.
+    /// * `client` - The registry to fetch the packages from.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(package))` if the package is fetched successfully,
+    /// `Ok(None)` if the package does not exist, and `Err` on an error.
+    pub fn fetch_dependency_tree_with_registry(
+        &mut self,
+        package_name: &str,
+        depth: usize,
+        options: &FetchOptions,
+        client: &impl CrateRegistry,
+    ) -> Result<Option<Package>, DepthError> {
+        let mut roots = self.fetch_dependency_trees_with_registry(
+            &[package_name.to_string()],
             depth,
-            optional,
-        )
+            options,
+            client,
+        )?;
+        Ok(roots.pop().flatten())
+    }
+
+    /// Fetches the dependency trees of several crates into the graph, from the given crate
+    /// registry. The crates shared by several trees are fetched once, see `add_merged_root` to
+    /// render the trees together.
+    ///
+    /// # Arguments
+    ///
+    /// * `package_names` - The names of the packages to fetch.
+    /// * `depth` - The maximum depth to fetch the dependencies of each package.
+    /// * `options` - The options controlling which dependencies are listed and followed.
+    /// * `client` - The registry to fetch the packages from.
+    ///
+    /// # Returns
+    ///
+    /// Returns the fetched packages in the given order, `None` for the packages that do not
+    /// exist, and `Err` on an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry, TimedRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(CrateInfo::new("app", "1.0.0"), vec![DependencyInfo::new("serde", "^1")]);
+    /// registry.add_crate(CrateInfo::new("cli", "1.0.0"), vec![DependencyInfo::new("serde", "^1")]);
+    /// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![]);
+    /// let registry = TimedRegistry::new(registry);
+    ///
+    /// let names = ["app".to_string(), "cli".to_string(), "clli".to_string()];
+    /// let mut graph = DependencyGraph::new();
+    /// let roots = graph
+    ///     .fetch_dependency_trees_with_registry(&names, 2, &FetchOptions::default(), &registry)
+    ///     .unwrap();
+    /// assert_eq!(roots[1].as_ref().unwrap().name, "cli");
+    /// assert!(roots[2].is_none());
+    /// // serde is fetched once, for both trees
+    /// assert_eq!(graph.packages().len(), 3);
+    /// assert_eq!(registry.request_count(), 7);
+    /// ```
+    pub fn fetch_dependency_trees_with_registry(
+        &mut self,
+        package_names: &[String],
+        depth: usize,
+        options: &FetchOptions,
+        client: &impl CrateRegistry,
+    ) -> Result<Vec<Option<Package>>, DepthError> {
+        let mut visited_packages = HashMap::new();
+        let mut roots = Vec::new();
+        for package_name in package_names {
+            // A root already fetched as a dependency of another root is fetched again as a
+            // root, down to the full depth
+            visited_packages.remove(package_name);
+            let result = fetch_package_info(
+                &(package_name.to_string(), "".to_string()),
+                &mut visited_packages,
+                self,
+                client,
+                depth,
+                options,
+            );
+
+            // Missing dependencies are rendered as unavailable, only a missing root is not found
+            roots.push(match result {
+                Err(DepthError::CrateNotFound(name)) if name == *package_name => None,
+                result => result?,
+            });
+        }
+        Ok(roots)
+    }
+
+    /// Fetches one more level below a fetched crate, without refetching the rest of the tree.
+    ///
+    /// The dependencies of the crate that are not in the graph yet are fetched, with the options
+    /// of the last fetch into the graph and so through the same cache, and added below it as
+    /// unexpanded nodes. Crates already in the graph, or known to be unavailable, are not
+    /// fetched again. The expanded dependencies are treated as below the root, so they do not
+    /// list dev-dependencies unless `dev_recursive` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the crate to expand, which must have been fetched.
+    /// * `client` - The registry to fetch the dependencies from.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry, TimedRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(CrateInfo::new("app", "1.0.0"), vec![DependencyInfo::new("hyper", "^1")]);
+    /// registry.add_crate(CrateInfo::new("hyper", "1.0.0"), vec![DependencyInfo::new("tokio", "^1")]);
+    /// registry.add_crate(CrateInfo::new("tokio", "1.0.0"), vec![DependencyInfo::new("mio", "^1")]);
+    /// registry.add_crate(CrateInfo::new("mio", "1.0.0"), vec![]);
+    /// let registry = TimedRegistry::new(registry);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// graph
+    ///     .fetch_dependency_tree_with_registry("app", 2, &FetchOptions::default(), &registry)
+    ///     .unwrap();
+    /// assert!(graph.package("tokio").is_none());
+    ///
+    /// // Fetches `tokio` alone, listing `mio` below it without fetching it
+    /// let requests = registry.request_count();
+    /// graph.expand_node("hyper", &registry).unwrap();
+    /// assert_eq!(graph.package("tokio").unwrap().dependencies[0].0, "mio");
+    /// assert!(graph.package("mio").is_none());
+    /// assert_eq!(registry.request_count(), requests + 2);
+    ///
+    /// // Expanding again has nothing left to fetch
+    /// graph.expand_node("hyper", &registry).unwrap();
+    /// assert_eq!(registry.request_count(), requests + 2);
+    /// assert!(graph.expand_node("serde", &registry).is_err());
+    /// ```
+    pub fn expand_node(
+        &mut self,
+        name: &str,
+        client: &impl CrateRegistry,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let package = self
+            .packages
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("crate '{}' is not in the graph", name))?;
+        self.add_package_to_graph(&package);
+
+        let missing: Vec<(String, String)> = package
+            .dependencies
+            .iter()
+            .filter(|dependency| {
+                !self.packages.contains_key(&dependency.0)
+                    && !self.unavailable.contains_key(&dependency.0)
+            })
+            .cloned()
+            .collect();
+        let options = self.fetch_options.clone();
+        Ok(fetch_children(&missing, self, client, &options)?)
+    }
+
+    /// Remembers the options of a fetch into the graph, for `expand_node`.
+    pub(crate) fn set_fetch_options(&mut self, options: &FetchOptions) {
+        self.fetch_options = options.clone();
+    }
+
+    /// Returns the options of the last fetch into the graph, the default ones if none.
+    pub(crate) fn fetch_options(&self) -> &FetchOptions {
+        &self.fetch_options
     }
 
     /// Adds a package and its dependencies to the graph.
@@ -146,33 +563,443 @@ impl DependencyGraph {
     /// # Returns
     ///
     /// Returns the `NodeIndex` of the added package.
+    ///
+    /// # Notes
+    ///
+    /// A crate has a single node however many crates depend on it, and adding a package again
+    /// adds nothing, so the node and edge counts of the graph are the ones of the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::MemoryRegistry;
+    ///
+    /// // A diamond: both `hyper` and `reqwest` depend on `tokio`
+    /// let registry = MemoryRegistry::from_edges(&[
+    ///     ("app", &["hyper", "reqwest"]),
+    ///     ("hyper", &["tokio"]),
+    ///     ("reqwest", &["hyper", "tokio"]),
+    ///     ("tokio", &[]),
+    /// ]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// let app = graph
+    ///     .fetch_dependency_tree_with_registry("app", 3, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    /// graph.add_package_to_graph(&app);
+    ///
+    /// let dot = graph.to_dot();
+    /// let edges = dot.lines().filter(|line| line.contains(" -> ")).count();
+    /// assert_eq!(dot.lines().count() - edges, 4);
+    /// assert_eq!(edges, 5);
+    /// ```
     pub fn add_package_to_graph(&mut self, package: &Package) -> NodeIndex {
-        let node_index = self
-            .graph
-            .add_node((package.name.clone(), package.url.clone()));
+        self.packages
+            .entry(package.name.clone())
+            .or_insert_with(|| package.clone());
+        let node_index = self.get_or_insert_node(&package.name, &package.url);
+        // Once fetched, a crate is labeled with its homepage instead of a requirement
+        self.graph[node_index].1 = package.url.clone();
 
         for dependency in &package.dependencies {
-            if !self
-                .graph
-                .node_indices()
-                .any(|i| self.graph[i] == *dependency)
-            {
-                let index = self.graph.add_node(dependency.clone());
-                self.add_dependency_edge(node_index, index);
-            }
+            let index = self.get_or_insert_node(&dependency.0, &dependency.1);
+            self.add_dependency_edge(node_index, index);
         }
 
         node_index
     }
 
+    /// Returns the node of a crate, adding it with the given label if the graph has none.
+    fn get_or_insert_node(&mut self, crate_name: &str, label: &str) -> NodeIndex {
+        if let Some(&node_index) = self.nodes.get(crate_name) {
+            return node_index;
+        }
+        let node_index = self
+            .graph
+            .add_node((crate_name.to_string(), label.to_string()));
+        self.nodes.insert(crate_name.to_string(), node_index);
+        node_index
+    }
+
     /// Adds a dependency edge between two packages in the graph.
     ///
     /// # Arguments
     ///
     /// * `source` - The `NodeIndex` of the source package.
     /// * `target` - The `NodeIndex` of the target package.
+    ///
+    /// The edge is labeled with the kind of the dependency in the source package, see
+    /// `Package::dependency_kind`, and normal if the source package is not in the graph. Two
+    /// packages are linked by a single edge, however often it is added.
     pub fn add_dependency_edge(&mut self, source: NodeIndex, target: NodeIndex) {
-        self.graph.add_edge(source, target, "depends");
+        let kind = self
+            .packages
+            .get(&self.graph[source].0)
+            .map_or(DependencyKind::Normal, |package| {
+                package.dependency_kind(&self.graph[target].0)
+            });
+        self.graph.update_edge(source, target, kind);
+    }
+
+    /// Returns the kind a crate is pulled into the graph as, the strongest of the kinds the
+    /// crates depending on it list it as, normal for the root.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, TreeOptions};
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, DependencyKind, MemoryRegistry};
+    ///
+    /// let of_kind = |name: &str, kind| DependencyInfo {
+    ///     kind,
+    ///     ..DependencyInfo::new(name, "^1")
+    /// };
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "1.0.0"),
+    ///     vec![
+    ///         DependencyInfo::new("serde", "^1"),
+    ///         of_kind("cc", DependencyKind::Build),
+    ///         of_kind("insta", DependencyKind::Dev),
+    ///         of_kind("serde", DependencyKind::Dev),
+    ///     ],
+    /// );
+    /// for name in ["serde", "cc", "insta"] {
+    ///     registry.add_crate(CrateInfo::new(name, "1.0.0"), vec![]);
+    /// }
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// let app = graph
+    ///     .fetch_dependency_tree_with_registry("app", 2, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// // `serde` is a dev-dependency too, but built as a normal one
+    /// assert_eq!(graph.dependency_kind("serde"), DependencyKind::Normal);
+    /// assert_eq!(graph.dependency_kind("cc"), DependencyKind::Build);
+    /// assert_eq!(graph.dependency_kind("insta"), DependencyKind::Dev);
+    ///
+    /// let mut output = Vec::new();
+    /// graph.write_cargo_tree(&app, 2, &TreeOptions::default(), &mut output).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(output).unwrap(),
+    ///     "app v1.0.0\n├── cc v1.0.0 [build]\n├── insta v1.0.0 [dev]\n└── serde v1.0.0\n"
+    /// );
+    /// assert!(graph.to_dot().contains("0 -> 2 [ label = \"\\\"depends on\\\"\" style = dashed ]"));
+    /// ```
+    pub fn dependency_kind(&self, crate_name: &str) -> DependencyKind {
+        self.packages
+            .values()
+            .filter(|package| package.dependencies.iter().any(|dep| dep.0 == crate_name))
+            .map(|package| package.dependency_kind(crate_name))
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Writes the kind of a dependency as ` [build]` or ` [dev]`, nothing for a normal one.
+    fn write_kind(kind: DependencyKind, out: &mut dyn Write) -> io::Result<()> {
+        match kind {
+            DependencyKind::Normal => Ok(()),
+            kind => write!(out, " [{}]", kind.label()),
+        }
+    }
+
+    /// Records a crate that could not be fetched, so it is rendered as unavailable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the unavailable crate.
+    /// * `reason` - Why the crate could not be fetched.
+    pub fn mark_unavailable(&mut self, name: &str, reason: String) {
+        self.unavailable.insert(name.to_string(), reason);
+    }
+
+    /// Records a crate that could not be resolved, so it is rendered as unavailable and listed
+    /// by `errors`.
+    pub fn record_error(&mut self, error: CrateError) {
+        self.mark_unavailable(&error.name, error.message.clone());
+        self.errors.insert(error.name.clone(), error);
+    }
+
+    /// Returns the crates that could not be resolved, sorted by name, with a structured reason.
+    pub fn errors(&self) -> Vec<&CrateError> {
+        self.errors.values().collect()
+    }
+
+    /// Returns the crates that could not be fetched, sorted by name, with the reason.
+    pub fn unavailable(&self) -> &BTreeMap<String, String> {
+        &self.unavailable
+    }
+
+    /// Leaves the given root crate out of the renderers and analyses, so they only cover its
+    /// dependencies, e.g. when merging the trees of several crates.
+    ///
+    /// The root is still printed as the header of the tree formats, but not as a node, and is
+    /// neither counted nor listed by `packages`, so hide it only once the lock is saved.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::Package;
+    ///
+    /// let dependencies = vec![("serde".to_string(), "".to_string())];
+    /// let root = Package::new("app".to_string(), "".to_string(), dependencies, false);
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&root);
+    /// graph.add_package_to_graph(&Package::new("serde".to_string(), "".to_string(), vec![], false));
+    ///
+    /// graph.hide_root("app");
+    /// let names: Vec<&str> = graph.packages().iter().map(|p| p.name.as_str()).collect();
+    /// assert_eq!(names, vec!["serde"]);
+    /// assert!(!graph.to_dot().contains("app"));
+    ///
+    /// let mut tree = Vec::new();
+    /// graph.write_dependencies_at_level(&root, 0, 3, &mut tree).unwrap();
+    /// assert_eq!(String::from_utf8(tree).unwrap(), "\x1b[32m ├── serde - ()\x1b[0m\n");
+    /// ```
+    pub fn hide_root(&mut self, root_name: &str) {
+        self.hidden_root = Some(root_name.to_string());
+    }
+
+    /// Adds a root depending on the given crates and hides it, see `hide_root`, so that the
+    /// trees of the crates are rendered together, each crate shared by several trees expanded
+    /// once. The root is named after the crates, e.g. `app, cli`, which is no valid crate name.
+    ///
+    /// The root adds a level to the tree, so the crates are rendered one level deeper than the
+    /// depth they were fetched at.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{ColorScheme, DependencyGraph};
+    /// use depth::package::Package;
+    ///
+    /// let serde = || vec![("serde".to_string(), "^1".to_string())];
+    /// let app = Package::new("app".to_string(), "".to_string(), serde(), false);
+    /// let cli = Package::new("cli".to_string(), "".to_string(), serde(), false);
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&app);
+    /// graph.add_package_to_graph(&cli);
+    /// graph.set_color_scheme(ColorScheme::None);
+    ///
+    /// let root = graph.add_merged_root(&[app, cli]);
+    /// assert_eq!(root.name, "app, cli");
+    /// let mut tree = Vec::new();
+    /// graph.write_dependencies_at_level(&root, 0, 3, &mut tree).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(tree).unwrap(),
+    ///     " ├── app - ()\n    ├── serde - (^1)\n ├── cli - ()\n    ├── serde - (^1)\n"
+    /// );
+    /// ```
+    pub fn add_merged_root(&mut self, roots: &[Package]) -> Package {
+        let names: Vec<&str> = roots.iter().map(|root| root.name.as_str()).collect();
+        let dependencies = roots
+            .iter()
+            .map(|root| (root.name.clone(), root.version.clone()))
+            .collect();
+        let root = Package::new(names.join(", "), "".to_string(), dependencies, false);
+        self.add_package_to_graph(&root);
+        self.hide_root(&root.name);
+        root
+    }
+
+    /// Returns `true` if the given crate is the root left out by `hide_root`.
+    pub fn is_hidden_root(&self, crate_name: &str) -> bool {
+        self.hidden_root.as_deref() == Some(crate_name)
+    }
+
+    /// Returns the fetched package with the given name, if any.
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        self.packages.get(name)
+    }
+
+    /// Returns every package fetched into the graph, sorted by name, without the hidden root.
+    pub fn packages(&self) -> Vec<&Package> {
+        let mut packages: Vec<&Package> = self
+            .packages
+            .values()
+            .filter(|package| !self.is_hidden_root(&package.name))
+            .collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        packages
+    }
+
+    /// Returns the name and the resolved version of every crate fetched into the graph, the
+    /// root included, sorted by name. Each crate is listed once, however many crates depend on
+    /// it; crates that were not fetched, e.g. pruned or unavailable ones, have no resolved version
+    /// and are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::build_dependency_graph_with_registry;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "0.3.0"),
+    ///     vec![DependencyInfo::new("serde", "^1"), DependencyInfo::new("itoa", "^1")],
+    /// );
+    /// registry.add_crate(CrateInfo::new("serde", "1.0.192"), vec![DependencyInfo::new("itoa", "^1")]);
+    /// registry.add_crate(CrateInfo::new("itoa", "1.0.9"), vec![]);
+    ///
+    /// let graph =
+    ///     build_dependency_graph_with_registry("app", 3, &FetchOptions::default(), &registry)
+    ///         .unwrap();
+    /// assert_eq!(
+    ///     graph.unique_crates(),
+    ///     vec![
+    ///         ("app".to_string(), "0.3.0".to_string()),
+    ///         ("itoa".to_string(), "1.0.9".to_string()),
+    ///         ("serde".to_string(), "1.0.192".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn unique_crates(&self) -> Vec<(String, String)> {
+        self.packages()
+            .into_iter()
+            .map(|package| (package.name.clone(), package.version.clone()))
+            .collect()
+    }
+
+    /// Counts the unique direct and transitive dependencies of a package.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The root package to count dependencies for.
+    /// * `max_depth` - The maximum depth to count, the root being at depth 0.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the number of direct dependencies and the number of other unique crates
+    /// reachable from the package within `max_depth`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "1.0.0"),
+    ///     vec![DependencyInfo::new("a", "^1"), DependencyInfo::new("b", "^1")],
+    /// );
+    /// registry.add_crate(CrateInfo::new("a", "1.0.0"), vec![DependencyInfo::new("c", "^1")]);
+    /// registry.add_crate(CrateInfo::new("b", "1.0.0"), vec![DependencyInfo::new("c", "^1")]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// let root = graph
+    ///     .fetch_dependency_tree_with_registry("app", 3, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(graph.dependency_counts(&root, 3), (2, 1));
+    /// ```
+    pub fn dependency_counts(&self, package: &Package, max_depth: usize) -> (usize, usize) {
+        let direct: HashSet<&str> = package
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.0.as_str())
+            .collect();
+
+        let mut seen = HashSet::from([package.name.as_str()]);
+        let mut level = vec![package];
+        let mut depth = 0;
+        // Breadth-first, so every crate counts at the shallowest depth it appears
+        while depth + 1 < max_depth && !level.is_empty() {
+            let mut next_level = Vec::new();
+            for current in level {
+                for dependency in &current.dependencies {
+                    if seen.insert(dependency.0.as_str()) {
+                        if let Some(child) = self.packages.get(&dependency.0) {
+                            next_level.push(child);
+                        }
+                    }
+                }
+            }
+            level = next_level;
+            depth += 1;
+        }
+
+        // `seen` holds the root, the direct dependencies and then the transitive ones
+        let transitive = seen.len().saturating_sub(1 + direct.len());
+        (direct.len(), transitive)
+    }
+
+    /// Returns the leaves of the tree of a package: the crates without dependencies of their
+    /// own within the fetched depth, which are the ends of the dependency chains.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The root package of the tree.
+    /// * `max_depth` - The depth up to which dependencies were fetched, the root being at depth 0.
+    ///
+    /// # Returns
+    ///
+    /// The names of the leaf crates, sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "1.0.0"),
+    ///     vec![DependencyInfo::new("a", "^1"), DependencyInfo::new("b", "^1")],
+    /// );
+    /// registry.add_crate(CrateInfo::new("a", "1.0.0"), vec![DependencyInfo::new("c", "^1")]);
+    /// registry.add_crate(CrateInfo::new("b", "1.0.0"), vec![]);
+    /// registry.add_crate(CrateInfo::new("c", "1.0.0"), vec![DependencyInfo::new("d", "^1")]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// let root = graph
+    ///     .fetch_dependency_tree_with_registry("app", 3, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// // `c` has dependencies, but they are deeper than the fetched depth
+    /// assert_eq!(graph.leaves(&root, 3), vec!["b", "c"]);
+    /// ```
+    pub fn leaves(&self, package: &Package, max_depth: usize) -> Vec<String> {
+        let mut seen = HashSet::from([package.name.as_str()]);
+        let mut leaves = BTreeSet::new();
+        let mut level = vec![package];
+        let mut depth = 0;
+        // Breadth-first, so every crate is judged at the shallowest depth it appears
+        while depth + 1 < max_depth && !level.is_empty() {
+            let mut next_level = Vec::new();
+            for current in level {
+                for dependency in &current.dependencies {
+                    if !seen.insert(dependency.0.as_str()) {
+                        continue;
+                    }
+                    match self.packages.get(&dependency.0) {
+                        Some(child) if depth + 2 < max_depth && !child.dependencies.is_empty() => {
+                            next_level.push(child)
+                        }
+                        // Pruned, unavailable and last level crates are leaves too
+                        _ => {
+                            leaves.insert(dependency.0.clone());
+                        }
+                    }
+                }
+            }
+            level = next_level;
+            depth += 1;
+        }
+
+        leaves.into_iter().collect()
     }
 
     /// Prints the dependencies of a package up to a specified level.
@@ -184,14 +1011,82 @@ impl DependencyGraph {
     /// * `max_depth` - The maximum depth to print dependencies.
     pub fn print_dependencies_at_level(&self, package: &Package, depth: usize, max_depth: usize) {
         let mut visited_nodes = HashSet::new();
-        let mut printed_packages = HashSet::new();
-        self.print_dependencies_recursive(
+        self.print_dependencies_recursive(package, depth, max_depth, &mut visited_nodes);
+    }
+
+    /// Writes the dependencies of a package up to a specified level to the given writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The package to write dependencies for.
+    /// * `depth` - The current depth in the dependency tree.
+    /// * `max_depth` - The maximum depth to write dependencies.
+    /// * `out` - The writer the tree is written to.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` indicating whether writing succeeded.
+    ///
+    /// # Examples
+    ///
+    /// A crate shared by several dependents is expanded below the first one only. Below the
+    /// others, it is suffixed with `(*)` when that hides its dependencies, like `cargo tree`:
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{ColorScheme, DependencyGraph};
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// // app depends on a and b, which both depend on c
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "1.0.0"),
+    ///     vec![DependencyInfo::new("a", "^1"), DependencyInfo::new("b", "^1")],
+    /// );
+    /// registry.add_crate(CrateInfo::new("a", "1.0.0"), vec![DependencyInfo::new("c", "^1")]);
+    /// registry.add_crate(CrateInfo::new("b", "1.0.0"), vec![DependencyInfo::new("c", "^1")]);
+    /// registry.add_crate(CrateInfo::new("c", "1.0.0"), vec![DependencyInfo::new("d", "^1")]);
+    /// registry.add_crate(CrateInfo::new("d", "1.0.0"), vec![]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// graph.set_color_scheme(ColorScheme::None);
+    /// let root = graph
+    ///     .fetch_dependency_tree_with_registry("app", 4, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    /// let tree = |max_depth| {
+    ///     let mut tree = Vec::new();
+    ///     graph.write_dependencies_at_level(&root, 0, max_depth, &mut tree).unwrap();
+    ///     String::from_utf8(tree).unwrap()
+    /// };
+    ///
+    /// assert_eq!(
+    ///     tree(4),
+    ///     " ├── app - ()\n    ├── a - ()\n       ├── c - ()\n          ├── d - ()\n    \
+    ///      ├── b - ()\n       ├── c - () (*)\n"
+    /// );
+    /// // Without its dependencies in the tree, c hides nothing below b
+    /// assert_eq!(
+    ///     tree(3),
+    ///     " ├── app - ()\n    ├── a - ()\n       ├── c - ()\n    ├── b - ()\n       ├── c - ()\n"
+    /// );
+    /// ```
+    pub fn write_dependencies_at_level(
+        &self,
+        package: &Package,
+        depth: usize,
+        max_depth: usize,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut visited_nodes = HashSet::new();
+        self.write_dependencies_recursive(
             package,
             depth,
             max_depth,
             &mut visited_nodes,
-            &mut printed_packages,
-        );
+            &self.subtree_sizes_for(self.sort_order),
+            out,
+        )
     }
 
     /// Recursively prints the dependencies of a given package in a tree-like structure,
@@ -203,79 +1098,2750 @@ impl DependencyGraph {
     /// - `package`: A reference to the Package for which dependencies are printed.
     /// - `depth`: The current depth in the recursion. Used for indentation and color-coding.
     /// - `max_depth`: The maximum depth to explore in the dependency tree. Set to 0 for unlimited depth.
-    /// - `visited_nodes`: A HashSet to keep track of visited nodes to avoid duplicates in the output.
-    /// - `printed_packages`: A HashSet to keep track of printed packages to avoid redundant output.
+    /// - `visited_nodes`: A HashSet of the nodes already expanded, which are not expanded again.
     ///
     /// # Notes
     ///
+    /// A crate is listed below every crate depending on it, but only expanded below the first.
+    /// Its later occurrences are suffixed with `(*)` when that hides dependencies, like in
+    /// `cargo tree`.
+    ///
     /// The function uses a Depth-First Search (DFS) traversal to explore the dependency graph.
     /// The DFS algorithm is chosen for its simplicity and suitability for exploring tree-like structures.
-    /// The ANSI escape codes are used for color-coding the output based on the depth.
-    ///
-    /// - Green (32) is used for even depths.
-    /// - White (37) is used for odd depths.
+    /// The ANSI escape codes are used for color-coding the output based on the depth, see
+    /// `ColorScheme::color_code` and `set_color_scheme`.
     pub fn print_dependencies_recursive(
         &self,
         package: &Package,
         depth: usize,
         max_depth: usize,
         visited_nodes: &mut HashSet<NodeIndex>,
-        printed_packages: &mut HashSet<String>,
     ) {
-        if depth < max_depth {
-            let node_index = self
-                .graph
-                .node_indices()
-                .find(|&index| self.graph[index] == (package.name.clone(), package.url.clone()))
-                .unwrap_or_else(NodeIndex::end);
-
-            if node_index != NodeIndex::end() && visited_nodes.insert(node_index) {
-                let package_key = &package.name;
-                if printed_packages.insert(package_key.clone()) || max_depth > 2 {
-                    // ANSI escape code based on depth
-                    // Green or white
-                    let color_code = if depth % 2 == 0 { 32 } else { 37 };
-
-                    println!(
-                        "{:indent$}\x1b[{}m ├── {} - ({})\x1b[0m",
-                        "",
-                        color_code,
-                        package.name,
-                        package.url,
-                        indent = depth * 3
-                    );
-
-                    let mut dfs = Dfs::new(&self.graph, node_index);
-                    // dfs traversal
-                    while let Some(neighbor_index) = dfs.next(&self.graph) {
-                        let neighbor_package = Package::new(
-                            self.graph[neighbor_index].clone().0,
-                            self.graph[neighbor_index].clone().1,
-                            vec![("".to_string(), "".to_string())],
-                            false,
-                        );
-                        self.print_dependencies_recursive(
-                            &neighbor_package,
-                            depth + 1,
-                            max_depth,
-                            visited_nodes,
-                            printed_packages,
-                        );
-                    }
-                }
-            }
-        }
+        // Printing to stdout only fails if stdout is closed, e.g. a broken pipe
+        let _ = self.write_dependencies_recursive(
+            package,
+            depth,
+            max_depth,
+            visited_nodes,
+            &self.subtree_sizes_for(self.sort_order),
+            &mut io::stdout().lock(),
+        );
     }
 
-    /// Generates a DOT format representation of the graph.
-    ///
-    /// # Returns
-    ///
-    /// Returns a `String` containing the DOT format representation.
-    pub fn to_dot(&self) -> String {
-        format!(
-            "{:?}",
-            Dot::with_config(&self.graph, &[Config::GraphContentOnly])
-        )
+    /// Recursively writes the dependencies of a given package, see `print_dependencies_recursive`.
+    /// The subtree sizes are only needed to sort by size.
+    fn write_dependencies_recursive(
+        &self,
+        package: &Package,
+        depth: usize,
+        max_depth: usize,
+        visited_nodes: &mut HashSet<NodeIndex>,
+        subtree_sizes: &HashMap<String, usize>,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        if depth >= max_depth {
+            return Ok(());
+        }
+        let Some(&node_index) = self.nodes.get(&package.name) else {
+            return Ok(());
+        };
+        // A crate is listed below every crate depending on it, but only expanded below the first
+        let expand = visited_nodes.insert(node_index);
+
+        // A hidden root is not written, its dependencies take its place one level up
+        let shown_depth = if self.hidden_root.is_some() {
+            depth.saturating_sub(1)
+        } else {
+            depth
+        };
+        let color_code = self.color_scheme.color_code(shown_depth);
+
+        if !self.is_hidden_root(&package.name) {
+            let mut suffix = Vec::new();
+            self.write_markers(&package.name, &mut suffix)?;
+            if depth > 0 {
+                Self::write_kind(self.dependency_kind(&package.name), &mut suffix)?;
+            }
+            // Like cargo, only mark the crates whose dependencies are left out here
+            let hides_dependencies =
+                depth + 1 < max_depth && self.graph.neighbors(node_index).next().is_some();
+            if !expand && hides_dependencies {
+                write!(suffix, " (*)")?;
+            }
+            let indent = shown_depth * 3;
+            let line = self.tree_line(package, &String::from_utf8_lossy(&suffix), indent);
+            write!(out, "{:indent$}", "")?;
+            // The line is fitted to the width before being colored, so no escape code is cut
+            match color_code {
+                Some(color_code) => writeln!(out, "\x1b[{}m{}\x1b[0m", color_code, line)?,
+                None => writeln!(out, "{}", line)?,
+            }
+            if expand && self.show_features {
+                let indent = (shown_depth + 1) * 3;
+                for (feature, enabled) in self.features(&package.name) {
+                    let line = format!(" ◦ {}", Self::feature_line(feature, enabled));
+                    writeln!(out, "{:indent$}{}", "", self.fit_to_width(&line, indent))?;
+                }
+            }
+        }
+
+        if expand {
+            // Neighbors come from the most recently added edge, reversed they are in insertion order
+            let mut children: Vec<(&str, NodeIndex)> = self
+                .graph
+                .neighbors(node_index)
+                .map(|child| (self.graph[child].0.as_str(), child))
+                .collect();
+            children.reverse();
+            self.sort_dependencies(&mut children, self.sort_order, subtree_sizes, |child| {
+                (child.0, "")
+            });
+            for (_, child) in children {
+                let (name, url) = &self.graph[child];
+                let child_package = Package::new(name.clone(), url.clone(), vec![], false);
+                self.write_dependencies_recursive(
+                    &child_package,
+                    depth + 1,
+                    max_depth,
+                    visited_nodes,
+                    subtree_sizes,
+                    out,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lays out the line of a crate in the default tree after its indentation: ` ├── name -
+    /// (link)` followed by the markers and the kind of the crate, without the link in compact
+    /// mode unless the crate is unavailable. A line wider than the maximum width has its link
+    /// shortened first, then is cut as a whole, each ending with `…`.
+    fn tree_line(&self, package: &Package, suffix: &str, indent: usize) -> String {
+        /// The fewest columns a shortened link keeps, below which the whole line is cut.
+        const MIN_LINK_WIDTH: usize = 12;
+
+        let name = self.display_name(&package.name);
+        let unavailable = self.unavailable.contains_key(&package.name);
+        let url = if unavailable {
+            Cow::Borrowed("unavailable")
+        } else {
+            self.link(&package.name, &package.url)
+        };
+        let layout = |link: Option<&str>| match link {
+            Some(link) => format!(" ├── {} - ({}){}", name, link, suffix),
+            None => format!(" ├── {}{}", name, suffix),
+        };
+        let link = (!self.compact || unavailable).then_some(url.as_ref());
+        let line = layout(link);
+
+        let width = match self.max_width {
+            Some(max_width) if indent + display_width(&line) > max_width => {
+                max_width.saturating_sub(indent)
+            }
+            _ => return line,
+        };
+        if let Some(link) = link {
+            let fixed = display_width(&layout(Some("")));
+            if width >= fixed + MIN_LINK_WIDTH {
+                return layout(Some(&truncate_to_width(link, width - fixed)));
+            }
+        }
+        truncate_to_width(&line, width)
+    }
+
+    /// Cuts a line of the default tree written after the given indentation to the maximum
+    /// width, if any.
+    fn fit_to_width<'a>(&self, line: &'a str, indent: usize) -> Cow<'a, str> {
+        match self.max_width {
+            Some(max_width) => {
+                Cow::Owned(truncate_to_width(line, max_width.saturating_sub(indent)))
+            }
+            None => Cow::Borrowed(line),
+        }
+    }
+
+    /// Writes the dependency tree of a package in the text layout of `cargo tree`.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The root package of the tree.
+    /// * `max_depth` - The maximum depth to write, the root being at depth 0.
+    /// * `options` - The options controlling how the tree is laid out.
+    /// * `out` - The writer the tree is written to.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` indicating whether writing succeeded.
+    ///
+    /// # Notes
+    ///
+    /// Dependencies are sorted by name and joined with `├──`/`└──` connectors, like `cargo tree`.
+    /// A crate is expanded the first time it is printed; later occurrences are suffixed with `(*)`
+    /// and not expanded again, unless the crate has no dependencies. Crates that were never
+    /// fetched (pruned or beyond the fetched depth) show their version requirement instead of a
+    /// resolved `v` version.
+    ///
+    /// With `collapse_chains` set, a crate that has exactly one dependency is printed on the same
+    /// line as that dependency, e.g. `a v1.0.0 → b v1.0.0 → c v1.0.0`, and the children of the
+    /// last crate of the chain are printed below the line.
+    ///
+    /// With `show_size` set, each fetched crate is followed by the size of its published tarball,
+    /// e.g. `serde v1.0.0 (75.4 KiB)`, and crates larger than `LARGE_CRATE_SIZE` are flagged with
+    /// `large`.
+    ///
+    /// # Examples
+    ///
+    /// The tree below mirrors the data behind `cargo tree -e normal` for `globset` and
+    /// produces the exact same output:
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, SortOrder, TreeOptions};
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// let mut add = |name: &str, version: &str, deps: &[&str]| {
+    ///     let deps = deps.iter().map(|dep| DependencyInfo::new(dep, "*")).collect();
+    ///     registry.add_crate(CrateInfo::new(name, version), deps);
+    /// };
+    /// add("globset", "0.4.20", &["regex-syntax", "log", "bstr", "aho-corasick", "regex-automata"]);
+    /// add("aho-corasick", "1.1.5", &["memchr"]);
+    /// add("bstr", "1.13.1", &["memchr"]);
+    /// add("log", "0.4.34", &[]);
+    /// add("memchr", "2.8.3", &[]);
+    /// add("regex-automata", "0.4.18", &["aho-corasick", "memchr", "regex-syntax"]);
+    /// add("regex-syntax", "0.8.11", &[]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// let root = graph
+    ///     .fetch_dependency_tree_with_registry("globset", 4, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let mut output = Vec::new();
+    /// graph.write_cargo_tree(&root, 4, &TreeOptions::default(), &mut output).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(output).unwrap(),
+    ///     "\
+    /// globset v0.4.20
+    /// ├── aho-corasick v1.1.5
+    /// │   └── memchr v2.8.3
+    /// ├── bstr v1.13.1
+    /// │   └── memchr v2.8.3
+    /// ├── log v0.4.34
+    /// ├── regex-automata v0.4.18
+    /// │   ├── aho-corasick v1.1.5 (*)
+    /// │   ├── memchr v2.8.3
+    /// │   └── regex-syntax v0.8.11
+    /// └── regex-syntax v0.8.11
+    /// "
+    /// );
+    ///
+    /// let options = TreeOptions {
+    ///     collapse_chains: true,
+    ///     ..TreeOptions::default()
+    /// };
+    /// let mut output = Vec::new();
+    /// graph.write_cargo_tree(&root, 4, &options, &mut output).unwrap();
+    ///
+    /// assert!(String::from_utf8(output)
+    ///     .unwrap()
+    ///     .contains("├── aho-corasick v1.1.5 → memchr v2.8.3\n"));
+    ///
+    /// // The heaviest dependency, regex-automata, comes first
+    /// let options = TreeOptions {
+    ///     sort: SortOrder::Size,
+    ///     ..TreeOptions::default()
+    /// };
+    /// let mut output = Vec::new();
+    /// graph.write_cargo_tree(&root, 2, &options, &mut output).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(output).unwrap().lines().nth(1),
+    ///     Some("├── regex-automata v0.4.18")
+    /// );
+    ///
+    /// let mut sized_root = root.clone();
+    /// sized_root.size = Some(3 * 1024 * 1024);
+    /// let options = TreeOptions {
+    ///     show_size: true,
+    ///     ..TreeOptions::default()
+    /// };
+    /// let mut output = Vec::new();
+    /// graph.write_cargo_tree(&sized_root, 1, &options, &mut output).unwrap();
+    ///
+    /// assert_eq!(String::from_utf8(output).unwrap(), "globset v0.4.20 (3.0 MiB, large)\n");
+    /// ```
+    pub fn write_cargo_tree(
+        &self,
+        package: &Package,
+        max_depth: usize,
+        options: &TreeOptions,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut writer = CargoTreeWriter {
+            graph: self,
+            max_depth,
+            options,
+            subtree_sizes: self.subtree_sizes_for(options.sort),
+            visited_packages: HashSet::from([package.name.clone()]),
+            levels_continue: Vec::new(),
+        };
+        if self.is_hidden_root(&package.name) {
+            return writer.write_children(package, 1, out);
+        }
+
+        write!(
+            out,
+            "{} v{}",
+            self.display_name(&package.name),
+            package.version
+        )?;
+        writer.write_size(package, out)?;
+        self.write_markers(&package.name, out)?;
+        let (package, depth) = writer.write_chain(package, 0, out)?;
+        writeln!(out)?;
+        writer.write_features(package, depth, out)?;
+
+        writer.write_children(package, depth + 1, out)
+    }
+
+    /// Writes the inverted tree of a crate, as built by `fetch_reverse_dependencies`: the
+    /// crates depending on it below it, with their requirement on their parent.
+    ///
+    /// A dependent already written further up is marked `(*)` and not expanded again.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The crate at the root of the inverted tree.
+    /// * `max_depth` - The number of levels to write, the crate itself included.
+    /// * `out` - The writer the tree is written to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::fetch_reverse_dependencies;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// let mut add = |name: &str, deps: &[(&str, &str)]| {
+    ///     let deps = deps.iter().map(|(dep, req)| DependencyInfo::new(dep, req)).collect();
+    ///     registry.add_crate(CrateInfo::new(name, "1.0.0"), deps);
+    /// };
+    /// add("libc", &[]);
+    /// add("mio", &[("libc", "^0.2.149")]);
+    /// add("tokio", &[("libc", "^0.2"), ("mio", "^0.8")]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// let libc = fetch_reverse_dependencies("libc", &mut graph, &registry, 3)
+    ///     .unwrap()
+    ///     .unwrap();
+    /// let mut output = Vec::new();
+    /// graph.write_dependents(&libc, 3, &mut output).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(output).unwrap(),
+    ///     "libc v1.0.0\n\
+    ///      ├── mio v1.0.0 (^0.2.149)\n\
+    ///      │   └── tokio v1.0.0 (^0.8)\n\
+    ///      └── tokio v1.0.0 (^0.2) (*)\n"
+    /// );
+    /// ```
+    pub fn write_dependents(
+        &self,
+        package: &Package,
+        max_depth: usize,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        writeln!(
+            out,
+            "{} v{}",
+            self.display_name(&package.name),
+            package.version
+        )?;
+        let (graph, nodes) = self.crate_graph();
+        let Some(&root) = nodes.get(package.name.as_str()) else {
+            return Ok(());
+        };
+
+        let dependents_of = |node: NodeIndex| {
+            let mut dependents: Vec<NodeIndex> = graph.neighbors_directed(node, Incoming).collect();
+            dependents.sort_by_key(|&dependent| graph[dependent]);
+            dependents
+        };
+        // Each entry is a crate to write, with its level, its parent, the prefix of its line and
+        // whether it is the last child of its parent
+        let mut stack: Vec<(NodeIndex, usize, NodeIndex, String, bool)> = Vec::new();
+        let push_children = |stack: &mut Vec<_>, node: NodeIndex, level: usize, prefix: String| {
+            let dependents = dependents_of(node);
+            for (index, &dependent) in dependents.iter().enumerate().rev() {
+                let last = index + 1 == dependents.len();
+                stack.push((dependent, level, node, prefix.clone(), last));
+            }
+        };
+        let mut written = HashSet::from([root]);
+        if max_depth > 1 {
+            push_children(&mut stack, root, 1, String::new());
+        }
+
+        while let Some((node, level, parent, prefix, last)) = stack.pop() {
+            let name = graph[node];
+            let version = self
+                .package(name)
+                .map_or("", |package| package.version.as_str());
+            let req = self
+                .package(name)
+                .and_then(|package| {
+                    package
+                        .dependencies
+                        .iter()
+                        .find(|dependency| dependency.0 == graph[parent])
+                })
+                .map_or("", |dependency| dependency.1.as_str());
+            let expand = written.insert(node);
+            writeln!(
+                out,
+                "{}{}{} v{} ({}){}",
+                prefix,
+                if last { "└── " } else { "├── " },
+                self.display_name(name),
+                version,
+                req,
+                if expand { "" } else { " (*)" }
+            )?;
+            if expand && level + 1 < max_depth {
+                let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                push_children(&mut stack, node, level + 1, prefix);
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the direct dependencies that are each solely responsible for pulling a crate into
+    /// the tree: without the given direct dependency, the crate would no longer be reachable.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The root package of the tree.
+    /// * `crate_name` - The name of the crate to blame.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the crate is not in the tree, otherwise the responsible direct dependencies
+    /// sorted by name, which is empty when the crate is pulled in by several direct dependencies.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::MemoryRegistry;
+    ///
+    /// let registry = MemoryRegistry::from_edges(&[
+    ///     ("app", &["clap", "serde", "tokio"]),
+    ///     ("clap", &["syn"]),
+    ///     ("serde", &["syn"]),
+    ///     ("tokio", &["mio"]),
+    /// ]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// let root = graph
+    ///     .fetch_dependency_tree_with_registry("app", 3, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(graph.blame(&root, "mio"), Some(vec!["tokio".to_string()]));
+    /// assert_eq!(graph.blame(&root, "syn"), Some(vec![]));
+    /// assert_eq!(graph.blame(&root, "rand"), None);
+    /// ```
+    pub fn blame(&self, package: &Package, crate_name: &str) -> Option<Vec<String>> {
+        if !self.reachable_from(package, None).contains(crate_name) {
+            return None;
+        }
+
+        let mut direct: Vec<&str> = package
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.0.as_str())
+            .collect();
+        direct.sort();
+        direct.dedup();
+
+        Some(
+            direct
+                .into_iter()
+                .filter(|dependency| {
+                    !self
+                        .reachable_from(package, Some(dependency))
+                        .contains(crate_name)
+                })
+                .map(str::to_string)
+                .collect(),
+        )
+    }
+
+    /// Collects the names of the crates reachable from a package, optionally ignoring one of
+    /// its direct dependencies.
+    fn reachable_from<'a>(
+        &'a self,
+        package: &'a Package,
+        without_direct: Option<&str>,
+    ) -> HashSet<&'a str> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![package];
+        while let Some(current) = stack.pop() {
+            for dependency in &current.dependencies {
+                let skipped =
+                    current.name == package.name && Some(dependency.0.as_str()) == without_direct;
+                if !skipped && seen.insert(dependency.0.as_str()) {
+                    if let Some(child) = self.packages.get(&dependency.0) {
+                        stack.push(child);
+                    }
+                }
+            }
+        }
+        seen
+    }
+
+    /// Sets the display aliases of crates, keyed by real crate name.
+    ///
+    /// Aliases only change the labels printed by the tree and DOT renderers; crates are still
+    /// fetched, looked up and reported by their real names.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, TreeOptions};
+    /// use depth::package::Package;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut root = Package::new(
+    ///     "app".to_string(),
+    ///     "".to_string(),
+    ///     vec![("proc-macro2".to_string(), "^1".to_string())],
+    ///     false,
+    /// );
+    /// root.version = "1.0.0".to_string();
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&root);
+    /// graph.set_aliases(HashMap::from([("proc-macro2".to_string(), "pm2".to_string())]));
+    ///
+    /// let mut output = Vec::new();
+    /// graph.write_cargo_tree(&root, 2, &TreeOptions::default(), &mut output).unwrap();
+    /// assert_eq!(String::from_utf8(output).unwrap(), "app v1.0.0\n└── pm2 ^1\n");
+    /// ```
+    pub fn set_aliases(&mut self, aliases: HashMap<String, String>) {
+        self.aliases = aliases;
+    }
+
+    /// Returns the name a renderer prints for a crate: its alias if it has one, else its name.
+    pub fn display_name<'a>(&'a self, crate_name: &'a str) -> &'a str {
+        self.aliases
+            .get(crate_name)
+            .map_or(crate_name, String::as_str)
+    }
+
+    /// Records a lookup of a package while fetching, `cached` if it had already been fetched.
+    pub(crate) fn record_lookup(&mut self, cached: bool) {
+        self.fetch_stats.lookups += 1;
+        if cached {
+            self.fetch_stats.cache_hits += 1;
+        }
+    }
+
+    /// Enters a crate whose dependencies are about to be fetched, see `leave_fetch_path`.
+    pub(crate) fn enter_fetch_path(&mut self, crate_name: &str) {
+        self.fetch_path.push(crate_name.to_string());
+    }
+
+    /// Leaves the crate entered last, once its dependencies are fetched.
+    pub(crate) fn leave_fetch_path(&mut self) {
+        self.fetch_path.pop();
+    }
+
+    /// Returns the cycle closed by depending on the given crate, e.g. `a -> b -> a`, if it is
+    /// being fetched.
+    pub(crate) fn fetch_cycle(&self, crate_name: &str) -> Option<String> {
+        let start = self.fetch_path.iter().position(|name| name == crate_name)?;
+        Some(format!(
+            "{} -> {}",
+            self.fetch_path[start..].join(" -> "),
+            crate_name
+        ))
+    }
+
+    /// Returns the number of crates in the graph, fetched or not, one node per crate.
+    pub fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    /// Returns `true` if a fetch stopped at `FetchOptions::max_nodes`, the crates it left
+    /// unfetched being marked `truncated`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "1.0.0"),
+    ///     vec![DependencyInfo::new("serde", "^1"), DependencyInfo::new("tokio", "^1")],
+    /// );
+    /// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![DependencyInfo::new("serde_derive", "^1")]);
+    /// registry.add_crate(CrateInfo::new("serde_derive", "1.0.0"), vec![]);
+    /// registry.add_crate(CrateInfo::new("tokio", "1.0.0"), vec![]);
+    ///
+    /// let options = FetchOptions {
+    ///     max_nodes: 4,
+    ///     ..FetchOptions::default()
+    /// };
+    /// let mut graph = DependencyGraph::new();
+    /// graph.fetch_dependency_tree_with_registry("app", 3, &options, &registry).unwrap();
+    ///
+    /// // Fetching serde adds serde_derive, which fills the graph before tokio is fetched
+    /// assert!(graph.is_truncated());
+    /// assert_eq!(graph.node_count(), 4);
+    /// assert!(graph.package("tokio").is_none());
+    /// assert_eq!(graph.markers("tokio"), ["truncated"]);
+    /// ```
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Records that a fetch stopped at `FetchOptions::max_nodes`.
+    pub(crate) fn mark_truncated(&mut self) {
+        self.truncated = true;
+    }
+
+    /// Returns how often packages were looked up while fetching the graph.
+    pub fn fetch_stats(&self) -> FetchStats {
+        self.fetch_stats
+    }
+
+    /// Sets the links printed next to crates by the renderers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, LinkStyle};
+    /// use depth::package::Package;
+    ///
+    /// let mut serde = Package::new("serde".to_string(), "https://serde.rs".to_string(), vec![], false);
+    /// serde.version = "1.0.192".to_string();
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&serde);
+    /// assert_eq!(graph.link("serde", &serde.url), "https://serde.rs");
+    ///
+    /// graph.set_link_style(LinkStyle::Docs);
+    /// assert_eq!(graph.link("serde", &serde.url), "https://docs.rs/serde/1.0.192");
+    /// // Crates that were not fetched link to their latest version
+    /// assert_eq!(graph.link("syn", "^2"), "https://docs.rs/syn/latest");
+    ///
+    /// graph.set_link_style(LinkStyle::Crates);
+    /// assert_eq!(graph.link("serde", &serde.url), "https://crates.io/crates/serde/1.0.192");
+    /// ```
+    pub fn set_link_style(&mut self, link_style: LinkStyle) {
+        self.link_style = link_style;
+    }
+
+    /// Sets the colors of the levels of the default tree, `ColorScheme::None` writing no escape
+    /// codes at all.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{ColorScheme, DependencyGraph};
+    /// use depth::package::Package;
+    ///
+    /// let serde = Package::new("serde".to_string(), "https://serde.rs".to_string(), vec![], false);
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&serde);
+    ///
+    /// let mut tree = Vec::new();
+    /// graph.write_dependencies_at_level(&serde, 0, 1, &mut tree).unwrap();
+    /// assert_eq!(String::from_utf8(tree).unwrap(), "\x1b[32m ├── serde - (https://serde.rs)\x1b[0m\n");
+    ///
+    /// graph.set_color_scheme(ColorScheme::None);
+    /// let mut tree = Vec::new();
+    /// graph.write_dependencies_at_level(&serde, 0, 1, &mut tree).unwrap();
+    /// assert_eq!(String::from_utf8(tree).unwrap(), " ├── serde - (https://serde.rs)\n");
+    /// ```
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        self.color_scheme = color_scheme;
+    }
+
+    /// Sets the order of the dependencies of each crate in the default tree and in the dot, svg
+    /// and graphml exports, by name unless set. The `cargo-tree` format takes its order from
+    /// `TreeOptions::sort` instead.
+    ///
+    /// Every order but `SortOrder::Insertion` is independent of the order crates were declared
+    /// and fetched in, so two runs over the same crates print the same output.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{ColorScheme, DependencyGraph, SortOrder};
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "1.0.0"),
+    ///     vec![DependencyInfo::new("serde", "^1"), DependencyInfo::new("anyhow", "^1")],
+    /// );
+    /// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![]);
+    /// registry.add_crate(CrateInfo::new("anyhow", "1.0.0"), vec![]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// graph.set_color_scheme(ColorScheme::None);
+    /// let root = graph
+    ///     .fetch_dependency_tree_with_registry("app", 2, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let tree = |graph: &DependencyGraph| {
+    ///     let mut tree = Vec::new();
+    ///     graph.write_dependencies_at_level(&root, 0, 2, &mut tree).unwrap();
+    ///     String::from_utf8(tree).unwrap()
+    /// };
+    /// assert_eq!(tree(&graph), " ├── app - ()\n    ├── anyhow - ()\n    ├── serde - ()\n");
+    ///
+    /// graph.set_sort_order(SortOrder::Insertion);
+    /// assert_eq!(tree(&graph), " ├── app - ()\n    ├── serde - ()\n    ├── anyhow - ()\n");
+    /// ```
+    pub fn set_sort_order(&mut self, sort_order: SortOrder) {
+        self.sort_order = sort_order;
+    }
+
+    /// Sets whether the default tree lists the features declared by each fetched crate under
+    /// it, with the optional dependencies each feature enables. The `cargo-tree` format takes
+    /// it from `TreeOptions::show_features` instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{ColorScheme, DependencyGraph};
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut serde = CrateInfo::new("serde", "1.0.0");
+    /// serde.features = HashMap::from([
+    ///     ("std".to_string(), vec![]),
+    ///     ("derive".to_string(), vec!["dep:serde_derive".to_string()]),
+    /// ]);
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     serde,
+    ///     vec![DependencyInfo {
+    ///         optional: true,
+    ///         ..DependencyInfo::new("serde_derive", "^1")
+    ///     }],
+    /// );
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// graph.set_color_scheme(ColorScheme::None);
+    /// graph.set_show_features(true);
+    /// let root = graph
+    ///     .fetch_dependency_tree_with_registry("serde", 2, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let mut tree = Vec::new();
+    /// graph.write_dependencies_at_level(&root, 0, 2, &mut tree).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(tree).unwrap(),
+    ///     " ├── serde - ()\n    ◦ feature derive → serde_derive\n    ◦ feature std\n"
+    /// );
+    /// ```
+    pub fn set_show_features(&mut self, show_features: bool) {
+        self.show_features = show_features;
+    }
+
+    /// Returns the features of a fetched crate, each with the optional dependencies it enables.
+    fn features(&self, crate_name: &str) -> impl Iterator<Item = (&String, &Vec<String>)> {
+        self.packages
+            .get(crate_name)
+            .into_iter()
+            .flat_map(|package| &package.features)
+    }
+
+    /// Formats a feature as `feature name → dependencies`, leaving out the arrow when it
+    /// enables no optional dependency.
+    fn feature_line(feature: &str, enabled: &[String]) -> String {
+        if enabled.is_empty() {
+            format!("feature {}", feature)
+        } else {
+            format!("feature {} → {}", feature, enabled.join(", "))
+        }
+    }
+
+    /// Sets whether the default tree leaves out the link of each crate, printing ` ├── name`
+    /// and its markers only. Unavailable crates are still marked `(unavailable)`.
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+    }
+
+    /// Sets the number of terminal columns the lines of the default tree are fitted to, or lifts
+    /// the limit with `None`. Long links are shortened first, then long lines are cut as a
+    /// whole, each ending with `…`. Colors are applied after, so no escape code is ever cut.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{ColorScheme, DependencyGraph};
+    /// use depth::package::Package;
+    ///
+    /// let root = Package::new(
+    ///     "serde".to_string(),
+    ///     "https://serde.rs/a/very/long/path".to_string(),
+    ///     vec![],
+    ///     false,
+    /// );
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&root);
+    /// graph.set_color_scheme(ColorScheme::None);
+    /// let tree = |graph: &DependencyGraph| {
+    ///     let mut tree = Vec::new();
+    ///     graph.write_dependencies_at_level(&root, 0, 1, &mut tree).unwrap();
+    ///     String::from_utf8(tree).unwrap()
+    /// };
+    ///
+    /// graph.set_max_width(Some(30));
+    /// assert_eq!(tree(&graph), " ├── serde - (https://serde.…)\n");
+    ///
+    /// graph.set_max_width(Some(8));
+    /// assert_eq!(tree(&graph), " ├── se…\n");
+    ///
+    /// graph.set_compact(true);
+    /// graph.set_max_width(None);
+    /// assert_eq!(tree(&graph), " ├── serde\n");
+    /// ```
+    pub fn set_max_width(&mut self, max_width: Option<usize>) {
+        self.max_width = max_width;
+    }
+
+    /// Sorts dependencies, each given as its name and version requirement, in the given order.
+    /// Ties are broken by name, and `SortOrder::Insertion` leaves them as they are.
+    fn sort_dependencies<T>(
+        &self,
+        dependencies: &mut [T],
+        order: SortOrder,
+        subtree_sizes: &HashMap<String, usize>,
+        key: impl Fn(&T) -> (&str, &str),
+    ) {
+        if order == SortOrder::Insertion {
+            return;
+        }
+        dependencies.sort_by(|a, b| key(a).cmp(&key(b)));
+
+        // Stable sorts, so ties stay sorted by name
+        match order {
+            SortOrder::Name | SortOrder::Insertion => {}
+            SortOrder::Size => dependencies.sort_by_key(|dependency| {
+                Reverse(subtree_sizes.get(key(dependency).0).copied().unwrap_or(0))
+            }),
+            SortOrder::Version => dependencies.sort_by_key(|dependency| {
+                let (name, req) = key(dependency);
+                let version = match self.packages.get(name) {
+                    Some(child) => child.version.as_str(),
+                    None => req.trim_start_matches(['^', '~', '=', ' ']),
+                };
+                // Unparseable versions (like `*`) go last
+                semver::Version::parse(version)
+                    .ok()
+                    .map_or((1, None), |v| (0, Some(v)))
+            }),
+            SortOrder::Downloads => dependencies.sort_by_key(|dependency| {
+                let downloads = self
+                    .packages
+                    .get(key(dependency).0)
+                    .and_then(|child| child.downloads);
+                Reverse(downloads)
+            }),
+        }
+    }
+
+    /// Returns the subtree sizes `sort_dependencies` needs to sort by size, none otherwise.
+    fn subtree_sizes_for(&self, order: SortOrder) -> HashMap<String, usize> {
+        match order {
+            SortOrder::Size => self.subtree_sizes(),
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Returns the graph with its nodes sorted by name and the edges of each node in the sort
+    /// order, for the exports iterating over node and edge indices. With `SortOrder::Insertion`,
+    /// the graph is returned as fetched.
+    fn ordered_graph(&self) -> Cow<'_, DiGraph<(String, String), DependencyKind>> {
+        if self.sort_order == SortOrder::Insertion {
+            return Cow::Borrowed(&self.graph);
+        }
+        let subtree_sizes = self.subtree_sizes_for(self.sort_order);
+        let mut nodes: Vec<NodeIndex> = self.graph.node_indices().collect();
+        nodes.sort_by(|&a, &b| self.graph[a].0.cmp(&self.graph[b].0));
+
+        let mut ordered = DiGraph::with_capacity(nodes.len(), self.graph.edge_count());
+        let indices: HashMap<NodeIndex, NodeIndex> = nodes
+            .iter()
+            .map(|&node| (node, ordered.add_node(self.graph[node].clone())))
+            .collect();
+        for &node in &nodes {
+            // Edges come from the most recently added, reversed they are in insertion order
+            let mut edges: Vec<_> = self.graph.edges(node).collect();
+            edges.reverse();
+            self.sort_dependencies(&mut edges, self.sort_order, &subtree_sizes, |edge| {
+                (self.graph[edge.target()].0.as_str(), "")
+            });
+            for edge in edges {
+                ordered.add_edge(indices[&node], indices[&edge.target()], *edge.weight());
+            }
+        }
+        Cow::Owned(ordered)
+    }
+
+    /// Returns the link a renderer prints for a crate, given the URL stored on its node.
+    pub fn link<'a>(&self, crate_name: &str, url: &'a str) -> Cow<'a, str> {
+        let version = self
+            .packages
+            .get(crate_name)
+            .map(|package| package.version.as_str())
+            .filter(|version| !version.is_empty());
+
+        match (self.link_style, version) {
+            (LinkStyle::Homepage, _) => Cow::Borrowed(url),
+            (LinkStyle::Docs, version) => Cow::Owned(format!(
+                "https://docs.rs/{}/{}",
+                crate_name,
+                version.unwrap_or("latest")
+            )),
+            (LinkStyle::Crates, Some(version)) => Cow::Owned(format!(
+                "https://crates.io/crates/{}/{}",
+                crate_name, version
+            )),
+            (LinkStyle::Crates, None) => {
+                Cow::Owned(format!("https://crates.io/crates/{}", crate_name))
+            }
+        }
+    }
+
+    /// Flags a crate with a short marker, printed in brackets next to it by the tree renderers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, TreeOptions};
+    /// use depth::package::Package;
+    ///
+    /// let mut root = Package::new("app".to_string(), "".to_string(), vec![], false);
+    /// root.version = "1.0.0".to_string();
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&root);
+    /// graph.add_marker("app", "owner changed");
+    ///
+    /// let mut output = Vec::new();
+    /// graph.write_cargo_tree(&root, 1, &TreeOptions::default(), &mut output).unwrap();
+    /// assert_eq!(String::from_utf8(output).unwrap(), "app v1.0.0 [owner changed]\n");
+    /// ```
+    pub fn add_marker(&mut self, crate_name: &str, marker: &str) {
+        self.markers
+            .entry(crate_name.to_string())
+            .or_default()
+            .push(marker.to_string());
+    }
+
+    /// Returns the markers a crate was flagged with.
+    pub fn markers(&self, crate_name: &str) -> &[String] {
+        self.markers.get(crate_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Writes the markers of a crate, each as ` [marker]`.
+    fn write_markers(&self, crate_name: &str, out: &mut dyn Write) -> io::Result<()> {
+        for marker in self.markers(crate_name) {
+            write!(out, " [{}]", marker)?;
+        }
+        Ok(())
+    }
+
+    /// Counts, for every fetched package, the unique crates reachable from it (itself excluded).
+    pub fn subtree_sizes(&self) -> HashMap<String, usize> {
+        self.packages
+            .values()
+            .map(|package| {
+                let mut seen = HashSet::new();
+                let mut stack = vec![package];
+                while let Some(current) = stack.pop() {
+                    for dependency in &current.dependencies {
+                        if dependency.0 != package.name && seen.insert(dependency.0.as_str()) {
+                            if let Some(child) = self.packages.get(&dependency.0) {
+                                stack.push(child);
+                            }
+                        }
+                    }
+                }
+                (package.name.clone(), seen.len())
+            })
+            .collect()
+    }
+
+    /// Compares the crates of this graph with those of another one, e.g. the trees of two
+    /// versions of a crate for upgrade analysis.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The graph to compare to, taken as the newer one.
+    ///
+    /// # Returns
+    ///
+    /// The crates added, removed and whose version changed in `other`, each sorted by name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{CrateVersion, DependencyGraph, VersionChange};
+    /// use depth::package::Package;
+    ///
+    /// let graph = |crates: &[(&str, &str)]| {
+    ///     let mut graph = DependencyGraph::new();
+    ///     for (name, version) in crates {
+    ///         let mut package = Package::new(name.to_string(), "".to_string(), vec![], false);
+    ///         package.version = version.to_string();
+    ///         graph.add_package_to_graph(&package);
+    ///     }
+    ///     graph
+    /// };
+    /// let old = graph(&[("app", "1.0.0"), ("syn", "1.0.109"), ("time", "0.1.45")]);
+    /// let new = graph(&[("app", "2.0.0"), ("syn", "1.0.109"), ("jiff", "0.2.0")]);
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(
+    ///     diff.added,
+    ///     vec![CrateVersion { name: "jiff".to_string(), version: "0.2.0".to_string() }]
+    /// );
+    /// assert_eq!(
+    ///     diff.removed,
+    ///     vec![CrateVersion { name: "time".to_string(), version: "0.1.45".to_string() }]
+    /// );
+    /// assert_eq!(
+    ///     diff.changed,
+    ///     vec![VersionChange {
+    ///         name: "app".to_string(),
+    ///         from: "1.0.0".to_string(),
+    ///         to: "2.0.0".to_string(),
+    ///     }]
+    /// );
+    /// ```
+    pub fn diff(&self, other: &DependencyGraph) -> GraphDiff {
+        let mut diff = GraphDiff::default();
+
+        for package in self.packages() {
+            match other.packages.get(&package.name) {
+                Some(newer) if newer.version != package.version => {
+                    diff.changed.push(VersionChange {
+                        name: package.name.clone(),
+                        from: package.version.clone(),
+                        to: newer.version.clone(),
+                    })
+                }
+                Some(_) => {}
+                None => diff.removed.push(CrateVersion::of(package)),
+            }
+        }
+        for package in other.packages() {
+            if !self.packages.contains_key(&package.name) {
+                diff.added.push(CrateVersion::of(package));
+            }
+        }
+
+        diff
+    }
+
+    /// Returns the crates most other crates of the tree depend on, sorted by their number of
+    /// dependents, most shared first.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The maximum number of crates to return.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, SharedCrate};
+    ///
+    /// let graph = DependencyGraph::from_edges(&[
+    ///     ("app", &["tokio", "serde", "log"]),
+    ///     ("tokio", &["libc", "log"]),
+    ///     ("mio", &["libc", "log"]),
+    /// ]);
+    ///
+    /// assert_eq!(
+    ///     graph.most_shared(2),
+    ///     vec![
+    ///         SharedCrate { name: "log".to_string(), dependents: 3 },
+    ///         SharedCrate { name: "libc".to_string(), dependents: 2 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn most_shared(&self, count: usize) -> Vec<SharedCrate> {
+        // One node per crate, so the in-degree is the number of distinct dependents
+        let (graph, _) = self.crate_graph();
+
+        let mut shared: Vec<SharedCrate> = graph
+            .node_indices()
+            .map(|node| SharedCrate {
+                name: graph[node].to_string(),
+                dependents: graph.neighbors_directed(node, Incoming).count(),
+            })
+            .filter(|shared| shared.dependents > 0)
+            .collect();
+        shared.sort_by(|a, b| b.dependents.cmp(&a.dependents).then(a.name.cmp(&b.name)));
+        shared.truncate(count);
+        shared
+    }
+
+    /// Returns the crates several of the given roots depend on, directly or not, each with the
+    /// roots depending on it in the given order, e.g. the crates shared by the trees of a
+    /// merged graph, see `add_merged_root`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let graph = DependencyGraph::from_edges(&[
+    ///     ("app", &["tokio", "serde"]),
+    ///     ("cli", &["clap", "serde"]),
+    ///     ("tokio", &["mio"]),
+    ///     ("clap", &["mio"]),
+    /// ]);
+    ///
+    /// let roots = ["app".to_string(), "cli".to_string()];
+    /// let both = vec!["app".to_string(), "cli".to_string()];
+    /// assert_eq!(
+    ///     graph.shared_dependencies(&roots),
+    ///     BTreeMap::from([("mio".to_string(), both.clone()), ("serde".to_string(), both)])
+    /// );
+    /// ```
+    pub fn shared_dependencies(&self, roots: &[String]) -> BTreeMap<String, Vec<String>> {
+        let (graph, nodes) = self.crate_graph();
+        let mut dependents: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for root in roots {
+            let Some(&start) = nodes.get(root.as_str()) else {
+                continue;
+            };
+            let mut dfs = Dfs::new(&graph, start);
+            while let Some(node) = dfs.next(&graph) {
+                if node != start {
+                    dependents
+                        .entry(graph[node].to_string())
+                        .or_default()
+                        .push(root.clone());
+                }
+            }
+        }
+        dependents.retain(|_, roots| roots.len() > 1);
+        dependents
+    }
+
+    /// Returns the crates of the tree that would be built in more than one version, because
+    /// their dependents require versions no single version satisfies, e.g. `^1` and `^2`.
+    ///
+    /// A crate has a single node resolved to a single version, so the requirement of each
+    /// dependent is resolved against the published versions of the crate. Only crates whose
+    /// resolved version does not satisfy every requirement are listed from the registry, and
+    /// crates whose versions cannot be listed are skipped with a warning.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::build_dependency_graph_with_registry;
+    /// use depth::dependency_graph::{DuplicateVersions, RequiredVersion};
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry, VersionInfo};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// let mut add = |name: &str, version: &str, deps: &[(&str, &str)]| {
+    ///     let deps = deps.iter().map(|(dep, req)| DependencyInfo::new(dep, req)).collect();
+    ///     registry.add_crate(CrateInfo::new(name, version), deps);
+    /// };
+    /// add("app", "1.0.0", &[("clap_derive", "^4"), ("serde_derive", "^1"), ("quote", "^1")]);
+    /// add("clap_derive", "4.4.7", &[("syn", "^2")]);
+    /// add("serde_derive", "1.0.192", &[("syn", "^1"), ("quote", "^1.0.30")]);
+    /// add("syn", "2.0.39", &[]);
+    /// add("quote", "1.0.33", &[]);
+    /// for num in ["1.0.109", "2.0.39"] {
+    ///     registry.add_version("syn", VersionInfo::new(num));
+    /// }
+    ///
+    /// let graph =
+    ///     build_dependency_graph_with_registry("app", 3, &FetchOptions::default(), &registry)
+    ///         .unwrap();
+    /// // quote is required twice, but 1.0.33 satisfies both requirements
+    /// assert_eq!(
+    ///     graph.duplicate_versions(&registry),
+    ///     vec![DuplicateVersions {
+    ///         name: "syn".to_string(),
+    ///         versions: vec![
+    ///             RequiredVersion {
+    ///                 version: "1.0.109".to_string(),
+    ///                 required_by: vec!["serde_derive".to_string()],
+    ///             },
+    ///             RequiredVersion {
+    ///                 version: "2.0.39".to_string(),
+    ///                 required_by: vec!["clap_derive".to_string()],
+    ///             },
+    ///         ],
+    ///     }]
+    /// );
+    /// ```
+    pub fn duplicate_versions(&self, registry: &impl CrateRegistry) -> Vec<DuplicateVersions> {
+        // The requirements of the dependents of each crate, with the dependents requiring them
+        let mut requirements: BTreeMap<&str, BTreeMap<&str, Vec<String>>> = BTreeMap::new();
+        for package in self.packages() {
+            for (name, req) in &package.dependencies {
+                requirements
+                    .entry(name.as_str())
+                    .or_default()
+                    .entry(req.as_str())
+                    .or_default()
+                    .push(package.name.clone());
+            }
+        }
+
+        let mut duplicates = Vec::new();
+        for (name, reqs) in requirements {
+            let Some(resolved) = self
+                .packages
+                .get(name)
+                .and_then(|package| Version::parse(&package.version).ok())
+            else {
+                continue;
+            };
+            let parsed: Vec<(VersionReq, &Vec<String>)> = reqs
+                .iter()
+                .filter_map(|(req, dependents)| Some((VersionReq::parse(req).ok()?, dependents)))
+                .collect();
+            if parsed.iter().all(|(req, _)| req.matches(&resolved)) {
+                continue;
+            }
+
+            let mut versions: BTreeMap<Version, Vec<String>> = BTreeMap::new();
+            for (req, dependents) in parsed {
+                match resolve_version(registry, name, &req) {
+                    Ok(Some(version)) => versions
+                        .entry(version)
+                        .or_default()
+                        .extend_from_slice(dependents),
+                    Ok(None) => {}
+                    Err(err) => {
+                        warn!("{}, skipping its duplicate check", err);
+                        versions.clear();
+                        break;
+                    }
+                }
+            }
+            if versions.len() > 1 {
+                duplicates.push(DuplicateVersions {
+                    name: name.to_string(),
+                    versions: versions
+                        .into_iter()
+                        .map(|(version, mut required_by)| {
+                            required_by.sort();
+                            required_by.dedup();
+                            RequiredVersion {
+                                version: version.to_string(),
+                                required_by,
+                            }
+                        })
+                        .collect(),
+                });
+            }
+        }
+        duplicates
+    }
+
+    /// Returns `true` if `to` is `from` or one of its direct or transitive dependencies.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    ///
+    /// let graph = DependencyGraph::from_edges(&[("app", &["tokio"]), ("tokio", &["mio"])]);
+    ///
+    /// assert!(graph.is_reachable("app", "mio"));
+    /// assert!(!graph.is_reachable("mio", "app"));
+    /// assert!(!graph.is_reachable("app", "serde"));
+    /// ```
+    pub fn is_reachable(&self, from: &str, to: &str) -> bool {
+        let (graph, nodes) = self.crate_graph();
+        match (nodes.get(from), nodes.get(to)) {
+            (Some(&from), Some(&to)) => has_path_connecting(&graph, from, to, None),
+            _ => false,
+        }
+    }
+
+    /// Enumerates the dependency paths from one crate to another, each path starting with `from`
+    /// and ending with `to`.
+    ///
+    /// The number of paths grows exponentially with the number of crates shared along the way,
+    /// a deep tree easily having millions of paths to a crate like `libc`. At most `max` paths
+    /// are enumerated, the enumeration stopping as soon as it finds them, so the cost of a call is
+    /// bounded by `max` rather than by the number of paths.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The crate the paths start at, usually the root package.
+    /// * `to` - The crate the paths end at.
+    /// * `max` - The maximum number of paths to return.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    ///
+    /// let graph = DependencyGraph::from_edges(&[
+    ///     ("app", &["hyper", "tokio"]),
+    ///     ("hyper", &["tokio"]),
+    ///     ("tokio", &["mio"]),
+    /// ]);
+    ///
+    /// let mut paths = graph.all_paths("app", "mio", 10);
+    /// paths.sort();
+    /// assert_eq!(
+    ///     paths,
+    ///     vec![vec!["app", "hyper", "tokio", "mio"], vec!["app", "tokio", "mio"]]
+    /// );
+    /// assert_eq!(graph.all_paths("app", "mio", 1).len(), 1);
+    /// ```
+    pub fn all_paths(&self, from: &str, to: &str, max: usize) -> Vec<Vec<String>> {
+        let (graph, nodes) = self.crate_graph();
+        let (Some(&from), Some(&to)) = (nodes.get(from), nodes.get(to)) else {
+            return Vec::new();
+        };
+        if from == to {
+            return vec![vec![graph[from].to_string()]];
+        }
+
+        all_simple_paths::<Vec<NodeIndex>, _>(&graph, from, to, 0, None)
+            .take(max)
+            .map(|path| {
+                path.into_iter()
+                    .map(|node| graph[node].to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Finds the dependency cycles of the graph, such as the ones dev-dependencies form, each
+    /// cycle starting and ending with the same crate, e.g. `["a", "b", "a"]`.
+    ///
+    /// Crates depending on each other directly or transitively form a strongly connected
+    /// component, and one shortest cycle is reported per component, starting at its crate that
+    /// sorts first. Cycles are sorted by that crate.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, DependencyKind, MemoryRegistry, TimedRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("serde", "1.0.0"),
+    ///     vec![DependencyInfo::new("serde_derive", "^1")],
+    /// );
+    /// registry.add_crate(
+    ///     CrateInfo::new("serde_derive", "1.0.0"),
+    ///     vec![DependencyInfo {
+    ///         kind: DependencyKind::Dev,
+    ///         ..DependencyInfo::new("serde", "^1")
+    ///     }],
+    /// );
+    /// let registry = TimedRegistry::new(registry);
+    ///
+    /// let options = FetchOptions {
+    ///     dev_recursive: true,
+    ///     ..FetchOptions::default()
+    /// };
+    /// let mut graph = DependencyGraph::new();
+    /// let serde = graph
+    ///     .fetch_dependency_tree_with_registry("serde", 10, &options, &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(graph.find_cycles(), vec![vec!["serde", "serde_derive", "serde"]]);
+    ///
+    /// // The tree still terminates, stopping where the cycle closes
+    /// let mut tree = Vec::new();
+    /// graph.write_dependencies_at_level(&serde, 0, 10, &mut tree).unwrap();
+    /// assert!(String::from_utf8(tree).unwrap().contains("serde_derive"));
+    /// ```
+    pub fn find_cycles(&self) -> Vec<Vec<String>> {
+        let (graph, _) = self.crate_graph();
+
+        let mut cycles: Vec<Vec<String>> = tarjan_scc(&graph)
+            .into_iter()
+            .filter_map(|component| {
+                let start = *component.iter().min_by_key(|&&node| graph[node])?;
+                let members: HashSet<NodeIndex> = component.into_iter().collect();
+
+                // Breadth-first from the start back to itself, within the component
+                let mut previous: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+                let mut queue = VecDeque::from([start]);
+                while let Some(node) = queue.pop_front() {
+                    for next in graph.neighbors(node) {
+                        if next == start {
+                            let mut cycle = vec![graph[start].to_string()];
+                            let mut current = node;
+                            while current != start {
+                                cycle.push(graph[current].to_string());
+                                current = previous[&current];
+                            }
+                            cycle.push(graph[start].to_string());
+                            cycle.reverse();
+                            return Some(cycle);
+                        }
+                        if members.contains(&next) && !previous.contains_key(&next) {
+                            previous.insert(next, node);
+                            queue.push_back(next);
+                        }
+                    }
+                }
+                None
+            })
+            .collect();
+        cycles.sort();
+        cycles
+    }
+
+    /// Returns the longest dependency chain of the graph, from a crate nothing depends on down
+    /// to a crate without dependencies, to spot crates layered over many others.
+    ///
+    /// Cycles are broken at the edge closing them in a depth-first walk from the crates nothing
+    /// depends on, and among chains of the same length the one through the alphabetically first
+    /// crates is returned.
+    ///
+    /// # Returns
+    ///
+    /// The names of the crates on the chain, in dependency order, empty for an empty graph.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    ///
+    /// let graph = DependencyGraph::from_edges(&[
+    ///     ("app", &["a", "b"]),
+    ///     ("a", &[]),
+    ///     ("b", &["c"]),
+    ///     ("c", &["d"]),
+    ///     // Closes a cycle, which does not make the chain endless
+    ///     ("d", &["b"]),
+    /// ]);
+    ///
+    /// // The first dependency of app is a leaf, the chain goes through the second one
+    /// assert_eq!(graph.longest_path(), ["app", "b", "c", "d"]);
+    /// ```
+    pub fn longest_path(&self) -> Vec<String> {
+        let (graph, _) = self.crate_graph();
+
+        // Walked from the crates nothing depends on first, so cycles break below them
+        let (mut starts, others): (Vec<NodeIndex>, Vec<NodeIndex>) = graph
+            .node_indices()
+            .partition(|&node| graph.neighbors_directed(node, Incoming).next().is_none());
+        starts.extend(others);
+        let mut post_order = Vec::new();
+        let mut dfs = DfsPostOrder::empty(&graph);
+        for start in starts {
+            if dfs.discovered.contains(start.index()) {
+                continue;
+            }
+            dfs.move_to(start);
+            while let Some(node) = dfs.next(&graph) {
+                post_order.push(node);
+            }
+        }
+        let position: HashMap<NodeIndex, usize> = post_order
+            .iter()
+            .enumerate()
+            .map(|(position, &node)| (node, position))
+            .collect();
+
+        // Dependencies come first in post-order, except across an edge closing a cycle
+        let mut length: HashMap<NodeIndex, usize> = HashMap::new();
+        let mut next: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        for &node in &post_order {
+            let longest = graph
+                .neighbors(node)
+                .filter(|dependency| position[dependency] < position[&node])
+                .max_by_key(|dependency| (length[dependency], Reverse(graph[*dependency])));
+            length.insert(
+                node,
+                longest.map_or(1, |dependency| length[&dependency] + 1),
+            );
+            if let Some(dependency) = longest {
+                next.insert(node, dependency);
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut current = post_order
+            .iter()
+            .copied()
+            .max_by_key(|node| (length[node], Reverse(graph[*node])));
+        while let Some(node) = current {
+            path.push(graph[node].to_string());
+            current = next.get(&node).copied();
+        }
+        path
+    }
+
+    /// Prunes the graph to the crates on the paths from a root to a crate, to see why that crate
+    /// is pulled in without the rest of the tree.
+    ///
+    /// The returned graph keeps the crates, their markers and their errors, and each package
+    /// only lists its dependencies on crates that are kept, so every renderer and analysis works
+    /// on it unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The name of the root crate of the tree.
+    /// * `crate_name` - The name of the crate to focus on.
+    ///
+    /// # Returns
+    ///
+    /// The pruned graph, or `None` if the crate cannot be reached from the root.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::MemoryRegistry;
+    ///
+    /// let registry = MemoryRegistry::from_edges(&[
+    ///     ("app", &["clap", "serde", "tokio"]),
+    ///     ("clap", &["syn"]),
+    ///     ("serde", &["serde_derive"]),
+    ///     ("serde_derive", &["syn"]),
+    ///     ("syn", &["unicode-ident"]),
+    ///     ("unicode-ident", &[]),
+    ///     ("tokio", &["mio"]),
+    ///     ("mio", &[]),
+    /// ]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// let root = graph
+    ///     .fetch_dependency_tree_with_registry("app", 5, &FetchOptions::default(), &registry)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let focused = graph.focus("app", "syn").unwrap();
+    /// let mut names: Vec<&str> = focused.packages().iter().map(|package| package.name.as_str()).collect();
+    /// names.sort_unstable();
+    /// assert_eq!(names, vec!["app", "clap", "serde", "serde_derive", "syn"]);
+    ///
+    /// // The tree only shows the paths to `syn`
+    /// let root = focused.package("app").unwrap();
+    /// let mut tree = Vec::new();
+    /// focused.write_dependencies_at_level(root, 0, 5, &mut tree).unwrap();
+    /// let tree = String::from_utf8(tree).unwrap();
+    /// assert!(tree.contains("serde_derive") && !tree.contains("tokio") && !tree.contains("unicode-ident"));
+    ///
+    /// assert!(graph.focus("app", "rand").is_none());
+    /// assert!(graph.focus("tokio", "syn").is_none());
+    /// ```
+    pub fn focus(&self, root: &str, crate_name: &str) -> Option<DependencyGraph> {
+        let (graph, nodes) = self.crate_graph();
+        let (&root_node, &target_node) = (nodes.get(root)?, nodes.get(crate_name)?);
+
+        let mut from_root = HashSet::new();
+        let mut dfs = Dfs::new(&graph, root_node);
+        while let Some(node) = dfs.next(&graph) {
+            from_root.insert(node);
+        }
+        if !from_root.contains(&target_node) {
+            return None;
+        }
+        // The crates on a path are both reachable from the root and reaching the crate
+        let mut kept: HashSet<&str> = HashSet::new();
+        let reversed = Reversed(&graph);
+        let mut dfs = Dfs::new(reversed, target_node);
+        while let Some(node) = dfs.next(reversed) {
+            if from_root.contains(&node) {
+                kept.insert(graph[node]);
+            }
+        }
+
+        let is_kept = |name: &String| kept.contains(name.as_str());
+        let graph = self.graph.filter_map(
+            |_, node| is_kept(&node.0).then(|| node.clone()),
+            |_, edge| Some(*edge),
+        );
+        Some(DependencyGraph {
+            nodes: graph
+                .node_indices()
+                .map(|index| (graph[index].0.clone(), index))
+                .collect(),
+            graph,
+            unavailable: self
+                .unavailable
+                .iter()
+                .filter(|(name, _)| is_kept(name))
+                .map(|(name, reason)| (name.clone(), reason.clone()))
+                .collect(),
+            errors: self
+                .errors
+                .iter()
+                .filter(|(name, _)| is_kept(name))
+                .map(|(name, error)| (name.clone(), error.clone()))
+                .collect(),
+            packages: self
+                .packages
+                .iter()
+                .filter(|(name, _)| is_kept(name))
+                .map(|(name, package)| {
+                    let mut package = package.clone();
+                    package
+                        .dependencies
+                        .retain(|dependency| is_kept(&dependency.0));
+                    (name.clone(), package)
+                })
+                .collect(),
+            aliases: self.aliases.clone(),
+            markers: self
+                .markers
+                .iter()
+                .filter(|(name, _)| is_kept(name))
+                .map(|(name, markers)| (name.clone(), markers.clone()))
+                .collect(),
+            link_style: self.link_style,
+            color_scheme: self.color_scheme,
+            sort_order: self.sort_order,
+            show_features: self.show_features,
+            compact: self.compact,
+            max_width: self.max_width,
+            fetch_stats: self.fetch_stats,
+            fetch_options: self.fetch_options.clone(),
+            hidden_root: self.hidden_root.clone(),
+            fetch_path: Vec::new(),
+            truncated: self.truncated,
+        })
+    }
+
+    /// Returns the crates of the graph with a name close to the given one, closest first, to
+    /// suggest when a crate is not in the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    ///
+    /// let graph = DependencyGraph::from_edges(&[("serde", &[]), ("serde_json", &[]), ("tokio", &[])]);
+    /// assert_eq!(graph.similar_crates("serde-jsno", 3), vec!["serde_json", "serde"]);
+    /// ```
+    pub fn similar_crates(&self, crate_name: &str, count: usize) -> Vec<String> {
+        // Crates.io treats `-` and `_` as the same character
+        let normalize = |name: &str| name.to_lowercase().replace('-', "_");
+        let wanted = normalize(crate_name);
+        let (graph, _) = self.crate_graph();
+
+        let mut similar: Vec<(usize, &str)> = graph
+            .node_weights()
+            .filter(|&&name| name != crate_name)
+            .filter_map(|&name| {
+                let candidate = normalize(name);
+                let distance = edit_distance(&candidate, &wanted);
+                let related = candidate.contains(&wanted) || wanted.contains(&candidate);
+                (distance <= 2 || related).then_some((distance, name))
+            })
+            .collect();
+        similar.sort_unstable();
+        similar
+            .into_iter()
+            .take(count)
+            .map(|(_, name)| name.to_string())
+            .collect()
+    }
+
+    /// Computes the size of the graph and the depth it actually reached, which is lower than the
+    /// requested depth when the tree bottoms out early.
+    ///
+    /// The depth is the longest chain of dependencies in the graph, found by a depth-first walk
+    /// from the crates nothing depends on. An edge closing a cycle is not followed, so cycles do
+    /// not make the depth infinite.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, GraphStats};
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::MemoryRegistry;
+    ///
+    /// let registry = MemoryRegistry::from_edges(&[
+    ///     ("app", &["serde", "tokio"]),
+    ///     ("serde", &["serde_derive"]),
+    ///     ("serde_derive", &["syn"]),
+    ///     ("syn", &[]),
+    ///     ("tokio", &["syn"]),
+    /// ]);
+    ///
+    /// let fetch = |depth: usize| {
+    ///     let mut graph = DependencyGraph::new();
+    ///     graph
+    ///         .fetch_dependency_tree_with_registry("app", depth, &FetchOptions::default(), &registry)
+    ///         .unwrap();
+    ///     graph.stats()
+    /// };
+    ///
+    /// // The tree bottoms out at app -> serde -> serde_derive -> syn, whatever the requested depth
+    /// let stats = GraphStats {
+    ///     node_count: 5,
+    ///     edge_count: 5,
+    ///     max_depth: 3,
+    ///     unique_crates: 5,
+    /// };
+    /// assert_eq!(fetch(10), stats);
+    ///
+    /// // `serde_derive` and `syn` are listed by the crates of the last level, but not fetched
+    /// assert_eq!(
+    ///     fetch(2),
+    ///     GraphStats {
+    ///         node_count: 5,
+    ///         edge_count: 4,
+    ///         max_depth: 2,
+    ///         unique_crates: 3,
+    ///     }
+    /// );
+    /// ```
+    pub fn stats(&self) -> GraphStats {
+        let (graph, _) = self.crate_graph();
+
+        // The longest chain below each crate, known once all of its dependencies are done
+        let mut longest: HashMap<NodeIndex, usize> = HashMap::new();
+        let mut on_path: HashSet<NodeIndex> = HashSet::new();
+        let mut starts: Vec<NodeIndex> = graph.node_indices().collect();
+        starts.sort_by_key(|&node| graph.neighbors_directed(node, Incoming).count() > 0);
+        for start in starts {
+            if longest.contains_key(&start) {
+                continue;
+            }
+            // Each entry is a crate and whether its dependencies were pushed already
+            let mut stack = vec![(start, false)];
+            while let Some((node, expanded)) = stack.pop() {
+                if expanded {
+                    let below = graph
+                        .neighbors(node)
+                        .filter_map(|next| longest.get(&next))
+                        .map(|length| length + 1)
+                        .max()
+                        .unwrap_or(0);
+                    on_path.remove(&node);
+                    longest.insert(node, below);
+                    continue;
+                }
+                if longest.contains_key(&node) || !on_path.insert(node) {
+                    continue;
+                }
+                stack.push((node, true));
+                for next in graph.neighbors(node) {
+                    // A crate still on the path closes a cycle
+                    if !on_path.contains(&next) && !longest.contains_key(&next) {
+                        stack.push((next, false));
+                    }
+                }
+            }
+        }
+
+        GraphStats {
+            node_count: graph.node_count(),
+            edge_count: graph.edge_count(),
+            max_depth: longest.values().copied().max().unwrap_or(0),
+            unique_crates: self.packages().len(),
+        }
+    }
+
+    /// Builds a graph with one node per crate and one edge per dependency, from the fetched
+    /// packages sorted by name so traversals are deterministic.
+    ///
+    /// The underlying graph holds the same nodes and edges, but in the order the crates were
+    /// fetched.
+    fn crate_graph(&self) -> (DiGraph<&str, ()>, HashMap<&str, NodeIndex>) {
+        let mut graph = DiGraph::<&str, ()>::new();
+        let mut nodes = HashMap::new();
+        for package in self.packages() {
+            let source = *nodes
+                .entry(package.name.as_str())
+                .or_insert_with(|| graph.add_node(package.name.as_str()));
+            for dependency in &package.dependencies {
+                let target = *nodes
+                    .entry(dependency.0.as_str())
+                    .or_insert_with(|| graph.add_node(dependency.0.as_str()));
+                graph.update_edge(source, target, ());
+            }
+        }
+        (graph, nodes)
+    }
+
+    /// Generates a JSON representation of the whole graph, a [`GraphJson`] with every node,
+    /// fetched or not, and every edge with its kind and requirement, so the graph can be
+    /// rebuilt exactly in another tool.
+    ///
+    /// Nodes and edges are written flat, sorted by name, so cycles between crates are written as
+    /// they are rather than followed.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `String` containing the pretty-printed JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, GraphJson, NodeState};
+    /// use depth::registry::DependencyKind;
+    ///
+    /// // `serde` and `serde_derive` depend on each other, `syn` is not fetched
+    /// let mut graph = DependencyGraph::from_edges(&[
+    ///     ("serde", &["serde_derive"]),
+    ///     ("serde_derive", &["serde", "syn", "gone"]),
+    /// ]);
+    /// graph.mark_unavailable("gone", "HTTP 404".to_string());
+    ///
+    /// let json: GraphJson = serde_json::from_str(&graph.to_json()).unwrap();
+    /// let nodes: Vec<(&str, NodeState)> = json
+    ///     .nodes
+    ///     .iter()
+    ///     .map(|node| (node.name.as_str(), node.state))
+    ///     .collect();
+    /// assert_eq!(
+    ///     nodes,
+    ///     [
+    ///         ("gone", NodeState::Unavailable),
+    ///         ("serde", NodeState::Fetched),
+    ///         ("serde_derive", NodeState::Fetched),
+    ///         ("syn", NodeState::Unfetched),
+    ///     ]
+    /// );
+    /// assert_eq!(json.edges.len(), 4);
+    /// assert_eq!((json.edges[0].from.as_str(), json.edges[0].to.as_str()), ("serde", "serde_derive"));
+    /// assert_eq!(json.edges[0].kind, DependencyKind::Normal);
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut nodes: Vec<GraphNode> = self
+            .graph
+            .node_weights()
+            .filter(|(name, _)| !self.is_hidden_root(name))
+            .map(|(name, _)| {
+                let package = self.packages.get(name);
+                let state = match (package, self.unavailable.contains_key(name)) {
+                    (_, true) => NodeState::Unavailable,
+                    (Some(_), false) => NodeState::Fetched,
+                    (None, false) => NodeState::Unfetched,
+                };
+                GraphNode {
+                    name: name.clone(),
+                    version: package.map(|package| package.version.clone()),
+                    url: package
+                        .map(|package| package.url.clone())
+                        .filter(|url| !url.is_empty()),
+                    internal: package.is_some_and(|package| package.internal),
+                    state,
+                    markers: self.markers(name).to_vec(),
+                }
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut edges: Vec<GraphEdge> = self
+            .graph
+            .edge_references()
+            .map(|edge| {
+                let (from, to) = (&self.graph[edge.source()].0, &self.graph[edge.target()].0);
+                let req = self
+                    .packages
+                    .get(from)
+                    .and_then(|package| package.dependencies.iter().find(|dep| dep.0 == *to))
+                    .map_or_else(String::new, |dep| dep.1.clone());
+                GraphEdge {
+                    from: from.clone(),
+                    to: to.clone(),
+                    kind: *edge.weight(),
+                    req,
+                }
+            })
+            .filter(|edge| !self.is_hidden_root(&edge.from) && !self.is_hidden_root(&edge.to))
+            .collect();
+        edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+
+        let json = GraphJson {
+            nodes,
+            edges,
+            truncated: self.truncated,
+        };
+        serde_json::to_string_pretty(&json).expect("the graph serializes to JSON")
+    }
+
+    /// Generates a Mermaid flowchart of the graph, with edges pointing from each crate to its
+    /// dependencies, to embed in Markdown.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `String` containing the `graph TD` flowchart.
+    pub fn to_mermaid(&self) -> String {
+        self.to_mermaid_with_style(EdgeStyle::default())
+    }
+
+    /// Generates a Mermaid flowchart of the graph, drawing the edges in the given style.
+    ///
+    /// Nodes and edges are sorted by name, the nodes being labeled with the crate name and
+    /// resolved version.
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - The direction of the edges and whether they are labeled.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `String` containing the `graph TD` flowchart.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, EdgeDirection, EdgeStyle};
+    /// use depth::package::Package;
+    ///
+    /// let dependencies = vec![("serde_json".to_string(), "".to_string())];
+    /// let mut app = Package::new("my-app".to_string(), "".to_string(), dependencies, false);
+    /// app.version = "0.1.0".to_string();
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&app);
+    /// graph.add_package_to_graph(&app);
+    ///
+    /// assert_eq!(
+    ///     graph.to_mermaid(),
+    ///     concat!(
+    ///         "graph TD\n",
+    ///         "    c_my_app[\"my-app v0.1.0\"]\n",
+    ///         "    c_serde_json[\"serde_json\"]\n",
+    ///         "    c_my_app --> c_serde_json\n",
+    ///     )
+    /// );
+    ///
+    /// let style = EdgeStyle {
+    ///     direction: EdgeDirection::DependedOnBy,
+    ///     labels: true,
+    /// };
+    /// assert!(graph
+    ///     .to_mermaid_with_style(style)
+    ///     .ends_with("    c_serde_json -->|depended on by| c_my_app\n"));
+    ///
+    /// // A crate named like a keyword of Mermaid is still a node
+    /// let graph = DependencyGraph::from_edges(&[("app", &["end"])]);
+    /// assert!(graph.to_mermaid().ends_with("    c_app --> c_end\n"));
+    /// ```
+    pub fn to_mermaid_with_style(&self, style: EdgeStyle) -> String {
+        let mut nodes = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+        for edge in self.graph.edge_indices() {
+            let Some((source, target)) = self.graph.edge_endpoints(edge) else {
+                continue;
+            };
+            let (source, target) = (&self.graph[source].0, &self.graph[target].0);
+            if self.is_hidden_root(source) || self.is_hidden_root(target) {
+                continue;
+            }
+            edges.insert(match style.direction {
+                EdgeDirection::DependsOn => (source, target),
+                EdgeDirection::DependedOnBy => (target, source),
+            });
+        }
+        for (name, _) in self.graph.node_weights() {
+            if !self.is_hidden_root(name) {
+                nodes.insert(name);
+            }
+        }
+
+        let mut mermaid = String::from("graph TD\n");
+        for name in nodes {
+            let mut label = self.display_name(name).to_string();
+            if let Some(package) = self.packages.get(name) {
+                label.push_str(&format!(" v{}", package.version));
+            }
+            mermaid.push_str(&format!(
+                "    {}[\"{}\"]\n",
+                mermaid_id(name),
+                label.replace('"', "#quot;")
+            ));
+        }
+        let arrow = if style.labels {
+            format!("-->|{}|", style.direction.label())
+        } else {
+            "-->".to_string()
+        };
+        for (from, to) in edges {
+            mermaid.push_str(&format!(
+                "    {} {} {}\n",
+                mermaid_id(from),
+                arrow,
+                mermaid_id(to)
+            ));
+        }
+        mermaid
+    }
+
+    /// Generates a GraphML document of the graph, to import in graph analysis tools such as
+    /// Gephi or yEd.
+    ///
+    /// Every crate is a node with its name, URL and resolved version, the latter two being
+    /// empty for crates that were not fetched, and every edge points from a crate to one of its
+    /// dependencies with the kind of the dependency. Nodes are sorted by name and the edges of
+    /// each node follow `set_sort_order`.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `String` containing the GraphML document.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::Package;
+    /// use depth::registry::DependencyKind;
+    ///
+    /// let dependencies = vec![
+    ///     ("serde".to_string(), "^1".to_string()),
+    ///     ("cc".to_string(), "^1".to_string()),
+    /// ];
+    /// let url = "https://example.com/?a=1&b=<2>".to_string();
+    /// let mut app = Package::new("app".to_string(), url, dependencies, false);
+    /// app.version = "0.1.0".to_string();
+    /// app.dependency_kinds.insert("cc".to_string(), DependencyKind::Build);
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&app);
+    ///
+    /// let graphml = graph.to_graphml();
+    /// assert!(graphml.starts_with(concat!(
+    ///     "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+    ///     "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+    /// )));
+    /// assert!(graphml.contains(concat!(
+    ///     "    <node id=\"n0\">\n",
+    ///     "      <data key=\"name\">app</data>\n",
+    ///     "      <data key=\"url\">https://example.com/?a=1&amp;b=&lt;2&gt;</data>\n",
+    ///     "      <data key=\"version\">0.1.0</data>\n",
+    ///     "    </node>\n",
+    /// )));
+    /// assert!(graphml.contains(concat!(
+    ///     "    <edge id=\"e0\" source=\"n0\" target=\"n1\">\n",
+    ///     "      <data key=\"kind\">build</data>\n",
+    ///     "    </edge>\n",
+    /// )));
+    /// assert!(graphml.ends_with("  </graph>\n</graphml>\n"));
+    ///
+    /// // Every element that is opened is closed
+    /// for element in ["graphml", "graph", "node", "edge", "data"] {
+    ///     let opened = graphml.matches(&format!("<{} ", element)).count()
+    ///         + graphml.matches(&format!("<{}>", element)).count();
+    ///     assert_eq!(opened, graphml.matches(&format!("</{}>", element)).count());
+    /// }
+    /// ```
+    pub fn to_graphml(&self) -> String {
+        let mut graphml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>\n",
+            "  <key id=\"url\" for=\"node\" attr.name=\"url\" attr.type=\"string\"/>\n",
+            "  <key id=\"version\" for=\"node\" attr.name=\"version\" attr.type=\"string\"/>\n",
+            "  <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "  <graph id=\"dependencies\" edgedefault=\"directed\">\n",
+        ));
+        let graph = self.ordered_graph();
+        for node in graph.node_indices() {
+            let name = &graph[node].0;
+            if self.is_hidden_root(name) {
+                continue;
+            }
+            let package = self.packages.get(name);
+            graphml.push_str(&format!(
+                concat!(
+                    "    <node id=\"n{}\">\n",
+                    "      <data key=\"name\">{}</data>\n",
+                    "      <data key=\"url\">{}</data>\n",
+                    "      <data key=\"version\">{}</data>\n",
+                    "    </node>\n",
+                ),
+                node.index(),
+                xml_escape(self.display_name(name)),
+                xml_escape(package.map_or("", |package| &package.url)),
+                xml_escape(package.map_or("", |package| &package.version)),
+            ));
+        }
+        for edge in graph.edge_references() {
+            let (source, target) = (edge.source(), edge.target());
+            if self.is_hidden_root(&graph[source].0) || self.is_hidden_root(&graph[target].0) {
+                continue;
+            }
+            graphml.push_str(&format!(
+                concat!(
+                    "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\">\n",
+                    "      <data key=\"kind\">{}</data>\n",
+                    "    </edge>\n",
+                ),
+                edge.id().index(),
+                source.index(),
+                target.index(),
+                edge.weight().label(),
+            ));
+        }
+        graphml.push_str("  </graph>\n</graphml>\n");
+        graphml
+    }
+
+    /// Generates a DOT format representation of the graph, with labeled edges pointing from
+    /// each crate to its dependencies.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `String` containing the DOT format representation.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with_style(EdgeStyle {
+            direction: EdgeDirection::DependsOn,
+            labels: true,
+        })
+    }
+
+    /// Generates a DOT format representation of the graph, drawing the edges in the given style.
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - The direction of the edges and whether they are labeled.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `String` containing the DOT format representation, without the enclosing
+    /// `digraph { }`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{DependencyGraph, EdgeDirection, EdgeStyle};
+    /// use depth::package::Package;
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&Package::new(
+    ///     "app".to_string(),
+    ///     "".to_string(),
+    ///     vec![("serde".to_string(), "".to_string())],
+    ///     false,
+    /// ));
+    ///
+    /// let dot = graph.to_dot_with_style(EdgeStyle::default());
+    /// assert!(dot.contains("0 -> 1 [ ]"));
+    ///
+    /// let dot = graph.to_dot_with_style(EdgeStyle {
+    ///     direction: EdgeDirection::DependedOnBy,
+    ///     labels: true,
+    /// });
+    /// assert!(dot.contains("1 -> 0 [ label = \"\\\"depended on by\\\"\" ]"));
+    /// ```
+    pub fn to_dot_with_style(&self, style: EdgeStyle) -> String {
+        let mut graph = self.ordered_graph().filter_map(
+            |_, (name, url)| {
+                let link = self.link(name, url).into_owned();
+                (!self.is_hidden_root(name)).then(|| (self.display_name(name).to_string(), link))
+            },
+            |_, &kind| {
+                Some(DotEdge {
+                    label: style.direction.label(),
+                    kind,
+                })
+            },
+        );
+        if style.direction == EdgeDirection::DependedOnBy {
+            graph.reverse();
+        }
+        let config: &[Config] = if style.labels {
+            &[Config::GraphContentOnly]
+        } else {
+            &[Config::GraphContentOnly, Config::EdgeNoLabel]
+        };
+        let edge_attributes = |_, edge: EdgeReference<'_, DotEdge>| match edge.weight().kind {
+            DependencyKind::Normal => String::new(),
+            DependencyKind::Build => "style = dotted ".to_string(),
+            DependencyKind::Dev => "style = dashed ".to_string(),
+        };
+        format!(
+            "{:?}",
+            Dot::with_attr_getters(&graph, config, &edge_attributes, &|_, _| String::new())
+        )
+    }
+}
+
+/// An edge of the DOT output, written as its label and drawn in the style of its kind.
+struct DotEdge {
+    label: &'static str,
+    kind: DependencyKind,
+}
+
+impl fmt::Debug for DotEdge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.label, f)
+    }
+}
+
+/// Escapes the characters with a meaning in XML text and attribute values.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Returns the Mermaid node ID of a crate, `c_` then its name with `-` and `.` replaced, which
+/// Mermaid rejects in IDs. The prefix keeps crates named like a keyword of Mermaid, such as
+/// `end` or `graph`, from being read as one.
+///
+/// Crates.io forbids names only differing by `-` and `_`, so IDs are unique within a graph.
+fn mermaid_id(crate_name: &str) -> String {
+    let name: String = crate_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("c_{}", name)
+}
+
+/// The order in which the dependencies of a package are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SortOrder {
+    /// Alphabetically by crate name.
+    #[default]
+    Name,
+    /// By the number of crates in their subtree, heaviest first.
+    Size,
+    /// By resolved version (or requirement, for crates that were not fetched), lowest first.
+    Version,
+    /// By number of downloads, most downloaded first, crates with an unknown count last.
+    Downloads,
+    /// In the order the crates were declared and fetched in, which can change between runs.
+    Insertion,
+}
+
+/// Counts of the package lookups made while fetching a graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FetchStats {
+    /// The number of times a package was looked up, once per edge of the tree plus the root.
+    pub lookups: usize,
+    /// The lookups answered by an already fetched package, without requests.
+    pub cache_hits: usize,
+}
+
+/// The links printed next to crates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum LinkStyle {
+    /// The homepage set by the crate, which is often empty.
+    #[default]
+    Homepage,
+    /// The documentation of the resolved version on docs.rs.
+    Docs,
+    /// The page of the resolved version on crates.io.
+    Crates,
+}
+
+/// The colors of the levels of the default tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ColorScheme {
+    /// Green and white levels, for dark terminals.
+    #[default]
+    Dark,
+    /// Blue and black levels, for light terminals.
+    Light,
+    /// No colors nor escape codes, e.g. for piped output.
+    None,
+}
+
+impl ColorScheme {
+    /// Returns the ANSI color code of the crates at the given depth, alternating between two
+    /// colors, or `None` when nothing is colored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::ColorScheme;
+    ///
+    /// assert_eq!(ColorScheme::Dark.color_code(0), Some(32));
+    /// assert_eq!(ColorScheme::Dark.color_code(1), Some(37));
+    /// assert_eq!(ColorScheme::Light.color_code(2), Some(34));
+    /// assert_eq!(ColorScheme::None.color_code(0), None);
+    /// ```
+    pub fn color_code(self, depth: usize) -> Option<u8> {
+        let (even, odd) = match self {
+            ColorScheme::Dark => (32, 37),
+            ColorScheme::Light => (34, 30),
+            ColorScheme::None => return None,
+        };
+        Some(if depth.is_multiple_of(2) { even } else { odd })
+    }
+
+    /// Returns the scheme to print with: `None` unless colors were asked for, stdout is a
+    /// terminal and the `NO_COLOR` environment variable is unset or empty.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::ColorScheme;
+    ///
+    /// assert_eq!(ColorScheme::Light.enabled_if(false), ColorScheme::None);
+    /// ```
+    pub fn enabled_if(self, colored: bool) -> ColorScheme {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        if colored && !no_color && io::stdout().is_terminal() {
+            self
+        } else {
+            ColorScheme::None
+        }
+    }
+}
+
+/// The direction of the edges drawn by the graph formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum EdgeDirection {
+    /// From each crate to the crates it depends on.
+    #[default]
+    DependsOn,
+    /// From each crate to the crates depending on it.
+    DependedOnBy,
+}
+
+impl EdgeDirection {
+    /// Returns the label of the edges drawn in this direction.
+    pub fn label(self) -> &'static str {
+        match self {
+            EdgeDirection::DependsOn => "depends on",
+            EdgeDirection::DependedOnBy => "depended on by",
+        }
+    }
+}
+
+/// How the edges of the graph formats are drawn; the tree formats ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EdgeStyle {
+    /// The direction the edges point in.
+    pub direction: EdgeDirection,
+    /// Label each edge with its direction.
+    pub labels: bool,
+}
+
+/// Options controlling how a dependency tree is laid out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeOptions {
+    /// Fold chains of crates with a single dependency onto one line.
+    pub collapse_chains: bool,
+    /// The order in which the dependencies of each package are printed.
+    pub sort: SortOrder,
+    /// Annotate each crate with the size of its published tarball.
+    pub show_size: bool,
+    /// List the features of each crate under it, with the optional dependencies they enable.
+    pub show_features: bool,
+    /// Leave out the link of each crate in the default tree.
+    pub compact: bool,
+    /// Fit the lines of the default tree to this many columns.
+    pub max_width: Option<usize>,
+}
+
+/// A crate at a resolved version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateVersion {
+    /// The name of the crate.
+    pub name: String,
+    /// The resolved version of the crate.
+    pub version: String,
+}
+
+impl CrateVersion {
+    /// Returns the name and version of a package.
+    fn of(package: &Package) -> Self {
+        CrateVersion {
+            name: package.name.clone(),
+            version: package.version.clone(),
+        }
+    }
+}
+
+/// A crate of the tree with the number of crates depending on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedCrate {
+    /// The name of the crate.
+    pub name: String,
+    /// The number of distinct crates of the tree depending on it.
+    pub dependents: usize,
+}
+
+/// A crate of the tree built in more than one version, see `DependencyGraph::duplicate_versions`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateVersions {
+    /// The name of the crate.
+    pub name: String,
+    /// The versions the requirements on the crate resolve to, lowest first.
+    pub versions: Vec<RequiredVersion>,
+}
+
+/// A version of a crate and the crates of the tree requiring it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequiredVersion {
+    /// The version, the highest published one matching the requirements.
+    pub version: String,
+    /// The crates whose requirement resolves to this version, sorted by name.
+    pub required_by: Vec<String>,
+}
+
+/// The whole graph as written by `DependencyGraph::to_json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphJson {
+    /// Every crate of the graph, sorted by name.
+    pub nodes: Vec<GraphNode>,
+    /// Every dependency between two crates, sorted by the names of their crates.
+    pub edges: Vec<GraphEdge>,
+    /// Whether the fetch stopped at `FetchOptions::max_nodes`, leaving crates unfetched.
+    pub truncated: bool,
+}
+
+/// A crate of the graph, as written by `DependencyGraph::to_json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphNode {
+    /// The name of the crate.
+    pub name: String,
+    /// The resolved version, if the crate was fetched.
+    pub version: Option<String>,
+    /// The homepage of the crate, if it was fetched and declares one.
+    pub url: Option<String>,
+    /// Whether the crate is internal, e.g. a sysroot crate, see `CrateFilter::is_internal`.
+    pub internal: bool,
+    /// Whether the crate was fetched.
+    pub state: NodeState,
+    /// The markers of the crate, e.g. `truncated`, see `DependencyGraph::markers`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<String>,
+}
+
+/// Whether a crate of the graph was fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeState {
+    /// The crate and its dependencies were fetched.
+    Fetched,
+    /// The crate is a leaf left unfetched, below the requested depth, pruned by a filter or
+    /// truncated.
+    Unfetched,
+    /// The crate could not be fetched, see `DependencyGraph::unavailable`.
+    Unavailable,
+}
+
+/// A dependency of a crate on another, as written by `DependencyGraph::to_json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    /// The crate declaring the dependency.
+    pub from: String,
+    /// The crate depended on.
+    pub to: String,
+    /// The kind of the dependency.
+    pub kind: DependencyKind,
+    /// The version requirement of the dependency, empty if it is not known.
+    pub req: String,
+}
+
+/// The size and the depth of a fetched graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphStats {
+    /// The number of crates in the graph, fetched or not, one node per crate.
+    pub node_count: usize,
+    /// The number of distinct dependency edges between them.
+    pub edge_count: usize,
+    /// The length in edges of the longest chain of dependencies, cycles being cut where they
+    /// close.
+    pub max_depth: usize,
+    /// The number of distinct crates that were fetched.
+    pub unique_crates: usize,
+}
+
+/// A crate resolved to a different version in two graphs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionChange {
+    /// The name of the crate.
+    pub name: String,
+    /// The version in the older graph.
+    pub from: String,
+    /// The version in the newer graph.
+    pub to: String,
+}
+
+/// The difference between the crates of two graphs, see `DependencyGraph::diff`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphDiff {
+    /// Crates only in the newer graph.
+    pub added: Vec<CrateVersion>,
+    /// Crates only in the older graph.
+    pub removed: Vec<CrateVersion>,
+    /// Crates in both graphs, at different versions.
+    pub changed: Vec<VersionChange>,
+}
+
+impl GraphDiff {
+    /// Returns `true` if both graphs have the same crates at the same versions.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Writes the difference one crate per line, prefixed with `+` when added, `-` when removed
+    /// and `~` when its version changed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{CrateVersion, GraphDiff};
+    ///
+    /// let diff = GraphDiff {
+    ///     added: vec![CrateVersion { name: "jiff".to_string(), version: "0.2.0".to_string() }],
+    ///     ..GraphDiff::default()
+    /// };
+    /// let mut output = Vec::new();
+    /// diff.write_text(&mut output).unwrap();
+    /// assert_eq!(String::from_utf8(output).unwrap(), "+ jiff v0.2.0\n");
+    /// ```
+    pub fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.write_text_colored(out, false)
+    }
+
+    /// Writes the difference like `write_text`, coloring the added crates in green and the
+    /// removed ones in red when `colored` is set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::{CrateVersion, GraphDiff};
+    ///
+    /// let diff = GraphDiff {
+    ///     removed: vec![CrateVersion { name: "time".to_string(), version: "0.1.45".to_string() }],
+    ///     ..GraphDiff::default()
+    /// };
+    /// let mut output = Vec::new();
+    /// diff.write_text_colored(&mut output, true).unwrap();
+    /// assert_eq!(String::from_utf8(output).unwrap(), "\x1b[31m- time v0.1.45\x1b[0m\n");
+    /// ```
+    pub fn write_text_colored(&self, out: &mut dyn Write, colored: bool) -> io::Result<()> {
+        if self.is_empty() {
+            return writeln!(out, "No crates added or removed.");
+        }
+        let (green, red, reset) = if colored {
+            ("\x1b[32m", "\x1b[31m", "\x1b[0m")
+        } else {
+            ("", "", "")
+        };
+        for added in &self.added {
+            writeln!(out, "{}+ {} v{}{}", green, added.name, added.version, reset)?;
+        }
+        for removed in &self.removed {
+            writeln!(
+                out,
+                "{}- {} v{}{}",
+                red, removed.name, removed.version, reset
+            )?;
+        }
+        for changed in &self.changed {
+            writeln!(
+                out,
+                "~ {} v{} -> v{}",
+                changed.name, changed.from, changed.to
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The traversal state of `DependencyGraph::write_cargo_tree`.
+struct CargoTreeWriter<'a> {
+    graph: &'a DependencyGraph,
+    max_depth: usize,
+    options: &'a TreeOptions,
+    /// The subtree size of each package, only computed when sorting by size.
+    subtree_sizes: HashMap<String, usize>,
+    /// The packages already printed, which are not expanded again.
+    visited_packages: HashSet<String>,
+    /// For each ancestor level, whether more siblings follow it, which decides between a `│`
+    /// and a blank in the indentation.
+    levels_continue: Vec<bool>,
+}
+
+impl<'a> CargoTreeWriter<'a> {
+    /// Recursively writes the dependencies of a package in the layout of `cargo tree`.
+    fn write_children(
+        &mut self,
+        package: &'a Package,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        if depth >= self.max_depth {
+            return Ok(());
+        }
+
+        let dependencies = self.sorted_dependencies(package);
+
+        for (i, dependency) in dependencies.iter().enumerate() {
+            let last = i + 1 == dependencies.len();
+            for &continues in self.levels_continue.iter() {
+                write!(out, "{}", if continues { "│   " } else { "    " })?;
+            }
+            write!(out, "{}", if last { "└── " } else { "├── " })?;
+
+            match self.graph.packages.get(&dependency.0) {
+                Some(child) => {
+                    let new = self.visited_packages.insert(child.name.clone());
+                    // Like cargo, only mark duplicates that actually hide dependencies
+                    let star = if new || child.dependencies.is_empty() {
+                        ""
+                    } else {
+                        " (*)"
+                    };
+                    let name = self.graph.display_name(&child.name);
+                    write!(out, "{} v{}", name, child.version)?;
+                    self.write_size(child, out)?;
+                    self.graph.write_markers(&child.name, out)?;
+                    DependencyGraph::write_kind(package.dependency_kind(&child.name), out)?;
+                    write!(out, "{}", star)?;
+                    let (child, child_depth) = if new {
+                        self.write_chain(child, depth, out)?
+                    } else {
+                        (child, depth)
+                    };
+                    writeln!(out)?;
+
+                    if new {
+                        self.levels_continue.push(!last);
+                        self.write_features(child, child_depth, out)?;
+                        self.write_children(child, child_depth + 1, out)?;
+                        self.levels_continue.pop();
+                    }
+                }
+                None => {
+                    let name = self.graph.display_name(&dependency.0);
+                    if self.graph.unavailable.contains_key(&dependency.0) {
+                        write!(out, "{} (unavailable)", name)?;
+                    } else {
+                        write!(out, "{} {}", name, dependency.1)?;
+                    }
+                    self.graph.write_markers(&dependency.0, out)?;
+                    DependencyGraph::write_kind(package.dependency_kind(&dependency.0), out)?;
+                    writeln!(out)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Follows a chain of packages that have exactly one dependency when chains are collapsed,
+    /// writing ` → name vX.Y.Z` for each folded package.
+    ///
+    /// # Returns
+    ///
+    /// The last package of the chain along with its depth.
+    fn write_chain(
+        &mut self,
+        mut package: &'a Package,
+        mut depth: usize,
+        out: &mut dyn Write,
+    ) -> io::Result<(&'a Package, usize)> {
+        if !self.options.collapse_chains {
+            return Ok((package, depth));
+        }
+
+        while depth + 1 < self.max_depth {
+            let dependencies = self.sorted_dependencies(package);
+            if dependencies.len() != 1 {
+                break;
+            }
+            let Some(next) = self.graph.packages.get(&dependencies[0].0) else {
+                break;
+            };
+            // Already printed crates end the chain and are printed with a `(*)` below it
+            if !self.visited_packages.insert(next.name.clone()) {
+                break;
+            }
+
+            let name = self.graph.display_name(&next.name);
+            write!(out, " → {} v{}", name, next.version)?;
+            self.write_size(next, out)?;
+            self.graph.write_markers(&next.name, out)?;
+            DependencyGraph::write_kind(package.dependency_kind(&next.name), out)?;
+            package = next;
+            depth += 1;
+        }
+
+        Ok((package, depth))
+    }
+
+    /// Writes the features of a package below it when features are shown, keeping the line down
+    /// to its dependencies when some are written.
+    fn write_features(
+        &self,
+        package: &Package,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        if !self.options.show_features {
+            return Ok(());
+        }
+        let children_follow = depth + 1 < self.max_depth && !package.dependencies.is_empty();
+        for (feature, enabled) in &package.features {
+            for &continues in self.levels_continue.iter() {
+                write!(out, "{}", if continues { "│   " } else { "    " })?;
+            }
+            write!(out, "{}", if children_follow { "│   " } else { "    " })?;
+            writeln!(out, "{}", DependencyGraph::feature_line(feature, enabled))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the tarball size of a package when sizes are shown, flagging heavyweight crates.
+    fn write_size(&self, package: &Package, out: &mut dyn Write) -> io::Result<()> {
+        match package.size {
+            Some(size) if self.options.show_size => {
+                let large = if size > LARGE_CRATE_SIZE {
+                    ", large"
+                } else {
+                    ""
+                };
+                write!(out, " ({}{})", format_size(size), large)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the dependencies of a package in the configured order, without duplicate names.
+    fn sorted_dependencies(&self, package: &'a Package) -> Vec<&'a (String, String)> {
+        let mut dependencies: Vec<&(String, String)> = package.dependencies.iter().collect();
+        self.graph.sort_dependencies(
+            &mut dependencies,
+            self.options.sort,
+            &self.subtree_sizes,
+            |dependency| (&dependency.0, &dependency.1),
+        );
+        let mut seen = HashSet::new();
+        dependencies.retain(|dependency| seen.insert(&dependency.0));
+
+        dependencies
     }
 }
@@ -0,0 +1,138 @@
+//! # error
+//!
+//! The `error` module defines `DepthError`, the error returned by the fetching and parsing
+//! functions of the library, so callers can match on why a tree could not be built instead of
+//! inspecting a message.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::dependency_graph::DependencyGraph;
+//! use depth::error::DepthError;
+//! use depth::package::{fetch_package_info, FetchOptions};
+//! use depth::registry::MemoryRegistry;
+//! use std::collections::HashMap;
+//!
+//! let result = fetch_package_info(
+//!     &("serde".to_string(), "".to_string()),
+//!     &mut HashMap::new(),
+//!     &mut DependencyGraph::new(),
+//!     &MemoryRegistry::new(),
+//!     2,
+//!     &FetchOptions::default(),
+//! );
+//!
+//! match result {
+//!     Err(DepthError::CrateNotFound(name)) => assert_eq!(name, "serde"),
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+use crate::registry::RegistryError;
+use crates_io_api::Error as CratesIoError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The number of versions listed by `DepthError::NoMatchingVersion`, the newest ones.
+const MAX_LISTED_VERSIONS: usize = 10;
+
+/// An error raised while fetching or parsing a dependency tree.
+#[derive(Debug)]
+pub enum DepthError {
+    /// The crate does not exist on the registry.
+    CrateNotFound(String),
+    /// The registry failed to return a crate, e.g. because access to it was denied, every
+    /// version of it is yanked or the request itself failed.
+    Api(RegistryError),
+    /// A manifest is not valid TOML.
+    Parse(toml::de::Error),
+    /// A file could not be read or written.
+    Io(io::Error),
+    /// The depth of a fetch is 0, while the root alone is at depth 1.
+    InvalidDepth(usize),
+    /// No published, non-yanked version of a crate matches the requested version.
+    NoMatchingVersion {
+        /// The name of the crate.
+        name: String,
+        /// The requested version requirement.
+        req: String,
+        /// The published, non-yanked versions of the crate, newest first.
+        available: Vec<String>,
+    },
+    /// The registry client could not be created from the options, e.g. because the user agent
+    /// is not a valid header value.
+    Client(String),
+}
+
+impl fmt::Display for DepthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DepthError::CrateNotFound(name) => write!(f, "crate '{}' was not found", name),
+            DepthError::Api(err) => err.fmt(f),
+            DepthError::Parse(err) => write!(f, "invalid manifest: {}", err),
+            DepthError::Io(err) => err.fmt(f),
+            DepthError::InvalidDepth(depth) => {
+                write!(f, "invalid depth {}, the depth must be at least 1", depth)
+            }
+            DepthError::NoMatchingVersion {
+                name,
+                req,
+                available,
+            } => {
+                write!(f, "no version of '{}' matches '{}'", name, req)?;
+                if available.is_empty() {
+                    return write!(f, ", every version is yanked");
+                }
+                let listed = &available[..available.len().min(MAX_LISTED_VERSIONS)];
+                write!(f, ", available versions: {}", listed.join(", "))?;
+                if available.len() > listed.len() {
+                    write!(f, " and {} more", available.len() - listed.len())?;
+                }
+                Ok(())
+            }
+            DepthError::Client(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for DepthError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DepthError::Api(err) => Some(err),
+            DepthError::Parse(err) => Some(err),
+            DepthError::Io(err) => Some(err),
+            DepthError::CrateNotFound(_)
+            | DepthError::InvalidDepth(_)
+            | DepthError::NoMatchingVersion { .. }
+            | DepthError::Client(_) => None,
+        }
+    }
+}
+
+impl From<RegistryError> for DepthError {
+    fn from(err: RegistryError) -> Self {
+        match err {
+            RegistryError::NotFound(name) => DepthError::CrateNotFound(name),
+            err => DepthError::Api(err),
+        }
+    }
+}
+
+impl From<CratesIoError> for DepthError {
+    fn from(err: CratesIoError) -> Self {
+        DepthError::Api(RegistryError::Api(err))
+    }
+}
+
+impl From<toml::de::Error> for DepthError {
+    fn from(err: toml::de::Error) -> Self {
+        DepthError::Parse(err)
+    }
+}
+
+impl From<io::Error> for DepthError {
+    fn from(err: io::Error) -> Self {
+        DepthError::Io(err)
+    }
+}
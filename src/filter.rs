@@ -0,0 +1,288 @@
+//! # filter
+//!
+//! The `filter` module decides which crates `depth` is allowed to fetch and traverse while
+//! building a dependency graph. Crates rejected by the filter are still shown in the tree,
+//! but as pruned leaf nodes: their own dependencies are never requested from Crates.io.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::filter::CrateFilter;
+//!
+//! let filter = CrateFilter::new(&["tokio*".to_string()]).unwrap();
+//! assert!(filter.allows("tokio-util"));
+//! assert!(!filter.allows("serde"));
+//! ```
+//!
+//! Internal crates, the sysroot crates and the crates matching an internal prefix (e.g. the
+//! private crates of a monorepo), are never fetched either, and are flagged as internal:
+//!
+//! ```rust
+//! use depth::filter::CrateFilter;
+//!
+//! let filter = CrateFilter::default().with_internal_prefixes(&["acme-".to_string()]);
+//! assert!(filter.is_internal("acme-billing"));
+//! assert!(filter.is_internal("core"));
+//! assert!(filter.is_internal("proc_macro"));
+//! // Published crates merely starting like a sysroot crate are not internal
+//! assert!(!filter.is_internal("stdweb"));
+//! assert!(!filter.is_internal("stderrlog"));
+//! assert!(!filter.is_internal("core-foundation"));
+//! assert!(!filter.is_internal("allocator-api2"));
+//! ```
+//!
+//! Internal crates are flagged as `[internal]` in the tree, or left out of it altogether:
+//!
+//! ```rust
+//! use depth::filter::CrateFilter;
+//!
+//! let filter = CrateFilter::default().with_hide_internal(true);
+//! assert!(filter.omits("std"));
+//! assert!(!filter.omits("stdweb"));
+//! ```
+//!
+//! Excluded crates, e.g. ubiquitous crates adding noise to a large graph, are not fetched and are
+//! left out of the graph along with their own dependencies, or kept as collapsed leaves:
+//!
+//! ```rust
+//! use depth::filter::CrateFilter;
+//!
+//! let patterns = ["serde*".to_string(), "libc".to_string()];
+//! let filter = CrateFilter::default().with_exclude(&patterns, false).unwrap();
+//! assert!(filter.omits("serde_json"));
+//! assert!(filter.omits("libc"));
+//! assert!(!filter.is_excluded("syn"));
+//!
+//! let filter = CrateFilter::default().with_exclude(&patterns, true).unwrap();
+//! assert!(filter.is_excluded("serde_json"));
+//! assert!(!filter.omits("serde_json"));
+//! ```
+//!
+//! Exclusion takes precedence over inclusion: a crate matching both an include and an exclude
+//! glob is excluded.
+//!
+//! ```rust
+//! use depth::build_dependency_graph_with_registry;
+//! use depth::filter::CrateFilter;
+//! use depth::package::FetchOptions;
+//! use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+//!
+//! let mut registry = MemoryRegistry::new();
+//! registry.add_crate(
+//!     CrateInfo::new("app", "1.0.0"),
+//!     vec![DependencyInfo::new("tokio", "^1"), DependencyInfo::new("tokio-util", "^0.7")],
+//! );
+//! registry.add_crate(CrateInfo::new("tokio", "1.40.0"), vec![]);
+//! registry.add_crate(CrateInfo::new("tokio-util", "0.7.12"), vec![]);
+//!
+//! let filter = CrateFilter::new(&["tokio*".to_string()])
+//!     .unwrap()
+//!     .with_exclude(&["tokio-*".to_string()], false)
+//!     .unwrap();
+//! assert!(filter.allows("tokio-util") && filter.is_excluded("tokio-util"));
+//!
+//! let options = FetchOptions {
+//!     filter,
+//!     ..FetchOptions::default()
+//! };
+//! let graph = build_dependency_graph_with_registry("app", 2, &options, &registry).unwrap();
+//! assert!(graph.package("tokio").is_some());
+//! assert!(graph.package("tokio-util").is_none());
+//! ```
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// A set of glob patterns restricting which crates are fetched.
+///
+/// An empty filter allows every crate. The root crate is never subject to the filter,
+/// since it is always fetched explicitly. A crate that is both included and excluded is
+/// excluded.
+#[derive(Debug, Clone, Default)]
+pub struct CrateFilter {
+    /// Crates whose names match one of these globs are fetched, the rest are pruned.
+    include: Option<GlobSet>,
+    /// Crates whose names start with one of these prefixes are internal.
+    internal_prefixes: Vec<String>,
+    /// Crates whose names match one of these globs are not fetched, nor their dependencies.
+    exclude: Option<GlobSet>,
+    /// Keep the excluded crates in the graph as collapsed leaves instead of leaving them out.
+    collapse_excluded: bool,
+    /// Leave the internal crates out of the graph instead of keeping them as flagged leaves.
+    hide_internal: bool,
+}
+
+/// The crates shipped with the Rust toolchain, which are never on Crates.io. Only these exact
+/// names are sysroot crates, published crates such as `stdweb` merely starting like them.
+const SYSROOT_CRATES: &[&str] = &["std", "core", "alloc", "proc_macro", "test"];
+
+impl CrateFilter {
+    /// Creates a new `CrateFilter` from a list of include globs.
+    ///
+    /// # Arguments
+    ///
+    /// * `include` - Glob patterns (e.g. `tokio*`) matched against crate names.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the filter or an error if one of the globs is invalid.
+    pub fn new(include: &[String]) -> Result<Self, globset::Error> {
+        Ok(CrateFilter {
+            include: build_glob_set(include)?,
+            ..CrateFilter::default()
+        })
+    }
+
+    /// Treats the crates whose names start with one of the given prefixes as internal.
+    pub fn with_internal_prefixes(mut self, prefixes: &[String]) -> Self {
+        self.internal_prefixes.extend_from_slice(prefixes);
+        self
+    }
+
+    /// Leaves the internal crates out of the graph, instead of keeping them as leaves flagged as
+    /// internal.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::build_dependency_graph_with_registry;
+    /// use depth::filter::CrateFilter;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "1.0.0"),
+    ///     vec![
+    ///         DependencyInfo::new("acme-billing", "^1"),
+    ///         DependencyInfo::new("stdweb", "^0.4"),
+    ///     ],
+    /// );
+    /// registry.add_crate(CrateInfo::new("stdweb", "0.4.20"), vec![]);
+    ///
+    /// let graph = |hide: bool| {
+    ///     let filter = CrateFilter::default()
+    ///         .with_internal_prefixes(&["acme-".to_string()])
+    ///         .with_hide_internal(hide);
+    ///     let options = FetchOptions {
+    ///         filter,
+    ///         ..FetchOptions::default()
+    ///     };
+    ///     build_dependency_graph_with_registry("app", 2, &options, &registry).unwrap()
+    /// };
+    ///
+    /// let flagged = graph(false);
+    /// assert_eq!(flagged.markers("acme-billing"), ["internal"]);
+    /// assert!(flagged.markers("stdweb").is_empty());
+    ///
+    /// let hidden = graph(true);
+    /// let app = hidden.package("app").unwrap();
+    /// assert_eq!(app.dependencies, vec![("stdweb".to_string(), "^0.4".to_string())]);
+    /// ```
+    pub fn with_hide_internal(mut self, hide: bool) -> Self {
+        self.hide_internal = hide;
+        self
+    }
+
+    /// Excludes the crates whose names match one of the given globs, e.g. `serde*` for every
+    /// crate starting with `serde`.
+    ///
+    /// # Arguments
+    ///
+    /// * `exclude` - Glob patterns matched against crate names.
+    /// * `collapse` - Keep the excluded crates as collapsed leaves, without their dependencies,
+    ///   instead of leaving them out of the graph.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the filter or an error if one of the globs is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::build_dependency_graph_with_registry;
+    /// use depth::filter::CrateFilter;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::MemoryRegistry;
+    ///
+    /// let registry = MemoryRegistry::from_edges(&[
+    ///     ("app", &["clap", "serde"]),
+    ///     ("clap", &[]),
+    ///     ("serde", &["serde_derive"]),
+    ///     ("serde_derive", &["syn"]),
+    ///     ("syn", &[]),
+    /// ]);
+    ///
+    /// let crates = |collapse: bool| {
+    ///     let filter = CrateFilter::default()
+    ///         .with_exclude(&["serde*".to_string()], collapse)
+    ///         .unwrap();
+    ///     let options = FetchOptions {
+    ///         filter,
+    ///         ..FetchOptions::default()
+    ///     };
+    ///     let graph = build_dependency_graph_with_registry("app", 4, &options, &registry).unwrap();
+    ///     let stats = graph.stats();
+    ///     (stats.node_count, stats.unique_crates)
+    /// };
+    ///
+    /// // Left out, serde and its dependencies are not in the graph
+    /// assert_eq!(crates(false), (2, 2));
+    /// // Collapsed, serde is a node of the graph but is not fetched
+    /// assert_eq!(crates(true), (3, 2));
+    /// ```
+    pub fn with_exclude(
+        mut self,
+        exclude: &[String],
+        collapse: bool,
+    ) -> Result<Self, globset::Error> {
+        self.exclude = build_glob_set(exclude)?;
+        self.collapse_excluded = collapse;
+        Ok(self)
+    }
+
+    /// Returns `true` if the crate is a sysroot crate or matches an internal prefix, in which
+    /// case it is not fetched from the registry.
+    pub fn is_internal(&self, crate_name: &str) -> bool {
+        SYSROOT_CRATES.contains(&crate_name)
+            || self
+                .internal_prefixes
+                .iter()
+                .any(|prefix| crate_name.starts_with(prefix.as_str()))
+    }
+
+    /// Returns `true` if the crate with the given name may be fetched.
+    pub fn allows(&self, crate_name: &str) -> bool {
+        match &self.include {
+            Some(include) => include.is_match(crate_name),
+            None => true,
+        }
+    }
+
+    /// Returns `true` if the crate is excluded, in which case it is not fetched.
+    pub fn is_excluded(&self, crate_name: &str) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(crate_name))
+    }
+
+    /// Returns `true` if the crate is left out of the graph: excluded rather than collapsed, or
+    /// internal while internal crates are hidden.
+    pub fn omits(&self, crate_name: &str) -> bool {
+        (!self.collapse_excluded && self.is_excluded(crate_name))
+            || (self.hide_internal && self.is_internal(crate_name))
+    }
+}
+
+/// Compiles a list of glob patterns into a `GlobSet`, or `None` if the list is empty.
+fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>, globset::Error> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+
+    Ok(Some(builder.build()?))
+}
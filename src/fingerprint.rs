@@ -0,0 +1,69 @@
+//! # fingerprint
+//!
+//! The `fingerprint` module computes a stable hash of a resolved dependency tree, the SHA-256 of
+//! its sorted `name version` pairs. Fetch order does not affect it, so two runs print the same
+//! fingerprint exactly when they resolved the same set of crates, which lets CI skip expensive
+//! steps when nothing changed.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::dependency_graph::DependencyGraph;
+//! use depth::fingerprint::fingerprint;
+//! use depth::package::Package;
+//!
+//! let crate_at = |name: &str, version: &str| {
+//!     let mut package = Package::new(name.to_string(), "".to_string(), vec![], false);
+//!     package.version = version.to_string();
+//!     package
+//! };
+//! let graph_of = |packages: &[Package]| {
+//!     let mut graph = DependencyGraph::new();
+//!     for package in packages {
+//!         graph.add_package_to_graph(package);
+//!     }
+//!     graph
+//! };
+//!
+//! let serde = crate_at("serde", "1.0.197");
+//! let syn = crate_at("syn", "2.0.48");
+//! let fetched = fingerprint(&graph_of(&[serde.clone(), syn.clone()]));
+//!
+//! // The order crates were fetched in does not matter, their versions do
+//! assert_eq!(fetched, fingerprint(&graph_of(&[syn.clone(), serde])));
+//! assert_ne!(fetched, fingerprint(&graph_of(&[syn, crate_at("serde", "1.0.198")])));
+//! assert_eq!(fetched.len(), 64);
+//!
+//! // An empty graph hashes an empty input
+//! assert_eq!(
+//!     fingerprint(&DependencyGraph::new()),
+//!     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+//! );
+//! ```
+
+use crate::dependency_graph::DependencyGraph;
+use sha2::{Digest, Sha256};
+
+/// Returns the fingerprint of the crates fetched into a graph, as a lowercase hex SHA-256.
+///
+/// Only the name and resolved version of each crate are hashed, sorted by name then version.
+pub fn fingerprint(graph: &DependencyGraph) -> String {
+    let mut crates: Vec<(&str, &str)> = graph
+        .packages()
+        .into_iter()
+        .map(|package| (package.name.as_str(), package.version.as_str()))
+        .collect();
+    crates.sort_unstable();
+
+    let mut input = String::new();
+    for (name, version) in crates {
+        input.push_str(name);
+        input.push(' ');
+        input.push_str(version);
+        input.push('\n');
+    }
+    Sha256::digest(input.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -26,12 +26,24 @@
 //! depth -c crate_name
 //! ```
 //!
+//! # Cargo Features
+//!
+//! - **`cli`** (enabled by default): The `depth` binary and its `clap` based command-line parsing.
+//!   Library consumers can disable it with `default-features = false` to only pull in the
+//!   library API (`DependencyGraph`, `Package` and the fetch functions).
+//! - **`msgpack`**: A compact MessagePack output format (`--format msgpack`) for services
+//!   caching or transmitting many trees. Its output is meant for machine consumption only.
+//! - **`async`**: `DependencyGraph::fetch_dependency_tree_async`, which fetches the crates of a
+//!   tree concurrently on `tokio` for applications already running an async runtime. The
+//!   default fetch stays synchronous and does not depend on `tokio`.
+//!
 //! # Key Features
 //!
 //! The `depth` crate offers the following key features:
 //!
 //! - **Fetch and Visualize Dependency Tree**: Fetch and visualize the dependency tree for a given Rust package using the Crates.io API.
 //! - **Command-Line Tool**: Use the `depth` command-line tool to interactively explore and visualize dependencies.
+//! - **Library API**: Fetch a tree without printing it with `build_dependency_graph`, then render or analyze the returned `DependencyGraph` yourself.
 //!
 //! # GitHub Repository
 //!
@@ -43,20 +55,296 @@
 //! please engage with the project on [GitHub](https://github.com/wiseaidev/depth).
 //! Your contributions help improve this crate for the community.
 
+pub mod audit;
+pub mod cache;
+#[cfg(feature = "cli")]
 pub mod cli;
+pub mod config;
 pub mod dependency_graph;
+pub mod error;
+pub mod filter;
+pub mod fingerprint;
+pub mod lock;
+pub mod logger;
+pub mod manifest;
+pub mod notes;
 pub mod package;
+mod progress;
+pub mod registry;
+pub mod render;
+pub mod report;
+pub mod semver_util;
+pub mod text;
+pub mod tui;
 
+use log::{info, warn};
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use audit::{Advisory, AdvisoryDatabase, OsvDatabase};
+use dependency_graph::{
+    ColorScheme, DependencyGraph, EdgeStyle, GraphDiff, LinkStyle, TreeOptions,
+};
+use fingerprint::fingerprint;
+use lock::VersionLock;
+use manifest::download_manifest;
+use notes::Notes;
+use package::{
+    fetch_manifest_info, fetch_reverse_dependencies, format_count, parse_manifest_info,
+    FetchOptions, Package,
+};
+use registry::{
+    crate_not_found, crates_io_client, detect_owner_change, resolve_version, ApiDump,
+    ClientOptions, ClientPool, CrateRegistry, MemoryRegistry, OwnerChange, TimedRegistry,
+};
+use render::{renderer, SummaryStream};
+use report::{Benchmark, Blame, Contributors, CrawlEstimate, Report};
+use semver::{Version, VersionReq};
+
+/// The formats a dependency tree can be visualized in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OutputFormat {
+    /// The colored tree printed by default.
+    #[default]
+    Tree,
+    /// The text layout of `cargo tree`, with `v` prefixed versions and `(*)` dedup markers.
+    CargoTree,
+    /// The analysis report as JSON, for scripts and CI.
+    Json,
+    /// A flat, sorted JSON array with one record per crate, to store a resolved dependency set.
+    FlatJson,
+    /// The whole graph as JSON nodes and edges, including crates left unfetched, to rebuild it
+    /// in another tool.
+    GraphJson,
+    /// Every fetched crate and its version, one per line, for audits such as license checks.
+    List,
+    /// A self-contained HTML page with a collapsible tree, linking each crate to crates.io.
+    Html,
+    /// The whole graph in the DOT language, to be laid out by Graphviz.
+    Dot,
+    /// The whole graph as an SVG image, laid out by the `dot` binary of Graphviz.
+    Svg,
+    /// The whole graph as a Mermaid flowchart, to paste in Markdown.
+    Mermaid,
+    /// The whole graph as GraphML, to import in graph analysis tools such as Gephi or yEd.
+    Graphml,
+    /// The analysis report as compact binary MessagePack, for machine consumption only.
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+}
+
+impl OutputFormat {
+    /// Returns the name the format is chosen by with `--format`.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Tree => "tree",
+            OutputFormat::CargoTree => "cargo-tree",
+            OutputFormat::Json => "json",
+            OutputFormat::FlatJson => "flat-json",
+            OutputFormat::GraphJson => "graph-json",
+            OutputFormat::List => "list",
+            OutputFormat::Html => "html",
+            OutputFormat::Dot => "dot",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Mermaid => "mermaid",
+            OutputFormat::Graphml => "graphml",
+            #[cfg(feature = "msgpack")]
+            OutputFormat::Msgpack => "msgpack",
+        }
+    }
+
+    /// Returns the one-line description printed by `--list-formats`.
+    pub fn description(self) -> &'static str {
+        match self {
+            OutputFormat::Tree => "The colored tree printed by default",
+            OutputFormat::CargoTree => "The text layout of `cargo tree`",
+            OutputFormat::Json => "The analysis report as JSON, for scripts and CI",
+            OutputFormat::FlatJson => "A flat, sorted JSON array with one record per crate",
+            OutputFormat::GraphJson => "The whole graph as JSON nodes and edges",
+            OutputFormat::List => "Every crate and its version, one per line",
+            OutputFormat::Html => "A self-contained HTML page with a collapsible tree",
+            OutputFormat::Dot => "The whole graph in the DOT language of Graphviz",
+            OutputFormat::Svg => "The whole graph as an SVG image, laid out by Graphviz",
+            OutputFormat::Mermaid => "The whole graph as a Mermaid flowchart for Markdown",
+            OutputFormat::Graphml => "The whole graph as GraphML for Gephi or yEd",
+            #[cfg(feature = "msgpack")]
+            OutputFormat::Msgpack => "The analysis report as compact binary MessagePack",
+        }
+    }
+}
 
-use dependency_graph::DependencyGraph;
+/// Options controlling how a fetched dependency tree is visualized.
+#[derive(Debug, Clone, Default)]
+pub struct VisualizeOptions {
+    /// The format the dependency tree is printed in.
+    pub format: OutputFormat,
+    /// Print the given number of crates that took the longest to fetch.
+    pub timings: Option<usize>,
+    /// The layout of the tree, collapsing chains and sizes only apply to the `cargo-tree` format.
+    pub tree: TreeOptions,
+    /// Report the direct dependencies solely responsible for pulling in this crate.
+    pub blame: Option<String>,
+    /// Print only the leaf crates of the tree as a flat sorted list instead of the tree.
+    pub leaves_only: bool,
+    /// Display aliases of crates, keyed by real crate name, applied to the rendered labels.
+    pub aliases: HashMap<String, String>,
+    /// Report the summed tarball size of the fetched crates.
+    pub total_size: bool,
+    /// Flag crates whose latest version was published by someone else than the previous one.
+    pub owner_changes: bool,
+    /// Annotate each crate with the number of crates depending on it.
+    pub show_rdeps: bool,
+    /// Annotate each crate with its number of downloads on the registry.
+    pub show_downloads: bool,
+    /// The links printed next to crates.
+    pub link: LinkStyle,
+    /// The colors of the levels of the default tree.
+    pub color: ColorScheme,
+    /// Print the throughput of the crawl instead of the tree.
+    pub benchmark: bool,
+    /// Only fetch the root and its direct dependencies, then print the estimated requests of
+    /// the crawl instead of the tree.
+    pub dry_run: bool,
+    /// Print the fingerprint of the resolved crates instead of the tree.
+    pub hash: bool,
+    /// Leave the root crate out of every output and analysis, only covering its dependencies.
+    pub no_root: bool,
+    /// Write the resolved versions to this lock file, on top of the pinned ones.
+    pub lock: Option<PathBuf>,
+    /// Flag crates that likely run a build script.
+    pub flag_build_scripts: bool,
+    /// Report the given number of crates most other crates of the tree depend on.
+    pub most_shared: Option<usize>,
+    /// Report the dependency cycles of the graph, e.g. between a crate and its dev-dependencies.
+    pub show_cycles: bool,
+    /// Report the longest dependency chain of the graph.
+    pub longest_path: bool,
+    /// Report the crates built in more than one version, with the crates requiring each version.
+    pub show_duplicates: bool,
+    /// Leave out the size and the depth reached of the graph printed after the tree.
+    pub quiet: bool,
+    /// Prune the graph to the paths from the root to this crate before printing it.
+    pub focus: Option<String>,
+    /// Report the distribution of the editions of the crates, as declared by a manifest or else
+    /// the newest each crate's `rust-version` allows.
+    pub editions: bool,
+    /// Check the fetched crates against the RustSec advisory database.
+    pub audit: bool,
+    /// The notes printed next to the crates they are kept about.
+    pub notes: Notes,
+    /// Report the distinct owners across the crates, and the owners of the most crates.
+    pub contributors: bool,
+    /// Write the raw crate and dependencies responses of Crates.io to this directory.
+    pub dump_api: Option<PathBuf>,
+    /// How the edges of the graph formats are drawn.
+    pub edge_style: EdgeStyle,
+    /// Write the rendered output to this file instead of stdout.
+    pub output: Option<PathBuf>,
+    /// Explore the fetched tree interactively in the terminal instead of printing it.
+    pub tui: bool,
+}
+
+/// The number of owners of the most crates reported by `--contributors`.
+const TOP_CONTRIBUTORS: usize = 10;
+
+/// Returns the depth of the fetch and render functions covering the given number of levels of
+/// dependencies, as passed to `--levels`.
+///
+/// A depth counts the root crate, alone at depth 1, while levels only count its dependencies:
+/// 0 levels is the root crate alone, 1 level adds its direct dependencies, 2 levels the
+/// dependencies of those too, and so on.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::build_dependency_graph_with_registry;
+/// use depth::depth_for_levels;
+/// use depth::dependency_graph::TreeOptions;
+/// use depth::package::FetchOptions;
+/// use depth::registry::MemoryRegistry;
+///
+/// let registry = MemoryRegistry::from_edges(&[
+///     ("app", &["clap", "serde"]),
+///     ("clap", &["clap_lex"]),
+///     ("clap_lex", &["memchr"]),
+///     ("memchr", &[]),
+///     ("serde", &[]),
+/// ]);
+///
+/// // The number of crates written in the tree, the root included
+/// let crates_at = |levels: usize| {
+///     let depth = depth_for_levels(levels);
+///     let graph =
+///         build_dependency_graph_with_registry("app", depth, &FetchOptions::default(), &registry)
+///             .unwrap();
+///     let app = graph.package("app").unwrap();
+///     let mut output = Vec::new();
+///     graph.write_cargo_tree(app, depth, &TreeOptions::default(), &mut output).unwrap();
+///     String::from_utf8(output).unwrap().lines().count()
+/// };
+///
+/// assert_eq!(crates_at(0), 1);
+/// assert_eq!(crates_at(1), 3);
+/// assert_eq!(crates_at(2), 4);
+/// assert_eq!(crates_at(3), 5);
+/// ```
+pub fn depth_for_levels(levels: usize) -> usize {
+    levels.saturating_add(1)
+}
+
+/// The depth of a fetch covering the whole tree, as passed with `--full`.
+///
+/// Every crate is fetched once, crates already fetched and crates closing a cycle are not
+/// fetched again, so shared dependencies do not make the fetch grow with the number of paths.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::dependency_graph::TreeOptions;
+/// use depth::package::FetchOptions;
+/// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+/// use depth::{build_dependency_graph_with_registry, FULL_DEPTH};
+///
+/// // 30 layers of 2 crates, each depending on both crates of the next layer: 2^30 paths
+/// let mut registry = MemoryRegistry::new();
+/// let mut add = |name: String, deps: Vec<String>| {
+///     let deps = deps.iter().map(|dep| DependencyInfo::new(dep, "^1")).collect();
+///     registry.add_crate(CrateInfo::new(&name, "1.0.0"), deps);
+/// };
+/// let layer = |level: usize| vec![format!("a{}", level), format!("b{}", level)];
+/// add("app".to_string(), layer(0));
+/// for level in 0..30 {
+///     for name in layer(level) {
+///         add(name, if level < 29 { layer(level + 1) } else { vec![] });
+///     }
+/// }
+///
+/// let options = FetchOptions::default();
+/// let graph = build_dependency_graph_with_registry("app", FULL_DEPTH, &options, &registry)
+///     .unwrap();
+/// assert_eq!(graph.packages().len(), 61);
+///
+/// // Shared crates are only expanded once in the tree as well
+/// let app = graph.package("app").unwrap();
+/// let mut output = Vec::new();
+/// graph.write_cargo_tree(app, FULL_DEPTH, &TreeOptions::default(), &mut output).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap().lines().count(), 119);
+/// ```
+pub const FULL_DEPTH: usize = usize::MAX;
 
 /// Visualizes the dependency tree for a given package.
 ///
 /// # Arguments
 ///
 /// * `package_name` - The name of the package to visualize.
-/// * `depth` - The depth up to which dependencies should be visualized.
+/// * `depth` - The depth up to which dependencies should be visualized, the root alone being at
+///   depth 1, see `depth_for_levels`.
+/// * `optional` - A boolean to scan optional dependencies only.
 ///
 /// # Returns
 ///
@@ -66,18 +354,1076 @@ pub fn visualize_dependency_tree(
     depth: usize,
     optional: bool,
 ) -> Result<(), Box<dyn Error>> {
+    let mut graph = build_dependency_graph(package_name, depth, optional)?;
+    let root_package = graph
+        .package(package_name)
+        .cloned()
+        .ok_or_else(|| format!("crate '{}' is not in the graph", package_name))?;
+    // The default options make no further requests
+    let client = TimedRegistry::new(MemoryRegistry::new());
+    print_dependency_tree(
+        &mut graph,
+        &root_package,
+        depth,
+        &VisualizeOptions::default(),
+        &client,
+        &[],
+    )
+}
+
+/// Fetches the dependency tree for a given package from Crates.io into a graph, without printing
+/// anything, to render or analyze it with `DependencyGraph::to_dot`, `to_json` or `stats`.
+///
+/// # Arguments
+///
+/// * `package_name` - The name of the package to fetch.
+/// * `depth` - The depth up to which dependencies should be fetched, the root alone being at
+///   depth 1, see `depth_for_levels`.
+/// * `optional` - A boolean to scan optional dependencies only.
+///
+/// # Returns
+///
+/// A Result containing the graph, or an error if the package does not exist or the fetch fails.
+pub fn build_dependency_graph(
+    package_name: &str,
+    depth: usize,
+    optional: bool,
+) -> Result<DependencyGraph, Box<dyn Error>> {
+    let options = FetchOptions::new(optional);
+    let client = ClientPool::new(&options.client, options.jobs)?;
+    build_dependency_graph_with_registry(package_name, depth, &options, &client)
+}
+
+/// Fetches the dependency tree for a given package from the given registry into a graph,
+/// without printing anything. See `build_dependency_graph`.
+///
+/// # Arguments
+///
+/// * `package_name` - The name of the package to fetch.
+/// * `depth` - The depth up to which dependencies should be fetched.
+/// * `options` - The options controlling which dependencies are listed and followed.
+/// * `registry` - The registry to fetch the crates from.
+///
+/// # Returns
+///
+/// A Result containing the graph, or an error if the package does not exist or the fetch fails.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::build_dependency_graph_with_registry;
+/// use depth::package::FetchOptions;
+/// use depth::registry::{CrateInfo, CrateNotFound, DependencyInfo, MemoryRegistry};
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![DependencyInfo::new("serde_derive", "^1")]);
+/// registry.add_crate(CrateInfo::new("serde_derive", "1.0.0"), vec![]);
+///
+/// let graph =
+///     build_dependency_graph_with_registry("serde", 2, &FetchOptions::default(), &registry)
+///         .unwrap();
+/// assert_eq!(graph.stats().unique_crates, 2);
+/// assert!(graph.to_dot().contains("serde_derive"));
+///
+/// let err = build_dependency_graph_with_registry("serd", 2, &FetchOptions::default(), &registry)
+///     .unwrap_err();
+/// assert_eq!(err.downcast_ref::<CrateNotFound>().unwrap().suggestions[0], "serde");
+/// ```
+pub fn build_dependency_graph_with_registry(
+    package_name: &str,
+    depth: usize,
+    options: &FetchOptions,
+    registry: &impl CrateRegistry,
+) -> Result<DependencyGraph, Box<dyn Error>> {
     let mut graph = DependencyGraph::new();
+    match graph.fetch_dependency_tree_with_registry(package_name, depth, options, registry)? {
+        Some(_) => Ok(graph),
+        None => Err(crate_not_found(registry, package_name).into()),
+    }
+}
+
+/// Visualizes the dependency tree for a given package using the given options.
+///
+/// # Arguments
+///
+/// * `package_name` - The name of the package to visualize.
+/// * `depth` - The depth up to which dependencies should be visualized.
+/// * `options` - The options controlling which dependencies are listed and followed.
+/// * `visualize_options` - The options controlling how the dependency tree is printed.
+///
+/// # Returns
+///
+/// A Result indicating success or an error if the visualization process fails.
+pub fn visualize_dependency_tree_with_options(
+    package_name: &str,
+    depth: usize,
+    options: &FetchOptions,
+    visualize_options: &VisualizeOptions,
+) -> Result<(), Box<dyn Error>> {
+    match &visualize_options.dump_api {
+        Some(dir) => visualize_crate(
+            package_name,
+            depth,
+            options,
+            visualize_options,
+            ApiDump::new(crates_io_client(&options.client)?, dir)?,
+        ),
+        None => visualize_crate(
+            package_name,
+            depth,
+            options,
+            visualize_options,
+            ClientPool::new(&options.client, options.jobs)?,
+        ),
+    }
+}
+
+/// Visualizes the dependency trees of several packages together, the crates they share being
+/// fetched once. The trees are printed side by side, below a header naming every package, and
+/// the report lists the crates shared by several of them.
+///
+/// # Arguments
+///
+/// * `package_names` - The names of the packages to visualize, a single package being
+///   visualized like `visualize_dependency_tree_with_options` does.
+/// * `depth` - The depth up to which the dependencies of each package should be visualized.
+/// * `options` - The options controlling which dependencies are listed and followed.
+/// * `visualize_options` - The options controlling how the dependency trees are printed.
+///
+/// # Returns
+///
+/// A Result indicating success or an error if one of the packages does not exist or the
+/// visualization process fails.
+pub fn visualize_dependency_trees(
+    package_names: &[String],
+    depth: usize,
+    options: &FetchOptions,
+    visualize_options: &VisualizeOptions,
+) -> Result<(), Box<dyn Error>> {
+    if let [package_name] = package_names {
+        return visualize_dependency_tree_with_options(
+            package_name,
+            depth,
+            options,
+            visualize_options,
+        );
+    }
+    match &visualize_options.dump_api {
+        Some(dir) => visualize_crates(
+            package_names,
+            depth,
+            options,
+            visualize_options,
+            ApiDump::new(crates_io_client(&options.client)?, dir)?,
+        ),
+        None => visualize_crates(
+            package_names,
+            depth,
+            options,
+            visualize_options,
+            ClientPool::new(&options.client, options.jobs)?,
+        ),
+    }
+}
+
+/// Visualizes the dependency trees of several packages fetched from the given registry.
+fn visualize_crates<R: CrateRegistry>(
+    package_names: &[String],
+    depth: usize,
+    options: &FetchOptions,
+    visualize_options: &VisualizeOptions,
+    registry: R,
+) -> Result<(), Box<dyn Error>> {
+    if visualize_options.focus.is_some() || visualize_options.dry_run || visualize_options.no_root {
+        return Err(
+            "--focus, --dry-run and --no-root only apply to the tree of a single crate".into(),
+        );
+    }
+    let mut graph = new_graph(visualize_options);
+    let client = TimedRegistry::new(registry);
+    let start = Instant::now();
 
-    if let Some(root_package) = graph.fetch_dependency_tree(package_name, depth, optional)? {
-        // Print dependencies
-        println!("Dependencies for package '{}':", package_name);
-        graph.print_dependencies_at_level(&root_package, 0, depth);
+    let fetched =
+        graph.fetch_dependency_trees_with_registry(package_names, depth, options, &client)?;
+    let mut roots = Vec::new();
+    for (package_name, root) in package_names.iter().zip(fetched) {
+        match root {
+            Some(root) => roots.push(root),
+            None => return Err(crate_not_found(&client, package_name).into()),
+        }
+    }
+
+    if let Some(path) = &visualize_options.lock {
+        save_lock(&graph, options, path, None)?;
+    }
+    let root_package = graph.add_merged_root(&roots);
+    if visualize_options.benchmark {
+        let benchmark = Benchmark::new(&graph, client.request_count(), start.elapsed());
+        return Ok(benchmark.write_text(&mut io::stdout())?);
+    }
+    if visualize_options.hash {
+        println!("{}", fingerprint(&graph));
+        return Ok(());
+    }
+    // The merged root is one level above the packages
+    print_dependency_tree(
+        &mut graph,
+        &root_package,
+        depth.saturating_add(1),
+        visualize_options,
+        &client,
+        package_names,
+    )
+}
+
+/// Visualizes the dependency tree of a package fetched from the given registry.
+fn visualize_crate<R: CrateRegistry>(
+    package_name: &str,
+    depth: usize,
+    options: &FetchOptions,
+    visualize_options: &VisualizeOptions,
+    registry: R,
+) -> Result<(), Box<dyn Error>> {
+    let mut graph = new_graph(visualize_options);
+    let client = TimedRegistry::new(registry);
+    let start = Instant::now();
 
-        // Visualize the graph (commented out for now)
-        // println!("{}", graph.to_dot());
+    // A dry run stops at the direct dependencies, whose dependency lists size the next level
+    let fetch_depth = if visualize_options.dry_run {
+        depth.min(2)
     } else {
-        eprintln!("Package not found or does not have a Cargo.toml file");
+        depth
+    };
+    let Some(root_package) =
+        graph.fetch_dependency_tree_with_registry(package_name, fetch_depth, options, &client)?
+    else {
+        return Err(crate_not_found(&client, package_name).into());
+    };
+    if visualize_options.dry_run {
+        let estimate = CrawlEstimate::new(&graph, depth, client.request_count());
+        return Ok(estimate.write_text(&mut io::stdout())?);
     }
 
+    if let Some(path) = &visualize_options.lock {
+        save_lock(&graph, options, path, None)?;
+    }
+    let (mut graph, root_package) = focus_graph(graph, root_package, visualize_options)?;
+    if visualize_options.no_root {
+        graph.hide_root(&root_package.name);
+    }
+    if visualize_options.benchmark {
+        let benchmark = Benchmark::new(&graph, client.request_count(), start.elapsed());
+        return Ok(benchmark.write_text(&mut io::stdout())?);
+    }
+    if visualize_options.hash {
+        println!("{}", fingerprint(&graph));
+        return Ok(());
+    }
+    print_dependency_tree(
+        &mut graph,
+        &root_package,
+        depth,
+        visualize_options,
+        &client,
+        &[],
+    )
+}
+
+/// Prints the crates a set of features of a crate pulls in on top of its default features.
+///
+/// # Arguments
+///
+/// * `package_name` - The name of the crate.
+/// * `depth` - The depth up to which dependencies should be compared.
+/// * `options` - The options of the fetch, with the features to evaluate in `features`.
+///
+/// # Returns
+///
+/// A Result indicating success or an error if either fetch fails.
+pub fn compare_default_features(
+    package_name: &str,
+    depth: usize,
+    options: &FetchOptions,
+) -> Result<(), Box<dyn Error>> {
+    let client = crates_io_client(&options.client)?;
+    let Some(diff) = feature_cost(&client, package_name, depth, options)? else {
+        return Err(crate_not_found(&client, package_name).into());
+    };
+
+    println!(
+        "Enabling {} on top of the default features of '{}' adds {} crates and removes {}:",
+        options.features.as_deref().unwrap_or_default().join(", "),
+        package_name,
+        diff.added.len(),
+        diff.removed.len()
+    );
+    Ok(diff.write_text(&mut io::stdout())?)
+}
+
+/// Prints the crates added, removed and changed in version by moving a crate from the version
+/// of the options, or its latest version, to another version.
+///
+/// # Arguments
+///
+/// * `package_name` - The name of the crate.
+/// * `depth` - The depth up to which dependencies should be compared.
+/// * `options` - The options of the fetch, with the version to move from in `version`.
+/// * `to` - The version to move to, an exact version or a requirement.
+/// * `colored` - Whether to color the added crates in green and the removed ones in red.
+///
+/// # Returns
+///
+/// A Result indicating success or an error if either fetch fails.
+pub fn compare_versions(
+    package_name: &str,
+    depth: usize,
+    options: &FetchOptions,
+    to: &VersionReq,
+    colored: bool,
+) -> Result<(), Box<dyn Error>> {
+    let client = crates_io_client(&options.client)?;
+    let Some(diff) = version_diff(&client, package_name, depth, options, to)? else {
+        return Err(crate_not_found(&client, package_name).into());
+    };
+
+    let from = options
+        .version
+        .as_ref()
+        .map_or("the latest version".to_string(), VersionReq::to_string);
+    println!(
+        "Moving '{}' from {} to {} adds {} crates, removes {} and changes {}:",
+        package_name,
+        from,
+        to,
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+    Ok(diff.write_text_colored(&mut io::stdout(), colored)?)
+}
+
+/// Fetches the tree of a crate at the version of the options, or its latest version, and at
+/// another version, and diffs the crates of both trees.
+///
+/// # Returns
+///
+/// The difference from the tree at the version of the options to the tree at `to`, or `None`
+/// if the crate does not exist.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::dependency_graph::VersionChange;
+/// use depth::package::FetchOptions;
+/// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry, VersionInfo};
+/// use depth::semver_util::parse_version_req;
+/// use depth::version_diff;
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("tokio", "1.40.0"), vec![DependencyInfo::new("bytes", "^1")]);
+/// for num in ["1.40.0", "1.38.1"] {
+///     registry.add_version("tokio", VersionInfo::new(num));
+/// }
+/// registry.add_crate(CrateInfo::new("bytes", "1.7.0"), vec![]);
+///
+/// let options = FetchOptions {
+///     version: Some(parse_version_req("~1.38").unwrap()),
+///     ..FetchOptions::default()
+/// };
+/// let to = parse_version_req("1.40.0").unwrap();
+/// let diff = version_diff(&registry, "tokio", 2, &options, &to).unwrap().unwrap();
+///
+/// assert!(diff.added.is_empty() && diff.removed.is_empty());
+/// assert_eq!(
+///     diff.changed,
+///     vec![VersionChange {
+///         name: "tokio".to_string(),
+///         from: "1.38.1".to_string(),
+///         to: "1.40.0".to_string(),
+///     }]
+/// );
+/// ```
+pub fn version_diff(
+    client: &impl CrateRegistry,
+    package_name: &str,
+    depth: usize,
+    options: &FetchOptions,
+    to: &VersionReq,
+) -> Result<Option<GraphDiff>, Box<dyn Error>> {
+    let mut from_graph = DependencyGraph::new();
+    if from_graph
+        .fetch_dependency_tree_with_registry(package_name, depth, options, client)?
+        .is_none()
+    {
+        return Ok(None);
+    }
+
+    let upgraded = FetchOptions {
+        version: Some(to.clone()),
+        ..options.clone()
+    };
+    let mut to_graph = DependencyGraph::new();
+    to_graph.fetch_dependency_tree_with_registry(package_name, depth, &upgraded, client)?;
+    Ok(Some(from_graph.diff(&to_graph)))
+}
+
+/// Fetches the tree of a crate with its default features only and with the features of the
+/// options, and diffs the crates of both trees.
+///
+/// # Returns
+///
+/// The difference from the default tree to the tree with the features, or `None` if the crate
+/// does not exist.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::feature_cost;
+/// use depth::package::FetchOptions;
+/// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+/// use std::collections::HashMap;
+///
+/// let optional = |name: &str| DependencyInfo {
+///     optional: true,
+///     ..DependencyInfo::new(name, "^1")
+/// };
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(
+///     CrateInfo {
+///         features: HashMap::from([("derive".to_string(), vec!["dep:serde_derive".to_string()])]),
+///         ..CrateInfo::new("serde", "1.0.0")
+///     },
+///     vec![optional("serde_derive")],
+/// );
+/// registry.add_crate(CrateInfo::new("serde_derive", "1.0.0"), vec![DependencyInfo::new("syn", "^2")]);
+/// registry.add_crate(CrateInfo::new("syn", "2.0.0"), vec![]);
+///
+/// let options = FetchOptions {
+///     features: Some(vec!["derive".to_string()]),
+///     ..FetchOptions::default()
+/// };
+/// let diff = feature_cost(&registry, "serde", 3, &options).unwrap().unwrap();
+/// let added: Vec<&str> = diff.added.iter().map(|added| added.name.as_str()).collect();
+/// assert_eq!(added, vec!["serde_derive", "syn"]);
+/// assert!(diff.removed.is_empty());
+/// ```
+pub fn feature_cost(
+    client: &impl CrateRegistry,
+    package_name: &str,
+    depth: usize,
+    options: &FetchOptions,
+) -> Result<Option<GraphDiff>, Box<dyn Error>> {
+    let defaults = FetchOptions {
+        features: Some(Vec::new()),
+        ..options.clone()
+    };
+    let mut default_graph = DependencyGraph::new();
+    if default_graph
+        .fetch_dependency_tree_with_registry(package_name, depth, &defaults, client)?
+        .is_none()
+    {
+        return Ok(None);
+    }
+
+    let mut featured_graph = DependencyGraph::new();
+    featured_graph.fetch_dependency_tree_with_registry(package_name, depth, options, client)?;
+    Ok(Some(default_graph.diff(&featured_graph)))
+}
+
+/// Prints the pinned crates of a lock file whose latest version on crates.io differs, as
+/// `name old -> new`, i.e. what updating the lock would change.
+///
+/// # Arguments
+///
+/// * `lock` - The lock to compare against the latest versions.
+/// * `client` - The settings of the Crates.io client.
+///
+/// # Returns
+///
+/// A Result indicating success or an error if the versions cannot be fetched.
+pub fn preview_lock_update(
+    lock: &VersionLock,
+    client: &ClientOptions,
+) -> Result<(), Box<dyn Error>> {
+    let updates = lock.pending_updates(&crates_io_client(client)?)?;
+    if updates.is_empty() {
+        println!("Every pinned crate is at its latest version.");
+    }
+    for update in updates {
+        println!("{} {} -> {}", update.name, update.from, update.to);
+    }
     Ok(())
 }
+
+/// Resolves the latest version of a crate on crates.io, without fetching its dependencies.
+///
+/// # Arguments
+///
+/// * `package_name` - The name of the crate to resolve.
+/// * `client` - The settings of the Crates.io client.
+///
+/// # Returns
+///
+/// A Result containing the highest published, non-yanked and stable version of the crate, or
+/// an error if the crate does not exist or has no such version.
+pub fn resolve_latest_version(
+    package_name: &str,
+    client: &ClientOptions,
+) -> Result<Version, Box<dyn Error>> {
+    resolve_version(&crates_io_client(client)?, package_name, &VersionReq::STAR)?
+        .ok_or_else(|| format!("crate '{}' has no published stable version", package_name).into())
+}
+
+/// Prints the inverted tree of a crate: the crates on Crates.io depending on it, the crates
+/// depending on those, and so on.
+///
+/// # Arguments
+///
+/// * `package_name` - The name of the crate whose dependents to print.
+/// * `depth` - The number of levels of the inverted tree, the crate itself included.
+/// * `client` - The settings of the Crates.io client.
+///
+/// # Returns
+///
+/// A Result indicating success or an error if the crate cannot be fetched.
+pub fn visualize_reverse_dependencies(
+    package_name: &str,
+    depth: usize,
+    client: &ClientOptions,
+) -> Result<(), Box<dyn Error>> {
+    let client = crates_io_client(client)?;
+    let mut graph = DependencyGraph::new();
+    let Some(root_package) = fetch_reverse_dependencies(package_name, &mut graph, &client, depth)?
+    else {
+        return Err(crate_not_found(&client, package_name).into());
+    };
+
+    if graph.packages().len() == 1 {
+        println!("No crate on Crates.io depends on '{}'.", package_name);
+        return Ok(());
+    }
+    Ok(graph.write_dependents(&root_package, depth, &mut io::stdout().lock())?)
+}
+
+/// Visualizes the dependency tree of a package whose `Cargo.toml` is downloaded from a URL,
+/// such as the GitHub raw URL of an unpublished branch.
+///
+/// # Arguments
+///
+/// * `manifest_url` - The URL of the root package's Cargo.toml.
+/// * `depth` - The depth up to which dependencies should be visualized.
+/// * `options` - The options controlling which dependencies are followed.
+/// * `visualize_options` - The options controlling how the dependency tree is printed.
+///
+/// # Returns
+///
+/// A Result indicating success or an error if the download or the visualization process fails.
+pub fn visualize_manifest_dependency_tree(
+    manifest_url: &str,
+    depth: usize,
+    options: &FetchOptions,
+    visualize_options: &VisualizeOptions,
+) -> Result<(), Box<dyn Error>> {
+    let cargo_toml_content = download_manifest(manifest_url)?;
+    visualize_manifest_content(&cargo_toml_content, depth, options, visualize_options)
+}
+
+/// Visualizes the dependency tree of a package from its local `Cargo.toml`, such as a private
+/// crate that is not published.
+///
+/// # Arguments
+///
+/// * `manifest_path` - The path of the root package's Cargo.toml.
+/// * `depth` - The depth up to which dependencies should be visualized.
+/// * `options` - The options controlling which dependencies are followed.
+/// * `visualize_options` - The options controlling how the dependency tree is printed.
+/// * `offline` - Only list the direct dependencies from the manifest, without any network
+///   access, instead of resolving them from crates.io.
+///
+/// # Returns
+///
+/// A Result indicating success or an error if the manifest cannot be read or the
+/// visualization process fails.
+pub fn visualize_local_manifest(
+    manifest_path: &Path,
+    depth: usize,
+    options: &FetchOptions,
+    visualize_options: &VisualizeOptions,
+    offline: bool,
+) -> Result<(), Box<dyn Error>> {
+    let cargo_toml_content = fs::read_to_string(manifest_path)
+        .map_err(|err| format!("failed to read '{}': {}", manifest_path.display(), err))?;
+    if !offline {
+        return visualize_manifest_content(&cargo_toml_content, depth, options, visualize_options);
+    }
+
+    let mut graph = new_graph(visualize_options);
+    let root_package = parse_manifest_info(&cargo_toml_content, &mut graph, options)?;
+    if depth > 2 {
+        info!("offline, only the direct dependencies are listed, not their own");
+    }
+    let (mut graph, root_package) = focus_graph(graph, root_package, visualize_options)?;
+    if visualize_options.no_root {
+        graph.hide_root(&root_package.name);
+    }
+    // Analyses querying the registry find nothing in an empty one
+    let client = TimedRegistry::new(MemoryRegistry::new());
+    print_dependency_tree(
+        &mut graph,
+        &root_package,
+        depth.min(2),
+        visualize_options,
+        &client,
+        &[],
+    )
+}
+
+/// Visualizes the dependency tree of the content of a manifest, its dependencies being fetched
+/// from crates.io.
+fn visualize_manifest_content(
+    cargo_toml_content: &str,
+    depth: usize,
+    options: &FetchOptions,
+    visualize_options: &VisualizeOptions,
+) -> Result<(), Box<dyn Error>> {
+    match &visualize_options.dump_api {
+        Some(dir) => visualize_manifest(
+            cargo_toml_content,
+            depth,
+            options,
+            visualize_options,
+            ApiDump::new(crates_io_client(&options.client)?, dir)?,
+        ),
+        None => visualize_manifest(
+            cargo_toml_content,
+            depth,
+            options,
+            visualize_options,
+            ClientPool::new(&options.client, options.jobs)?,
+        ),
+    }
+}
+
+/// Visualizes the dependency tree of a manifest, its dependencies being fetched from the given
+/// registry.
+fn visualize_manifest<R: CrateRegistry>(
+    cargo_toml_content: &str,
+    depth: usize,
+    options: &FetchOptions,
+    visualize_options: &VisualizeOptions,
+    registry: R,
+) -> Result<(), Box<dyn Error>> {
+    let mut graph = new_graph(visualize_options);
+    let client = TimedRegistry::new(registry);
+    let start = Instant::now();
+
+    let root_package = fetch_manifest_info(
+        cargo_toml_content,
+        &mut HashMap::new(),
+        &mut graph,
+        &client,
+        depth,
+        options,
+    )?;
+    if let Some(path) = &visualize_options.lock {
+        // The root of a manifest is not published, so its version cannot be pinned
+        save_lock(&graph, options, path, Some(&root_package.name))?;
+    }
+    if visualize_options.no_root {
+        graph.hide_root(&root_package.name);
+    }
+    if visualize_options.benchmark {
+        let benchmark = Benchmark::new(&graph, client.request_count(), start.elapsed());
+        return Ok(benchmark.write_text(&mut io::stdout())?);
+    }
+    if visualize_options.hash {
+        println!("{}", fingerprint(&graph));
+        return Ok(());
+    }
+    print_dependency_tree(
+        &mut graph,
+        &root_package,
+        depth,
+        visualize_options,
+        &client,
+        &[],
+    )
+}
+
+/// Creates an empty graph printing the way the given options ask for.
+fn new_graph(visualize_options: &VisualizeOptions) -> DependencyGraph {
+    let mut graph = DependencyGraph::new();
+    graph.set_aliases(visualize_options.aliases.clone());
+    graph.set_link_style(visualize_options.link);
+    graph.set_color_scheme(visualize_options.color);
+    graph.set_sort_order(visualize_options.tree.sort);
+    graph.set_show_features(visualize_options.tree.show_features);
+    graph.set_compact(visualize_options.tree.compact);
+    graph.set_max_width(visualize_options.tree.max_width);
+    graph
+}
+
+/// Prunes a fetched graph to the paths from its root to the crate of `--focus`, if set.
+///
+/// # Returns
+///
+/// The pruned graph and its root, or an error suggesting similar crates if the crate is not
+/// reachable from the root.
+fn focus_graph(
+    graph: DependencyGraph,
+    root_package: Package,
+    visualize_options: &VisualizeOptions,
+) -> Result<(DependencyGraph, Package), Box<dyn Error>> {
+    let Some(crate_name) = &visualize_options.focus else {
+        return Ok((graph, root_package));
+    };
+    let Some(focused) = graph.focus(&root_package.name, crate_name) else {
+        let mut message = format!(
+            "crate '{}' is not in the dependency tree of '{}'",
+            crate_name, root_package.name
+        );
+        let suggestions: Vec<String> = graph
+            .similar_crates(crate_name, 3)
+            .iter()
+            .map(|name| format!("'{}'", name))
+            .collect();
+        if !suggestions.is_empty() {
+            message.push_str(&format!(", did you mean {}?", suggestions.join(", ")));
+        }
+        return Err(message.into());
+    };
+    // The root now only lists the dependencies leading to the crate
+    let root_package = focused
+        .package(&root_package.name)
+        .cloned()
+        .unwrap_or(root_package);
+    Ok((focused, root_package))
+}
+
+/// Writes the pinned versions along with the newly resolved ones to a lock file.
+fn save_lock(
+    graph: &DependencyGraph,
+    options: &FetchOptions,
+    path: &Path,
+    unpublished_root: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let mut lock = options.lock.clone();
+    lock.extend_from_graph(graph);
+    if let Some(root) = unpublished_root {
+        if options.lock.version(root).is_none() {
+            lock.versions.remove(root);
+        }
+    }
+    lock.save(path)
+}
+
+/// Prints a fetched dependency tree, its report and the fetch timings. The crates shared by the
+/// trees of the given roots are reported when several crates were merged into the graph.
+fn print_dependency_tree<R: CrateRegistry>(
+    graph: &mut DependencyGraph,
+    root_package: &Package,
+    depth: usize,
+    visualize_options: &VisualizeOptions,
+    client: &TimedRegistry<R>,
+    merged_roots: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let owner_changes = visualize_options
+        .owner_changes
+        .then(|| flag_owner_changes(graph, client));
+    let reverse_dependencies = visualize_options
+        .show_rdeps
+        .then(|| annotate_reverse_dependencies(graph, client));
+    let downloads = visualize_options
+        .show_downloads
+        .then(|| annotate_downloads(graph));
+    let build_scripts = visualize_options
+        .flag_build_scripts
+        .then(|| flag_build_scripts(graph));
+    let editions = visualize_options.editions.then(|| flag_editions(graph));
+    let advisories = match visualize_options.audit {
+        true => Some(flag_advisories(
+            graph,
+            &OsvDatabase::new(&graph.fetch_options().client.user_agent)?,
+        )),
+        false => None,
+    };
+    for (name, note) in visualize_options.notes.iter() {
+        graph.add_marker(name, note);
+    }
+    let graph = &*graph;
+    if visualize_options.tui {
+        return Ok(tui::explore(graph, &root_package.name)?);
+    }
+
+    let mut report = Report::new(graph, root_package, depth);
+    report.owner_changes = owner_changes;
+    report.reverse_dependencies = reverse_dependencies;
+    report.downloads = downloads;
+    report.build_scripts = build_scripts;
+    report.editions = editions;
+    report.advisories = advisories;
+    report.colored = visualize_options.color != ColorScheme::None;
+    if let Some(crate_name) = &visualize_options.blame {
+        report.blame = Some(Blame::new(graph, root_package, crate_name));
+    }
+    if visualize_options.leaves_only {
+        report.leaves = Some(graph.leaves(root_package, depth));
+    }
+    if visualize_options.contributors {
+        report.contributors = Some(Contributors::collect(graph, client, TOP_CONTRIBUTORS));
+    }
+    if let Some(count) = visualize_options.most_shared {
+        report.most_shared = Some(graph.most_shared(count));
+    }
+    if visualize_options.show_cycles {
+        report.cycles = Some(graph.find_cycles());
+    }
+    if visualize_options.longest_path {
+        report.longest_path = Some(graph.longest_path());
+    }
+    if merged_roots.len() > 1 {
+        report.shared = Some(graph.shared_dependencies(merged_roots));
+    }
+    if visualize_options.show_duplicates {
+        report.duplicates = Some(graph.duplicate_versions(client));
+    }
+    if !visualize_options.quiet {
+        report.stats = Some(graph.stats());
+    }
+    if visualize_options.total_size {
+        report.total_size = Some(report.sum_sizes());
+    }
+
+    let renderer = renderer(visualize_options, depth, &report);
+    let mut out: Box<dyn Write> = match &visualize_options.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    renderer.render(graph, root_package, &mut out)?;
+    match renderer.summary_stream() {
+        SummaryStream::Stdout => report.write_text(&mut out)?,
+        SummaryStream::Stderr => report.write_text(&mut io::stderr())?,
+        SummaryStream::None => {}
+    }
+    out.flush()?;
+
+    if let Some(count) = visualize_options.timings {
+        eprintln!(
+            "Slowest crates to fetch ({} requests in total):",
+            client.request_count()
+        );
+        for (name, duration) in client.slowest(count) {
+            eprintln!(" - {}: {:.2?}", name, duration);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks every fetched crate for a change of publisher and marks the flagged ones in the graph.
+///
+/// This costs one more request per crate. Crates whose versions cannot be listed are skipped with
+/// a warning, since the check is best-effort.
+fn flag_owner_changes(
+    graph: &mut DependencyGraph,
+    client: &impl CrateRegistry,
+) -> BTreeMap<String, OwnerChange> {
+    let packages: Vec<(String, String)> = graph
+        .packages()
+        .into_iter()
+        .map(|package| (package.name.clone(), package.version.clone()))
+        .collect();
+
+    let mut owner_changes = BTreeMap::new();
+    for (name, version) in packages {
+        match detect_owner_change(client, &name, &version) {
+            Ok(Some(change)) => {
+                graph.add_marker(&name, "owner changed");
+                owner_changes.insert(name, change);
+            }
+            Ok(None) => {}
+            Err(err) => warn!("{}, skipping its owner check", err),
+        }
+    }
+    owner_changes
+}
+
+/// Fetches the reverse-dependency count of every fetched crate and annotates it in the graph.
+///
+/// This costs one more request per crate, each crate being counted once however many times it
+/// appears in the tree. Crates whose count cannot be fetched are skipped with a warning.
+fn annotate_reverse_dependencies(
+    graph: &mut DependencyGraph,
+    client: &impl CrateRegistry,
+) -> BTreeMap<String, u64> {
+    let names: Vec<String> = graph
+        .packages()
+        .into_iter()
+        .map(|package| package.name.clone())
+        .collect();
+
+    let mut counts = BTreeMap::new();
+    for name in names {
+        match client.reverse_dependency_count(&name) {
+            Ok(count) => {
+                graph.add_marker(&name, &format!("used by {} crates", count));
+                counts.insert(name, count);
+            }
+            Err(err) => warn!("{}, skipping its reverse dependencies", err),
+        }
+    }
+    counts
+}
+
+/// Annotates every fetched crate with its number of downloads, fetched along with the crate.
+///
+/// # Returns
+///
+/// The downloads of every crate whose count is known, keyed by crate name.
+fn annotate_downloads(graph: &mut DependencyGraph) -> BTreeMap<String, u64> {
+    let downloads: BTreeMap<String, u64> = graph
+        .packages()
+        .into_iter()
+        .filter_map(|package| Some((package.name.clone(), package.downloads?)))
+        .collect();
+
+    for (name, count) in &downloads {
+        graph.add_marker(name, &format!("{} downloads", format_count(*count)));
+    }
+    downloads
+}
+
+/// Marks the fetched crates that likely run a build script in the graph.
+///
+/// The registry does not tell whether a crate has a `build.rs`, so this is a heuristic: crates
+/// declaring build-dependencies, such as `cc` or `bindgen`, almost always have one. Crates with a
+/// build script but no build-dependencies are missed.
+///
+/// # Returns
+///
+/// The build-dependencies of every flagged crate, keyed by crate name.
+fn flag_build_scripts(graph: &mut DependencyGraph) -> BTreeMap<String, Vec<String>> {
+    let build_scripts: BTreeMap<String, Vec<String>> = graph
+        .packages()
+        .into_iter()
+        .filter(|package| !package.build_dependencies.is_empty())
+        .map(|package| (package.name.clone(), package.build_dependencies.clone()))
+        .collect();
+
+    for name in build_scripts.keys() {
+        graph.add_marker(name, "build script");
+    }
+    build_scripts
+}
+
+/// Checks every fetched crate against an advisory database and marks the affected crates in
+/// the graph with the identifiers of their advisories. Crates that cannot be checked are
+/// skipped with a warning.
+///
+/// # Returns
+///
+/// The advisories found, most severe first.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::audit::{Advisory, MemoryAdvisories, Severity};
+/// use depth::build_dependency_graph_with_registry;
+/// use depth::flag_advisories;
+/// use depth::package::FetchOptions;
+/// use depth::registry::MemoryRegistry;
+/// use depth::report::Report;
+///
+/// let registry = MemoryRegistry::from_edges(&[
+///     ("app", &["time", "smallvec"]),
+///     ("time", &[]),
+///     ("smallvec", &[]),
+/// ]);
+/// let mut graph =
+///     build_dependency_graph_with_registry("app", 2, &FetchOptions::default(), &registry).unwrap();
+///
+/// let advisory = |crate_name: &str, severity| Advisory {
+///     id: format!("RUSTSEC-{}", crate_name),
+///     crate_name: crate_name.to_string(),
+///     version: "1.0.0".to_string(),
+///     title: "".to_string(),
+///     severity: Some(severity),
+/// };
+/// let mut database = MemoryAdvisories::new();
+/// database.add(advisory("time", Severity::Medium));
+/// database.add(advisory("smallvec", Severity::Critical));
+///
+/// let advisories = flag_advisories(&mut graph, &database);
+/// let ids: Vec<&str> = advisories.iter().map(|advisory| advisory.id.as_str()).collect();
+/// assert_eq!(ids, ["RUSTSEC-smallvec", "RUSTSEC-time"]);
+/// assert_eq!(graph.markers("time"), ["RUSTSEC-time"]);
+///
+/// // The summary colors them by severity when colors are enabled
+/// let mut report = Report::new(&graph, graph.package("app").unwrap(), 2);
+/// report.advisories = Some(advisories);
+/// report.colored = true;
+/// let mut text = Vec::new();
+/// report.write_text(&mut text).unwrap();
+/// let text = String::from_utf8(text).unwrap();
+/// assert!(text.contains("\x1b[31m - RUSTSEC-smallvec [critical] smallvec v1.0.0: \x1b[0m"));
+/// assert!(text.contains("\x1b[33m - RUSTSEC-time [medium] time v1.0.0: \x1b[0m"));
+/// ```
+pub fn flag_advisories(
+    graph: &mut DependencyGraph,
+    database: &impl AdvisoryDatabase,
+) -> Vec<Advisory> {
+    let packages: Vec<(String, String)> = graph
+        .packages()
+        .into_iter()
+        .map(|package| (package.name.clone(), package.version.clone()))
+        .collect();
+
+    let mut advisories = Vec::new();
+    for (name, version) in packages {
+        match database.advisories(&name, &version) {
+            Ok(found) => {
+                for advisory in &found {
+                    graph.add_marker(&name, &advisory.id);
+                }
+                advisories.extend(found);
+            }
+            Err(err) => warn!("{}, skipping the audit of '{}'", err, name),
+        }
+    }
+    // Unrated advisories sort below the rated ones
+    advisories.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.crate_name.cmp(&b.crate_name))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    advisories
+}
+
+/// Groups the fetched crates by their declared edition, or else by the newest edition their
+/// `rust-version` allows, and marks the crates still on edition 2015 in the graph. A crate whose
+/// `rust-version` only allows edition 2015 is known to be on it.
+///
+/// # Returns
+///
+/// The names of the crates of every edition, `unknown` holding the crates declaring neither an
+/// edition nor a `rust-version`.
+fn flag_editions(graph: &mut DependencyGraph) -> BTreeMap<String, Vec<String>> {
+    let mut editions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for package in graph.packages() {
+        let edition = package
+            .edition
+            .as_deref()
+            .or(package.max_edition.as_deref())
+            .unwrap_or("unknown");
+        editions
+            .entry(edition.to_string())
+            .or_default()
+            .push(package.name.clone());
+    }
+
+    for name in editions.get("2015").into_iter().flatten() {
+        graph.add_marker(name, "edition 2015");
+    }
+    editions
+}
@@ -0,0 +1,170 @@
+//! # lock
+//!
+//! The `lock` module keeps trees stable between runs. Crates otherwise resolve to their latest
+//! version, which changes as new versions are published; with `--lock <path>`, the versions
+//! resolved by the first run are written to a [`VersionLock`] file and later runs pin every crate
+//! found in it, only resolving the crates the lock does not have yet. `--update-lock` ignores
+//! the existing lock and resolves everything again, and `--update-preview` prints the crates
+//! that would change without touching the lock.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::lock::VersionLock;
+//!
+//! let lock: VersionLock = "[versions]\nserde = \"1.0.192\"\n".parse().unwrap();
+//! assert_eq!(lock.version("serde"), Some("1.0.192"));
+//! assert_eq!(lock.version("syn"), None);
+//! ```
+//!
+//! Pinned crates are fetched at their locked version:
+//!
+//! ```rust
+//! use depth::dependency_graph::DependencyGraph;
+//! use depth::lock::VersionLock;
+//! use depth::package::FetchOptions;
+//! use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+//!
+//! let mut registry = MemoryRegistry::new();
+//! registry.add_crate(CrateInfo::new("app", "1.0.0"), vec![DependencyInfo::new("serde", "^1")]);
+//! registry.add_crate(CrateInfo::new("serde", "1.0.197"), vec![]);
+//!
+//! let options = FetchOptions {
+//!     lock: "[versions]\nserde = \"1.0.192\"\n".parse().unwrap(),
+//!     ..FetchOptions::default()
+//! };
+//! let mut graph = DependencyGraph::new();
+//! graph.fetch_dependency_tree_with_registry("app", 2, &options, &registry).unwrap();
+//! assert_eq!(graph.package("serde").unwrap().version, "1.0.192");
+//!
+//! // `app` was not pinned yet, so it is added at its resolved version
+//! let mut lock = options.lock.clone();
+//! lock.extend_from_graph(&graph);
+//! assert_eq!(lock.version("app"), Some("1.0.0"));
+//! assert_eq!(lock.version("serde"), Some("1.0.192"));
+//! ```
+
+use crate::dependency_graph::{DependencyGraph, VersionChange};
+use crate::registry::{CrateRegistry, RegistryError};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The versions crates are pinned to, keyed by crate name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionLock {
+    /// The pinned version of each crate.
+    #[serde(default)]
+    pub versions: BTreeMap<String, String>,
+}
+
+impl VersionLock {
+    /// Reads a lock file, returning an empty lock if the file does not exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the TOML lock file.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the lock or an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(content.parse()?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(VersionLock::default()),
+            Err(err) => Err(format!("failed to read lock '{}': {}", path.display(), err).into()),
+        }
+    }
+
+    /// Writes the lock file, replacing its previous content.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, toml::to_string(self)?)
+            .map_err(|err| format!("failed to write lock '{}': {}", path.display(), err).into())
+    }
+
+    /// Returns the version a crate is pinned to, if any.
+    pub fn version(&self, crate_name: &str) -> Option<&str> {
+        self.versions.get(crate_name).map(String::as_str)
+    }
+
+    /// Adds the resolved version of every package fetched into the graph.
+    ///
+    /// Crates already in the lock keep their pinned version, so a lock file only grows as new
+    /// crates show up in the tree.
+    pub fn extend_from_graph(&mut self, graph: &DependencyGraph) {
+        for package in graph.packages() {
+            if !package.version.is_empty() {
+                self.versions
+                    .entry(package.name.clone())
+                    .or_insert_with(|| package.version.clone());
+            }
+        }
+    }
+
+    /// Lists the pinned crates whose latest version differs from the pinned one, i.e. the
+    /// crates an update of the lock would change, sorted by name.
+    ///
+    /// This costs one request per pinned crate. Crates that are private or gone are skipped
+    /// with a warning.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::VersionChange;
+    /// use depth::lock::VersionLock;
+    /// use depth::registry::{CrateInfo, MemoryRegistry};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(CrateInfo::new("serde", "1.0.197"), vec![]);
+    /// registry.add_crate(CrateInfo::new("syn", "2.0.48"), vec![]);
+    ///
+    /// let lock: VersionLock = "[versions]\nserde = \"1.0.192\"\nsyn = \"2.0.48\"\n"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     lock.pending_updates(&registry).unwrap(),
+    ///     vec![VersionChange {
+    ///         name: "serde".to_string(),
+    ///         from: "1.0.192".to_string(),
+    ///         to: "1.0.197".to_string(),
+    ///     }]
+    /// );
+    /// ```
+    pub fn pending_updates(
+        &self,
+        registry: &impl CrateRegistry,
+    ) -> Result<Vec<VersionChange>, RegistryError> {
+        let mut updates = Vec::new();
+        for (name, pinned) in &self.versions {
+            let latest = match registry.get_crate(name) {
+                Ok(info) => info.max_version,
+                Err(err) if err.is_unavailable() => {
+                    warn!("{}, skipping its update", err);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if latest != *pinned {
+                updates.push(VersionChange {
+                    name: name.clone(),
+                    from: pinned.clone(),
+                    to: latest,
+                });
+            }
+        }
+        Ok(updates)
+    }
+}
+
+impl FromStr for VersionLock {
+    type Err = toml::de::Error;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        toml::from_str(content)
+    }
+}
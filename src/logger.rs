@@ -0,0 +1,78 @@
+//! # logger
+//!
+//! The `logger` module is the logger of the `depth` binary: it writes the records of the `log`
+//! crate to stderr, so the tree on stdout is the same at every verbosity.
+//!
+//! The library logs its warnings (`warn`) and notes (`info`) instead of printing them, along
+//! with each fetched crate and how long it took (`debug`) and each crate served from the
+//! fetched ones (`trace`). Applications embedding the library see them through whichever
+//! logger they install, and nothing without one.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::logger::{self, level_for};
+//! use log::LevelFilter;
+//!
+//! assert_eq!(level_for(true, 0), LevelFilter::Error);
+//! assert_eq!(level_for(false, 0), LevelFilter::Info);
+//! assert_eq!(level_for(false, 2), LevelFilter::Trace);
+//!
+//! logger::init(level_for(false, 0));
+//! log::warn!("'serde' has no feature 'derive'");
+//! ```
+
+use crate::progress;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::{self, Write};
+
+/// Writes records to stderr, prefixed with their level. The notes, debug and trace records of
+/// dependencies, e.g. of the HTTP client, are left out.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && (metadata.level() <= Level::Warn || metadata.target().starts_with("depth"))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let prefix = match record.level() {
+            Level::Error => "Error",
+            Level::Warn => "Warning",
+            Level::Info => "Note",
+            Level::Debug => "Debug",
+            Level::Trace => "Trace",
+        };
+        // Logging is best effort, a closed stderr must not fail the run
+        progress::suspend(|| {
+            let _ = writeln!(io::stderr().lock(), "{}: {}", prefix, record.args());
+        });
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+/// Returns the level to log at for the `--quiet` and `--verbose` flags: errors only when
+/// quiet, warnings and notes by default, then debug and trace records for each `-v`.
+pub fn level_for(quiet: bool, verbose: u8) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+/// Installs the stderr logger at the given level. Does nothing if a logger is already
+/// installed, e.g. by an application embedding the library.
+pub fn init(level: LevelFilter) {
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(level);
+    }
+}
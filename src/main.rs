@@ -1,19 +1,345 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use depth::cache::{default_cache_dir, DiskCache, MetadataCache, NoCache};
 use depth::cli::Cli;
-use depth::visualize_dependency_tree;
+use depth::config::Config;
+use depth::dependency_graph::{ColorScheme, EdgeStyle, TreeOptions};
+use depth::error::DepthError;
+use depth::filter::CrateFilter;
+use depth::lock::VersionLock;
+use depth::logger;
+use depth::manifest::ManifestError;
+use depth::notes::Notes;
+use depth::package::{FetchOptions, KindDepths};
+use depth::registry::{ClientOptions, CrateNotFound, RegistryError};
+use depth::render::list_formats;
+use depth::text::terminal_width;
+use depth::{
+    compare_default_features, compare_versions, depth_for_levels, preview_lock_update,
+    resolve_latest_version, visualize_dependency_trees, visualize_local_manifest,
+    visualize_manifest_dependency_tree, visualize_reverse_dependencies, VisualizeOptions,
+};
+use log::warn;
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Cli::parse();
-    let crate_ = &args.crate_;
-    let levels = &args.levels;
-    let optional = &args.optional;
-    if *optional {
-        if let Err(err) = visualize_dependency_tree(crate_, *levels + 1, true) {
-            eprintln!("Error: {}", err);
+// The exit codes of `depth`, so scripts can branch on the kind of failure.
+//
+// - 0: success.
+// - 1: any failure not listed below.
+// - 2: the crate, a version of it matching --version or the manifest URL was not found.
+// - 3: the registry or the manifest URL could not be reached.
+// - 4: a manifest or a lock file could not be parsed.
+// - 5: reserved for policy and audit violations.
+//
+// Invalid command-line arguments are reported by clap, which exits with 2 as well.
+
+/// A failure not covered by a more specific exit code occurred.
+const EXIT_FAILURE: i32 = 1;
+/// The crate, the requested version of it or the manifest URL was not found.
+const EXIT_NOT_FOUND: i32 = 2;
+/// The registry or the manifest URL could not be reached.
+const EXIT_NETWORK_ERROR: i32 = 3;
+/// A manifest or a lock file could not be parsed.
+const EXIT_PARSE_ERROR: i32 = 4;
+
+fn main() {
+    let matches = Cli::command().get_matches();
+    let args = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    logger::init(logger::level_for(args.quiet, args.verbose));
+    let registry = args.registry.clone();
+
+    if let Err(err) = run(args, &matches) {
+        let code = exit_code(err.as_ref());
+        match registry {
+            Some(url) if code == EXIT_NETWORK_ERROR => {
+                eprintln!(
+                    "Error: the registry at {} could not be reached: {}",
+                    url, err
+                )
+            }
+            _ => eprintln!("Error: {}", err),
+        }
+        process::exit(code);
+    }
+}
+
+fn run(mut args: Cli, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    if args.list_formats {
+        list_formats(&mut io::stdout().lock())?;
+        return Ok(());
+    }
+    let config = match args.config.clone().or_else(Config::discover) {
+        Some(path) => {
+            let config = Config::load(&path)?;
+            args.apply_config(&config, matches)
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+            config
+        }
+        None => Config::default(),
+    };
+    let client = ClientOptions {
+        user_agent: args.user_agent.clone(),
+        rate_limit: Duration::from_millis(args.rate_limit_ms),
+        retries: args.retries,
+        registry: args.registry.clone(),
+    };
+    if let (true, Some(path)) = (args.update_preview, &args.lock) {
+        return preview_lock_update(&VersionLock::load(path)?, &client);
+    }
+    if args.crate_.len() > 1
+        && (args.version.is_some()
+            || args.diff_version.is_some()
+            || args.resolve_only
+            || args.reverse
+            || args.compare_default)
+    {
+        return Err(
+            "--version, --diff-version, --resolve-only, --reverse and --compare-default take a single --crate"
+                .into(),
+        );
+    }
+    if let (true, Some(crate_)) = (args.resolve_only, args.crate_.first()) {
+        println!("{}", resolve_latest_version(crate_, &client)?);
+        return Ok(());
+    }
+
+    // `--full` asks for every level, fetching stops at the crates already fetched.
+    let requested_levels = if args.full { usize::MAX } else { args.levels };
+    // Kinds without their own depth follow `-l`, and the fetch goes as deep as the deepest kind.
+    // Build and dev-dependencies are left out, at a depth of 0, unless they are included.
+    let included_depth = |depth: Option<usize>, included: bool| match depth {
+        Some(depth) => depth,
+        None if included => requested_levels,
+        None => 0,
+    };
+    let kind_depths = KindDepths {
+        normal: Some(args.normal_depth.unwrap_or(requested_levels)),
+        build: Some(included_depth(args.build_depth, args.include_build)),
+        dev: Some(included_depth(
+            args.dev_depth,
+            args.include_dev || args.dev_recursive,
+        )),
+    };
+    let levels = kind_depths
+        .normal
+        .max(kind_depths.build)
+        .max(kind_depths.dev)
+        .unwrap_or(requested_levels);
+    // The whole tree is asked for on purpose with `--full`, a dry run does not crawl it and a
+    // cap passed on the command line already bounds the crawl
+    let capped = matches.value_source("max_nodes") == Some(ValueSource::CommandLine);
+    if !args.full && !args.dry_run && !capped {
+        confirm_levels(levels, args.warn_levels, args.max_nodes, args.yes)?;
+    }
+    let depth = depth_for_levels(levels);
+    let options = FetchOptions {
+        optional: args.optional,
+        filter: CrateFilter::new(&args.include)?
+            .with_exclude(&args.exclude, args.exclude_collapse)?
+            .with_internal_prefixes(&args.internal_prefix)
+            .with_internal_prefixes(&config.internal_prefixes)
+            .with_hide_internal(args.hide_internal),
+        dev_recursive: args.dev_recursive,
+        kind_depths,
+        lock: match &args.lock {
+            Some(path) if !args.update_lock => VersionLock::load(path)?,
+            _ => VersionLock::default(),
+        },
+        features: args.features.clone(),
+        cache: metadata_cache(&args),
+        client,
+        jobs: args.jobs,
+        version: args.version.clone(),
+        strict: args.strict,
+        // No spinner when the output is piped or redirected
+        progress: !args.quiet && io::stdout().is_terminal(),
+        max_fanout: args.max_fanout,
+        max_nodes: args.max_nodes,
+    };
+    let max_width = match args.max_width {
+        Some(None) => {
+            let width = terminal_width();
+            if width.is_none() {
+                warn!("the width of the terminal is unknown, pass it to --max-width");
+            }
+            width
+        }
+        width => width.flatten(),
+    };
+    let visualize_options = VisualizeOptions {
+        format: args.format,
+        timings: args.timings,
+        tree: TreeOptions {
+            collapse_chains: args.collapse_chains,
+            sort: args.sort,
+            show_size: args.show_size,
+            show_features: args.show_features,
+            compact: args.compact,
+            max_width,
+        },
+        blame: args.blame,
+        leaves_only: args.leaves_only,
+        aliases: config.aliases,
+        total_size: args.total_size,
+        owner_changes: args.owner_changes,
+        show_rdeps: args.show_rdeps,
+        show_downloads: args.show_downloads,
+        link: args.link,
+        color: args
+            .color_scheme
+            .enabled_if(!args.no_color && args.output.is_none()),
+        benchmark: args.benchmark,
+        dry_run: args.dry_run,
+        hash: args.hash,
+        no_root: args.no_root,
+        lock: args.lock,
+        flag_build_scripts: args.flag_build_scripts,
+        most_shared: args.most_shared,
+        show_cycles: args.show_cycles,
+        longest_path: args.longest_path,
+        show_duplicates: args.show_duplicates,
+        quiet: args.quiet,
+        focus: args.focus,
+        editions: args.editions,
+        audit: args.audit,
+        contributors: args.contributors,
+        dump_api: args.dump_api,
+        edge_style: EdgeStyle {
+            direction: args.edge_style,
+            labels: args.edge_labels,
+        },
+        output: args.output,
+        tui: args.tui,
+        notes: match &args.notes {
+            Some(path) => Notes::load(path)?,
+            None => Notes::default(),
+        },
+    };
+    if let Some(manifest_path) = &args.manifest_path {
+        return visualize_local_manifest(
+            manifest_path,
+            depth,
+            &options,
+            &visualize_options,
+            args.offline,
+        );
+    }
+    if let (Some(to), Some(crate_)) = (&args.diff_version, args.crate_.first()) {
+        let colored = visualize_options.color != ColorScheme::None;
+        return compare_versions(crate_, depth, &options, to, colored);
+    }
+    match (&args.manifest_url, args.crate_.first()) {
+        (None, Some(crate_)) if args.reverse => {
+            visualize_reverse_dependencies(crate_, depth, &options.client)
+        }
+        (None, Some(crate_)) if args.compare_default => {
+            compare_default_features(crate_, depth, &options)
+        }
+        (Some(manifest_url), _) => visualize_manifest_dependency_tree(
+            manifest_url,
+            depth,
+            &options,
+            &visualize_options,
+        ),
+        (None, Some(_)) => {
+            visualize_dependency_trees(&args.crate_, depth, &options, &visualize_options)
         }
-    } else if let Err(err) = visualize_dependency_tree(crate_, *levels + 1, false) {
-        eprintln!("Error: {}", err);
+        (None, None) => unreachable!(
+            "clap requires --crate, --manifest-url, --manifest-path, --list-formats or --update-preview"
+        ),
+    }
+}
+
+/// Returns the on-disk cache of crate metadata, unless disabled or the platform has no cache
+/// directory. `--dump-api` bypasses it, so that every response is actually received and dumped.
+fn metadata_cache(args: &Cli) -> Arc<dyn MetadataCache> {
+    if args.no_cache || args.dump_api.is_some() {
+        return Arc::new(NoCache);
+    }
+    let Some(mut dir) = default_cache_dir() else {
+        return Arc::new(NoCache);
+    };
+    if let Some(url) = &args.registry {
+        // The crates of another registry must not be served from the ones of Crates.io
+        let host = url.host_str().unwrap_or_default();
+        dir.push(match url.port() {
+            Some(port) => format!("{}-{}", host, port),
+            None => host.to_string(),
+        });
+    }
+    Arc::new(DiskCache::new(&dir).with_ttl(Duration::from_secs(args.cache_ttl)))
+}
+
+/// Guards against accidentally crawling an enormous tree, which can get the client rate limited.
+///
+/// Above `warn_levels` levels a warning naming the `max_nodes` cap is printed; in a terminal,
+/// the user must then confirm unless `yes` is set. Non-interactive runs proceed after the
+/// warning.
+fn confirm_levels(
+    levels: usize,
+    warn_levels: usize,
+    max_nodes: usize,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    if levels <= warn_levels {
+        return Ok(());
+    }
+
+    eprintln!(
+        "Warning: fetching {} levels of dependencies may request up to {} crates from the registry, \
+         pass --max-nodes to change the cap",
+        levels, max_nodes
+    );
+    if yes || !io::stdin().is_terminal() {
+        return Ok(());
+    }
+
+    eprint!("Continue? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(()),
+        _ => Err("aborted, pass --yes to skip this confirmation".into()),
+    }
+}
+
+/// Maps an error to the exit code documented at the top of this file.
+fn exit_code(err: &(dyn Error + 'static)) -> i32 {
+    if err.is::<CrateNotFound>() {
+        return EXIT_NOT_FOUND;
+    }
+    let registry_err = match err.downcast_ref::<DepthError>() {
+        Some(DepthError::Api(registry_err)) => Some(registry_err),
+        Some(DepthError::CrateNotFound(_) | DepthError::NoMatchingVersion { .. }) => {
+            return EXIT_NOT_FOUND
+        }
+        Some(DepthError::Parse(_)) => return EXIT_PARSE_ERROR,
+        Some(DepthError::Io(_) | DepthError::InvalidDepth(_) | DepthError::Client(_)) => {
+            return EXIT_FAILURE
+        }
+        None => err.downcast_ref::<RegistryError>(),
+    };
+    if let Some(registry_err) = registry_err {
+        return match registry_err {
+            RegistryError::NotFound(_) => EXIT_NOT_FOUND,
+            RegistryError::Api(_) => EXIT_NETWORK_ERROR,
+            RegistryError::PermissionDenied(_) | RegistryError::NoVersions(_) => EXIT_FAILURE,
+        };
+    }
+    if let Some(manifest_err) = err.downcast_ref::<ManifestError>() {
+        return match manifest_err {
+            ManifestError::Http { status: 404, .. } => EXIT_NOT_FOUND,
+            _ => EXIT_NETWORK_ERROR,
+        };
+    }
+    if err.is::<toml::de::Error>() {
+        return EXIT_PARSE_ERROR;
     }
 
-    Ok(())
+    EXIT_FAILURE
 }
@@ -0,0 +1,87 @@
+//! # manifest
+//!
+//! The `manifest` module downloads a `Cargo.toml` over HTTP, so `depth` can build the tree of a
+//! crate that is not published yet, for instance a branch on GitHub through its raw URL.
+//!
+//! # Usage
+//!
+//! ```rust,no_run
+//! use depth::manifest::download_manifest;
+//!
+//! let manifest = download_manifest(
+//!     "https://raw.githubusercontent.com/wiseaidev/depth/main/Cargo.toml",
+//! ).unwrap();
+//! assert!(manifest.contains("[package]"));
+//! ```
+
+use reqwest::blocking::Client;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// An error raised while downloading a manifest.
+#[derive(Debug)]
+pub enum ManifestError {
+    /// The server answered with a non-success HTTP status.
+    Http { url: String, status: u16 },
+    /// The request could not be sent or its body could not be read.
+    Network { url: String, source: reqwest::Error },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Http { url, status } => {
+                write!(
+                    f,
+                    "failed to download manifest from '{}': HTTP {}",
+                    url, status
+                )
+            }
+            ManifestError::Network { url, source } => {
+                write!(f, "failed to download manifest from '{}': {}", url, source)
+            }
+        }
+    }
+}
+
+impl Error for ManifestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ManifestError::Http { .. } => None,
+            ManifestError::Network { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Downloads the content of a `Cargo.toml` file.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the raw manifest, e.g. a GitHub raw URL.
+///
+/// # Returns
+///
+/// A Result containing the manifest content or an error if the download fails.
+pub fn download_manifest(url: &str) -> Result<String, ManifestError> {
+    let network_error = |source| ManifestError::Network {
+        url: url.to_string(),
+        source,
+    };
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(network_error)?;
+    let response = client.get(url).send().map_err(network_error)?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ManifestError::Http {
+            url: url.to_string(),
+            status: status.as_u16(),
+        });
+    }
+
+    response.text().map_err(network_error)
+}
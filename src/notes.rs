@@ -0,0 +1,84 @@
+//! # notes
+//!
+//! The `notes` module reads the file passed with `--notes`, which maps crate names to notes a
+//! team keeps about them, e.g. `"approved 2024-01"` or `"deprecated, migrate to jiff"`. The
+//! renderers print each note next to its crate, turning the tree into an audit document.
+//! Crates without a note render unchanged.
+//!
+//! Files ending in `.json` are parsed as a JSON object, any other file as TOML.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::notes::Notes;
+//!
+//! let notes = Notes::from_toml(r#"
+//!     time = "deprecated, migrate to jiff"
+//!     serde = "approved 2024-01"
+//! "#)
+//! .unwrap();
+//! assert_eq!(notes.note("serde"), Some("approved 2024-01"));
+//!
+//! let notes = Notes::from_json(r#"{"serde": "approved 2024-01"}"#).unwrap();
+//! assert_eq!(notes.note("serde"), Some("approved 2024-01"));
+//! assert_eq!(notes.note("syn"), None);
+//! ```
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// The notes kept about crates, keyed by crate name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Notes {
+    notes: BTreeMap<String, String>,
+}
+
+impl Notes {
+    /// Reads a notes file, as JSON if its extension is `.json` and as TOML otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the notes file.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the notes or an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read notes '{}': {}", path.display(), err))?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            Ok(Self::from_json(&content)?)
+        } else {
+            Ok(Self::from_toml(&content)?)
+        }
+    }
+
+    /// Parses notes from a TOML table of `crate = "note"` pairs.
+    pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// Parses notes from a JSON object of `"crate": "note"` pairs.
+    pub fn from_json(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+
+    /// Returns the note kept about a crate, if any.
+    pub fn note(&self, crate_name: &str) -> Option<&str> {
+        self.notes.get(crate_name).map(String::as_str)
+    }
+
+    /// Returns every note, sorted by crate name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.notes
+            .iter()
+            .map(|(name, note)| (name.as_str(), note.as_str()))
+    }
+}
@@ -29,7 +29,7 @@
 //! let mut graph = DependencyGraph::new();
 //!
 //! // for dep in dependencies {
-//!     // fetch_package_info(&dep, &mut visited_packages, &mut graph, &client, 2)?;
+//!     // fetch_package_info(&dep, &mut visited_packages, &mut graph, &client, 2, &FetchOptions::default())?;
 //! // }
 //! ```
 //!
@@ -66,7 +66,7 @@
 //! let mut visited_packages: HashMap<String, Package> = HashMap::new();
 //! let mut graph = DependencyGraph::new();
 //! // let client = SyncClient::new(
-//! //     "my-user-agent (my-contact@domain.com)",
+//! //     depth::registry::DEFAULT_USER_AGENT,
 //! //   std::time::Duration::from_millis(1000),
 //! // )?;
 //!
@@ -76,6 +76,7 @@
 //! //     &mut graph,
 //! //     &client,
 //! //     2,
+//! //     &FetchOptions::default(),
 //! // )?;
 //! ```
 //!
@@ -93,28 +94,76 @@
 //! let mut visited_packages: HashMap<String, Package> = HashMap::new();
 //! let mut graph = DependencyGraph::new();
 //! // let client = SyncClient::new(
-//! //     "my-user-agent (my-contact@domain.com)",
+//! //     depth::registry::DEFAULT_USER_AGENT,
 //! //     std::time::Duration::from_millis(1000),
 //! // ).unwrap();
 //!
 //! // for dep in dependencies {
-//! //     fetch_package_info(&(dep, "".to_string()), &mut visited_packages, &mut graph, &client, 2).unwrap();
+//! //     fetch_package_info(&dep, &mut visited_packages, &mut graph, &client, 2, &FetchOptions::default()).unwrap();
 //! // }
 //! ```
 
+use crate::cache::{MemoryCache, MetadataCache, NoCache};
 use crate::dependency_graph::DependencyGraph;
-use crates_io_api::{Crate, Error as CratesIoError, SyncClient};
-use std::collections::HashMap;
+use crate::error::DepthError;
+use crate::filter::CrateFilter;
+use crate::lock::VersionLock;
+use crate::progress;
+#[cfg(feature = "async")]
+use crate::registry::AsyncClientPool;
+use crate::registry::{
+    resolve_version, ClientOptions, CrateError, CrateInfo, CrateRegistry, DependencyInfo,
+    DependencyKind, RegistryError, VersionInfo,
+};
+use log::{debug, trace, warn};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+#[cfg(feature = "async")]
+use tokio::sync::Semaphore;
+#[cfg(feature = "async")]
+use tokio::task::JoinSet;
 use toml::Value;
 
 /// Represents a Rust package with its name, URL, dependencies, and internal status.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Package {
     pub name: String,
     pub url: String,
     pub dependencies: Vec<(String, String)>,
     pub internal: bool,
+    /// The resolved version, empty if the package was not fetched from a registry.
+    pub version: String,
+    /// The license of the resolved version, if known.
+    pub license: Option<String>,
+    /// The size in bytes of the published tarball of the resolved version, if known.
+    pub size: Option<u64>,
+    /// The downloads of every version of the crate, if known. Not serialized, the report lists
+    /// them with `--show-downloads`.
+    #[serde(skip)]
+    pub downloads: Option<u64>,
+    /// The features declared by the resolved version, each with the optional dependencies it
+    /// enables, see `feature_dependencies`. Not serialized, only written with `--show-features`.
+    #[serde(skip)]
+    pub features: BTreeMap<String, Vec<String>>,
+    /// The names of the build-dependencies of the resolved version, whether they are listed or
+    /// not, sorted.
+    pub build_dependencies: Vec<String>,
+    /// The edition declared by the manifest, only known for a root read from a manifest, as
+    /// the registry does not expose it.
+    pub edition: Option<String>,
+    /// The newest edition the declared `rust-version` of the resolved version supports, if it
+    /// declares one, an upper bound of the edition of the crate.
+    pub max_edition: Option<String>,
+    /// The kind of the listed dependencies that are not normal dependencies, keyed by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependency_kinds: BTreeMap<String, DependencyKind>,
 }
 
 impl Package {
@@ -130,8 +179,442 @@ impl Package {
             url,
             dependencies,
             internal,
+            version: String::new(),
+            license: None,
+            size: None,
+            downloads: None,
+            features: BTreeMap::new(),
+            build_dependencies: Vec::new(),
+            edition: None,
+            max_edition: None,
+            dependency_kinds: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the kind of a dependency of the package, normal unless it is only listed as a
+    /// build or dev-dependency.
+    pub fn dependency_kind(&self, dependency_name: &str) -> DependencyKind {
+        self.dependency_kinds
+            .get(dependency_name)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Crates whose tarball is larger than this many bytes are highlighted as heavyweight.
+pub const LARGE_CRATE_SIZE: u64 = 1024 * 1024;
+
+/// Formats a count with a metric suffix, e.g. `12.3M`, to keep download counts short.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::package::format_count;
+///
+/// assert_eq!(format_count(950), "950");
+/// assert_eq!(format_count(12_345), "12.3K");
+/// assert_eq!(format_count(412_000_000), "412.0M");
+/// assert_eq!(format_count(2_500_000_000), "2.5B");
+/// ```
+pub fn format_count(count: u64) -> String {
+    const UNITS: [&str; 3] = ["K", "M", "B"];
+    if count < 1000 {
+        return count.to_string();
+    }
+    let mut value = count as f64 / 1000.0;
+    let mut unit = 0;
+    while value >= 1000.0 && unit + 1 < UNITS.len() {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+/// Formats a size in bytes in a human readable unit, e.g. `12.3 KiB`.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::package::format_size;
+///
+/// assert_eq!(format_size(512), "512 B");
+/// assert_eq!(format_size(12_595), "12.3 KiB");
+/// assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+/// ```
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// The editions with the Rust version that stabilized them, newest first.
+const EDITIONS: &[(&str, (u64, u64))] = &[
+    ("2024", (1, 85)),
+    ("2021", (1, 56)),
+    ("2018", (1, 31)),
+    ("2015", (1, 0)),
+];
+
+/// Infers the newest edition a crate can use from its minimum supported Rust version.
+///
+/// A crate declaring `rust-version = "1.40"` cannot be on edition 2021, which needs Rust 1.56,
+/// so the result is an upper bound: only a crate inferred as `2015` is known to be on it.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::package::edition_for_rust_version;
+///
+/// assert_eq!(edition_for_rust_version("1.70"), Some("2021"));
+/// assert_eq!(edition_for_rust_version("1.56.1"), Some("2021"));
+/// assert_eq!(edition_for_rust_version("1.85"), Some("2024"));
+/// assert_eq!(edition_for_rust_version("1.28"), Some("2015"));
+/// assert_eq!(edition_for_rust_version("latest"), None);
+/// ```
+///
+/// Fetched packages store the edition inferred from the `rust-version` of their resolved version
+/// as their `max_edition`:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::FetchOptions;
+/// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("app", "1.0.0"), vec![DependencyInfo::new("old", "^0.1")]);
+/// registry.add_crate(
+///     CrateInfo {
+///         rust_version: Some("1.20".to_string()),
+///         ..CrateInfo::new("old", "0.1.0")
+///     },
+///     vec![],
+/// );
+///
+/// let mut graph = DependencyGraph::new();
+/// graph
+///     .fetch_dependency_tree_with_registry("app", 2, &FetchOptions::default(), &registry)
+///     .unwrap();
+///
+/// assert_eq!(graph.package("old").unwrap().max_edition.as_deref(), Some("2015"));
+/// assert_eq!(graph.package("app").unwrap().max_edition, None);
+/// ```
+pub fn edition_for_rust_version(rust_version: &str) -> Option<&'static str> {
+    let mut parts = rust_version.trim().split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |minor| minor.parse().ok())?;
+
+    EDITIONS
+        .iter()
+        .find(|(_, stabilized)| (major, minor) >= *stabilized)
+        .map(|(edition, _)| *edition)
+}
+
+/// Resolves the optional dependencies enabled by a set of features of a crate, on top of its
+/// default features.
+///
+/// Features enable other features, optional dependencies (`dep:name`, or the implicit feature
+/// named after an optional dependency) and features of dependencies (`name/feature`, which
+/// enables `name` too unless written `name?/feature`). Features the crate does not declare
+/// are taken as the implicit feature of the optional dependency of that name.
+///
+/// # Arguments
+///
+/// * `declared` - The features declared by the crate.
+/// * `selected` - The features to enable besides `default`.
+///
+/// # Returns
+///
+/// The names of the enabled optional dependencies.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::package::enabled_dependencies;
+/// use std::collections::HashMap;
+///
+/// let features = HashMap::from([
+///     ("default".to_string(), vec!["std".to_string()]),
+///     ("std".to_string(), vec![]),
+///     ("derive".to_string(), vec!["dep:serde_derive".to_string()]),
+///     ("full".to_string(), vec!["derive".to_string(), "tokio/rt".to_string()]),
+/// ]);
+///
+/// assert!(enabled_dependencies(&features, &[]).is_empty());
+/// assert_eq!(
+///     enabled_dependencies(&features, &["full".to_string()]),
+///     ["serde_derive", "tokio"].into_iter().map(String::from).collect()
+/// );
+/// ```
+pub fn enabled_dependencies(
+    declared: &HashMap<String, Vec<String>>,
+    selected: &[String],
+) -> BTreeSet<String> {
+    let mut enabled = BTreeSet::new();
+    let mut seen = HashSet::new();
+    let mut pending: Vec<&str> = selected.iter().map(String::as_str).collect();
+    pending.push("default");
+
+    while let Some(entry) = pending.pop() {
+        if let Some(dependency) = entry.strip_prefix("dep:") {
+            enabled.insert(dependency.to_string());
+        } else if let Some((dependency, _)) = entry.split_once('/') {
+            if !dependency.ends_with('?') {
+                enabled.insert(dependency.to_string());
+            }
+        } else if !seen.insert(entry) {
+            continue;
+        } else if let Some(entries) = declared.get(entry) {
+            pending.extend(entries.iter().map(String::as_str));
+        } else if entry != "default" {
+            // An implicit feature, named after the optional dependency it enables
+            enabled.insert(entry.to_string());
         }
     }
+
+    enabled
+}
+
+/// Lists the features of a crate with the optional dependencies each of them enables, directly
+/// or through the features it enables.
+///
+/// Optional dependencies no feature enables with `dep:name` get the implicit feature named
+/// after them, like cargo does. Features of dependencies (`name/feature`) only count when
+/// `name` is optional.
+///
+/// # Arguments
+///
+/// * `declared` - The features declared by the crate.
+/// * `optional` - The names of the optional dependencies of the crate.
+///
+/// # Returns
+///
+/// The optional dependencies enabled by each feature, sorted, keyed by feature name.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::package::feature_dependencies;
+/// use std::collections::{BTreeSet, HashMap};
+///
+/// let features = HashMap::from([
+///     ("default".to_string(), vec!["std".to_string()]),
+///     ("std".to_string(), vec![]),
+///     ("derive".to_string(), vec!["dep:serde_derive".to_string()]),
+///     ("full".to_string(), vec!["derive".to_string(), "tokio/rt".to_string()]),
+/// ]);
+/// let optional = BTreeSet::from(["serde_derive".to_string(), "log".to_string()]);
+///
+/// let enabled = feature_dependencies(&features, &optional);
+/// assert!(enabled["std"].is_empty());
+/// assert_eq!(enabled["derive"], ["serde_derive"]);
+/// assert_eq!(enabled["full"], ["serde_derive"]);
+/// assert_eq!(enabled["log"], ["log"]);
+/// ```
+pub fn feature_dependencies(
+    declared: &HashMap<String, Vec<String>>,
+    optional: &BTreeSet<String>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut features: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for feature in declared.keys() {
+        let mut enabled = BTreeSet::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![feature.as_str()];
+        while let Some(entry) = pending.pop() {
+            if let Some(dependency) = entry.strip_prefix("dep:") {
+                enabled.insert(dependency);
+            } else if let Some((dependency, _)) = entry.split_once('/') {
+                if !dependency.ends_with('?') {
+                    enabled.insert(dependency);
+                }
+            } else if !seen.insert(entry) {
+                continue;
+            } else if let Some(entries) = declared.get(entry) {
+                pending.extend(entries.iter().map(String::as_str));
+            } else {
+                enabled.insert(entry);
+            }
+        }
+        let enabled = enabled
+            .into_iter()
+            .filter(|dependency| optional.contains(*dependency))
+            .map(str::to_string)
+            .collect();
+        features.insert(feature.clone(), enabled);
+    }
+
+    // An optional dependency only gets an implicit feature if no feature refers to it as `dep:`
+    let explicit: HashSet<&str> = declared
+        .values()
+        .flatten()
+        .filter_map(|entry| entry.strip_prefix("dep:"))
+        .collect();
+    for dependency in optional {
+        if !explicit.contains(dependency.as_str()) && !declared.contains_key(dependency) {
+            features.insert(dependency.clone(), vec![dependency.clone()]);
+        }
+    }
+
+    features
+}
+
+/// Options controlling which dependencies `fetch_package_info` follows.
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// Scan optional dependencies only.
+    pub optional: bool,
+    /// Restricts which crates are fetched; crates rejected by the filter become pruned leaves.
+    pub filter: CrateFilter,
+    /// List dev-dependencies at every level instead of only for the root, which Cargo never
+    /// builds and which can make the tree a lot larger.
+    pub dev_recursive: bool,
+    /// The deepest level each kind of dependency is listed at.
+    pub kind_depths: KindDepths,
+    /// The versions crates are pinned to instead of their latest version.
+    pub lock: VersionLock,
+    /// The features enabled on the root crate, on top of its default features. When set, the
+    /// root lists the optional dependencies these features enable instead of following
+    /// `optional`; `Some(vec![])` enables the default features only.
+    pub features: Option<Vec<String>>,
+    /// The cache consulted for the dependencies of a crate version before the registry.
+    pub cache: Arc<dyn MetadataCache>,
+    /// The settings of the Crates.io client, for the functions creating one rather than being
+    /// given a registry.
+    pub client: ClientOptions,
+    /// The number of sibling crates fetched concurrently, 1 fetching every crate in turn. The
+    /// graph is built in the same order either way, so it does not depend on this setting.
+    pub jobs: usize,
+    /// The version the root crate is resolved to instead of its latest version: the highest
+    /// published, non-yanked version matching this requirement. It takes precedence over the
+    /// version `lock` pins the root crate to.
+    pub version: Option<VersionReq>,
+    /// Fail the fetch on the first dependency that cannot be fetched, instead of recording it on
+    /// the graph and fetching the rest of the tree.
+    pub strict: bool,
+    /// Write a spinner with the number of crates fetched and the crate being fetched to stderr
+    /// while fetching.
+    pub progress: bool,
+    /// The number of dependencies followed per crate, the others being left out of the tree
+    /// and counted in a marker on the crate. Non-optional normal dependencies are followed
+    /// first, then build and dev-dependencies, then optional ones.
+    pub max_fanout: Option<usize>,
+    /// The number of crates, fetched or not, the graph stops growing at: once it holds this
+    /// many, the remaining dependencies are left unfetched and marked `truncated`.
+    pub max_nodes: usize,
+}
+
+/// The number of crates a fetch stops at by default, far above the trees of most crates but
+/// low enough to contain a misjudged `--full` crawl.
+pub const DEFAULT_MAX_NODES: usize = 5000;
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl FetchOptions {
+    /// Creates a new FetchOptions instance that fetches every crate.
+    pub fn new(optional: bool) -> Self {
+        FetchOptions {
+            optional,
+            filter: CrateFilter::default(),
+            dev_recursive: false,
+            kind_depths: KindDepths::default(),
+            lock: VersionLock::default(),
+            features: None,
+            cache: Arc::new(NoCache),
+            client: ClientOptions::default(),
+            jobs: 1,
+            version: None,
+            strict: false,
+            progress: false,
+            max_fanout: None,
+            max_nodes: DEFAULT_MAX_NODES,
+        }
+    }
+}
+
+/// The deepest level, the direct dependencies being at level 1, each kind of dependency is
+/// listed at. A kind without a limit is listed down to the depth of the whole fetch, and a limit
+/// of 0 leaves the kind out of the tree.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::package::KindDepths;
+/// use depth::registry::DependencyKind;
+///
+/// // Full depth for normal dependencies, but only direct build-dependencies
+/// let kind_depths = KindDepths {
+///     build: Some(1),
+///     ..KindDepths::default()
+/// };
+/// assert!(kind_depths.allows(DependencyKind::Normal, 5));
+/// assert!(kind_depths.allows(DependencyKind::Build, 1));
+/// assert!(!kind_depths.allows(DependencyKind::Build, 2));
+/// ```
+///
+/// The limits apply while fetching, to the kind of each dependency edge:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{FetchOptions, KindDepths};
+/// use depth::registry::{CrateInfo, DependencyInfo, DependencyKind, MemoryRegistry};
+///
+/// let build = |name: &str| DependencyInfo {
+///     kind: DependencyKind::Build,
+///     ..DependencyInfo::new(name, "^1")
+/// };
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("app", "1.0.0"), vec![DependencyInfo::new("ring", "^1")]);
+/// registry.add_crate(CrateInfo::new("ring", "1.0.0"), vec![build("cc")]);
+/// registry.add_crate(CrateInfo::new("cc", "1.0.0"), vec![]);
+///
+/// let options = FetchOptions {
+///     kind_depths: KindDepths {
+///         build: Some(1),
+///         ..KindDepths::default()
+///     },
+///     ..FetchOptions::default()
+/// };
+/// let mut graph = DependencyGraph::new();
+/// graph.fetch_dependency_tree_with_registry("app", 3, &options, &registry).unwrap();
+///
+/// // `cc` is a build-dependency at level 2, still recorded as one
+/// let ring = graph.package("ring").unwrap();
+/// assert!(ring.dependencies.is_empty());
+/// assert_eq!(ring.build_dependencies, vec!["cc"]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KindDepths {
+    /// The deepest level of `[dependencies]`.
+    pub normal: Option<usize>,
+    /// The deepest level of `[build-dependencies]`.
+    pub build: Option<usize>,
+    /// The deepest level of `[dev-dependencies]`.
+    pub dev: Option<usize>,
+}
+
+impl KindDepths {
+    /// Returns `true` if a dependency of the given kind may be listed at the given level.
+    pub fn allows(&self, kind: DependencyKind, level: usize) -> bool {
+        let limit = match kind {
+            DependencyKind::Normal => self.normal,
+            DependencyKind::Build => self.build,
+            DependencyKind::Dev => self.dev,
+        };
+        limit.is_none_or(|limit| level <= limit)
+    }
 }
 
 /// Parses the dependencies from the content of a Cargo.toml file.
@@ -142,21 +625,200 @@ impl Package {
 ///
 /// # Returns
 ///
-/// A Result containing a Vec of dependency names or an error if parsing fails.
-pub fn parse_dependencies(
+/// A Result containing a Vec of `(name, requirement)` tuples, like the dependencies of a
+/// `Package`, or `DepthError::Parse` if the manifest is not valid TOML. Dependencies declared
+/// without a version, such as git, path or workspace dependencies, have an empty requirement.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::package::parse_dependencies;
+///
+/// let manifest = r#"
+///     [dependencies]
+///     serde = "1.0"
+///     tokio = { version = "1.35", features = ["full"] }
+///     local = { path = "../local" }
+///     forked = { git = "https://github.com/owner/forked" }
+/// "#;
+///
+/// let requirement = |name: &str| (name.to_string(), String::new());
+/// assert_eq!(
+///     parse_dependencies(manifest).unwrap(),
+///     vec![
+///         requirement("forked"),
+///         requirement("local"),
+///         ("serde".to_string(), "1.0".to_string()),
+///         ("tokio".to_string(), "1.35".to_string()),
+///     ]
+/// );
+/// ```
+pub fn parse_dependencies(cargo_toml_content: &str) -> Result<Vec<(String, String)>, DepthError> {
+    let cargo_toml: Value = cargo_toml_content.parse()?;
+    Ok(table_dependencies(&cargo_toml, "dependencies"))
+}
+
+/// Returns the dependencies declared in the given table of a manifest as `(name, requirement)`
+/// tuples, empty if the table is missing.
+fn table_dependencies(cargo_toml: &Value, table: &str) -> Vec<(String, String)> {
+    let Some(Value::Table(dependencies_table)) = cargo_toml.get(table) else {
+        return Vec::new();
+    };
+    dependencies_table
+        .iter()
+        .map(|(name, declaration)| {
+            // Either the `dep = "1.0"` shorthand or a `dep = { version = "1.0" }` table
+            let requirement = match declaration {
+                Value::String(requirement) => Some(requirement.as_str()),
+                Value::Table(table) => table.get("version").and_then(Value::as_str),
+                _ => None,
+            };
+            (name.clone(), requirement.unwrap_or("").to_string())
+        })
+        .collect()
+}
+
+/// Builds the root package described by the `[package]` table of a manifest.
+fn manifest_package(
+    cargo_toml: &Value,
+    dependencies: Vec<(String, String)>,
+    options: &FetchOptions,
+) -> Result<Package, Box<dyn Error>> {
+    let package_table = cargo_toml
+        .get("package")
+        .ok_or("the manifest has no [package] table")?;
+    let name = package_table
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or("the manifest has no package name")?;
+    // Fields inherited from a workspace are tables, not strings, and are left empty
+    let field = |key: &str| {
+        package_table
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string()
+    };
+
+    let mut package = Package::new(
+        name.to_string(),
+        field("homepage"),
+        dependencies,
+        options.filter.is_internal(name),
+    );
+    package.version = field("version");
+    package.license = Some(field("license")).filter(|license| !license.is_empty());
+    // Cargo reads a manifest without an edition as edition 2015
+    package.edition = match package_table.get("edition") {
+        None => Some("2015".to_string()),
+        Some(edition) => edition.as_str().map(str::to_string),
+    };
+    Ok(package)
+}
+
+/// Builds a graph of a local Cargo.toml and its direct dependencies without any network
+/// access, for private crates or working offline.
+///
+/// The dependencies of the `[dependencies]`, `[build-dependencies]` and `[dev-dependencies]`
+/// tables are added as unresolved leaves, build and dev-dependencies being marked `build` and
+/// `dev` unless they are normal dependencies too. Their own dependencies cannot be known
+/// without a registry, so the graph stops at depth 1.
+///
+/// # Arguments
+///
+/// * `cargo_toml_content` - The content of the Cargo.toml file as a string.
+/// * `graph` - A mutable reference to a DependencyGraph where package information will be stored.
+/// * `options` - The options of the fetch, only the internal crates and the kinds with a depth
+///   limit of 0, which are left out, being taken from it.
+///
+/// # Returns
+///
+/// A Result containing the root Package or an error if the manifest is invalid.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{parse_manifest_info, FetchOptions};
+///
+/// let manifest = r#"
+///     [package]
+///     name = "app"
+///     version = "0.1.0"
+///     edition = "2021"
+///
+///     [dependencies]
+///     serde = "1"
+///
+///     [build-dependencies]
+///     cc = "1"
+///
+///     [dev-dependencies]
+///     insta = "1"
+///     serde = "1"
+/// "#;
+///
+/// let mut graph = DependencyGraph::new();
+/// let root = parse_manifest_info(manifest, &mut graph, &FetchOptions::default()).unwrap();
+///
+/// let names: Vec<&str> = root.dependencies.iter().map(|dep| dep.0.as_str()).collect();
+/// assert_eq!(names, vec!["serde", "cc", "insta"]);
+/// assert_eq!(root.build_dependencies, vec!["cc"]);
+/// assert_eq!(root.edition.as_deref(), Some("2021"));
+/// assert_eq!(graph.markers("cc"), ["build"]);
+/// assert_eq!(graph.markers("insta"), ["dev"]);
+/// assert!(graph.markers("serde").is_empty());
+/// ```
+pub fn parse_manifest_info(
     cargo_toml_content: &str,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    graph: &mut DependencyGraph,
+    options: &FetchOptions,
+) -> Result<Package, Box<dyn Error>> {
+    graph.set_fetch_options(options);
+    let options = &with_prefetch_cache(options);
     let cargo_toml: Value = cargo_toml_content.parse()?;
+    let normal = parse_dependencies(cargo_toml_content)?;
+    let build = table_dependencies(&cargo_toml, "build-dependencies");
+    let dev = table_dependencies(&cargo_toml, "dev-dependencies");
+    let is_normal = |name: &str| normal.iter().any(|dependency| dependency.0 == name);
+    let allows = |kind| options.kind_depths.allows(kind, 1);
+    let listed_build: &[_] = if allows(DependencyKind::Build) {
+        &build
+    } else {
+        &[]
+    };
+    let listed_dev: &[_] = if allows(DependencyKind::Dev) {
+        &dev
+    } else {
+        &[]
+    };
 
-    if let Some(Value::Table(dependencies_table)) = cargo_toml.get("dependencies") {
-        let dependencies: Vec<String> = dependencies_table
+    let mut dependencies: Vec<(String, String)> = Vec::new();
+    for dependency in normal.iter().chain(listed_build).chain(listed_dev) {
+        if !options.filter.omits(&dependency.0)
+            && !dependencies.iter().any(|listed| listed.0 == dependency.0)
+        {
+            dependencies.push(dependency.clone());
+        }
+    }
+
+    let mut package = manifest_package(&cargo_toml, dependencies, options)?;
+    package.build_dependencies = build
+        .iter()
+        .map(|dependency| dependency.0.clone())
+        .collect();
+    package.build_dependencies.sort();
+    graph.add_package_to_graph(&package);
+    for (dependencies, marker) in [(listed_build, "build"), (listed_dev, "dev")] {
+        for dependency in dependencies
             .iter()
-            .map(|(dep_name, _)| dep_name.clone())
-            .collect();
-        return Ok(dependencies);
+            .filter(|dependency| !is_normal(&dependency.0))
+        {
+            graph.add_marker(&dependency.0, marker);
+        }
     }
 
-    Ok(Vec::new())
+    Ok(package)
 }
 
 /// Fetches package information, including dependencies, from Crates.io and builds a dependency graph.
@@ -166,87 +828,1177 @@ pub fn parse_dependencies(
 /// * `package_name` - A tuple containing the package name and its homepage URL.
 /// * `visited_packages` - A mutable HashMap to store visited packages and prevent redundant fetching.
 /// * `graph` - A mutable reference to a DependencyGraph where package information will be stored.
-/// * `client` - A SyncClient instance for interacting with the Crates.io API.
+/// * `client` - A CrateRegistry instance, such as the Crates.io SyncClient, to fetch crates from.
 /// * `depth` - The depth up to which dependencies should be fetched and added to the graph.
-/// * `optional` - A boolean to scan optional dependencies only.
+/// * `options` - The options controlling which dependencies are listed and followed.
 ///
 /// # Returns
 ///
-/// A Result containing an optional Package or an error if the fetching process fails.
+/// A Result containing an optional Package or an error if the fetching process fails:
+/// `DepthError::CrateNotFound` if the package does not exist, `DepthError::InvalidDepth` if the
+/// depth is 0 and `DepthError::NoMatchingVersion` if no version matches `options.version`.
+///
+/// # Notes
+///
+/// Dependencies that cannot be fetched, because they are private, gone or yanked (HTTP 403/404)
+/// or because their request failed, do not abort the fetch: a warning is printed, the crate is
+/// recorded on the graph with the reason, see `DependencyGraph::errors`, and its subtree is
+/// skipped. Only a failure to fetch the package itself is returned as an error, unless
+/// `FetchOptions::strict` is set, which returns the first failure of a dependency too.
+///
+/// A dependency listed more than once, e.g. once for every target or as both a normal and a
+/// dev-dependency, is merged into a single child of the strongest of its kinds.
+///
+/// A dependency on a crate that is still being fetched, i.e. a cycle such as the ones
+/// dev-dependencies form, is linked to the crate without fetching it again, and the cycle is
+/// logged. This guard does not rely on the visited packages, so it holds however they are keyed.
+///
+/// Dev-dependencies are only listed for the fetched package itself, like Cargo which never builds
+/// the dev-dependencies of dependencies. Set `FetchOptions::dev_recursive` to list them at every
+/// level, which can make the tree a lot larger.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{fetch_package_info, FetchOptions};
+/// use depth::registry::{CrateInfo, DependencyInfo, DependencyKind, MemoryRegistry};
+/// use std::collections::HashMap;
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(
+///     CrateInfo::new("app", "1.0.0"),
+///     vec![
+///         DependencyInfo::new("libc", "^0.2"),
+///         DependencyInfo::new("libc", "^0.2.150"),
+///         DependencyInfo {
+///             kind: DependencyKind::Dev,
+///             ..DependencyInfo::new("rand", "^0.8")
+///         },
+///         DependencyInfo::new("rand", "^0.8"),
+///     ],
+/// );
+/// registry.add_crate(CrateInfo::new("libc", "0.2.150"), vec![]);
+/// registry.add_crate(CrateInfo::new("rand", "0.8.5"), vec![]);
+///
+/// let mut graph = DependencyGraph::new();
+/// let app = fetch_package_info(
+///     &("app".to_string(), "".to_string()),
+///     &mut HashMap::new(),
+///     &mut graph,
+///     &registry,
+///     2,
+///     &FetchOptions::default(),
+/// )
+/// .unwrap()
+/// .unwrap();
+///
+/// let names: Vec<&str> = app.dependencies.iter().map(|dep| dep.0.as_str()).collect();
+/// assert_eq!(names, ["libc", "rand"]);
+/// assert_eq!(app.dependencies[0].1, "^0.2");
+/// assert_eq!(graph.dependency_counts(&app, 2), (2, 0));
+///
+/// // Listed as both a dev and a normal dependency, `rand` is built as a normal one
+/// assert_eq!(app.dependency_kind("rand"), DependencyKind::Normal);
+/// ```
+///
+/// Only the dev-dependencies of the root are followed by default:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{fetch_package_info, FetchOptions};
+/// use depth::registry::{CrateInfo, DependencyInfo, DependencyKind, MemoryRegistry};
+/// use std::collections::HashMap;
+///
+/// let dev = |name: &str| DependencyInfo {
+///     kind: DependencyKind::Dev,
+///     ..DependencyInfo::new(name, "^1")
+/// };
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(
+///     CrateInfo::new("app", "1.0.0"),
+///     vec![DependencyInfo::new("serde", "^1"), dev("insta")],
+/// );
+/// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![dev("serde_test")]);
+/// registry.add_crate(CrateInfo::new("insta", "1.0.0"), vec![]);
+/// registry.add_crate(CrateInfo::new("serde_test", "1.0.0"), vec![]);
+///
+/// let fetch = |options: &FetchOptions| {
+///     let mut graph = DependencyGraph::new();
+///     let app = fetch_package_info(
+///         &("app".to_string(), "".to_string()),
+///         &mut HashMap::new(),
+///         &mut graph,
+///         &registry,
+///         3,
+///         options,
+///     )
+///     .unwrap()
+///     .unwrap();
+///     graph.dependency_counts(&app, 3)
+/// };
+///
+/// // `insta` is listed, but not the dev-dependency `serde_test` of `serde`
+/// assert_eq!(fetch(&FetchOptions::default()), (2, 0));
+///
+/// let options = FetchOptions {
+///     dev_recursive: true,
+///     ..FetchOptions::default()
+/// };
+/// assert_eq!(fetch(&options), (2, 1));
+/// ```
+///
+/// Mutually dependent crates are fetched once each:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{fetch_package_info, FetchOptions};
+/// use depth::registry::{CrateInfo, DependencyInfo, DependencyKind, MemoryRegistry, TimedRegistry};
+/// use std::collections::HashMap;
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(
+///     CrateInfo::new("serde", "1.0.0"),
+///     vec![DependencyInfo::new("serde_derive", "^1")],
+/// );
+/// registry.add_crate(
+///     CrateInfo::new("serde_derive", "1.0.0"),
+///     vec![DependencyInfo {
+///         kind: DependencyKind::Dev,
+///         ..DependencyInfo::new("serde", "^1")
+///     }],
+/// );
+/// let registry = TimedRegistry::new(registry);
+///
+/// let options = FetchOptions {
+///     dev_recursive: true,
+///     ..FetchOptions::default()
+/// };
+/// let mut graph = DependencyGraph::new();
+/// fetch_package_info(
+///     &("serde".to_string(), "".to_string()),
+///     &mut HashMap::new(),
+///     &mut graph,
+///     &registry,
+///     10,
+///     &options,
+/// )
+/// .unwrap();
+///
+/// assert!(graph.is_reachable("serde_derive", "serde"));
+/// assert_eq!(registry.request_count(), 4);
+/// ```
+///
+/// Sibling crates can be fetched concurrently, the graph being the same:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{fetch_package_info, FetchOptions};
+/// use depth::registry::{MemoryRegistry, TimedRegistry};
+/// use std::collections::HashMap;
+///
+/// let registry = MemoryRegistry::from_edges(&[
+///     ("app", &["clap", "serde", "tokio"]),
+///     ("clap", &["syn"]),
+///     ("serde", &["serde_derive"]),
+///     ("serde_derive", &["syn"]),
+///     ("syn", &[]),
+///     ("tokio", &["mio"]),
+///     ("mio", &[]),
+/// ]);
+/// let registry = TimedRegistry::new(registry);
+///
+/// let fetch = |jobs: usize| {
+///     let options = FetchOptions {
+///         jobs,
+///         ..FetchOptions::default()
+///     };
+///     let mut graph = DependencyGraph::new();
+///     fetch_package_info(
+///         &("app".to_string(), "".to_string()),
+///         &mut HashMap::new(),
+///         &mut graph,
+///         &registry,
+///         4,
+///         &options,
+///     )
+///     .unwrap();
+///     graph.to_json()
+/// };
+///
+/// let serial = fetch(1);
+/// let requests = registry.request_count();
+/// assert_eq!(fetch(4), serial);
+/// // Prefetched crates are not requested again
+/// assert_eq!(registry.request_count(), 2 * requests);
+/// ```
+///
+/// A missing dependency is recorded, unless the fetch is strict:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::error::DepthError;
+/// use depth::package::{fetch_package_info, FetchOptions};
+/// use depth::registry::{CrateInfo, DependencyInfo, ErrorReason, MemoryRegistry};
+/// use std::collections::HashMap;
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(
+///     CrateInfo::new("app", "1.0.0"),
+///     vec![DependencyInfo::new("gone", "^1"), DependencyInfo::new("serde", "^1")],
+/// );
+/// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![]);
+///
+/// let fetch = |graph: &mut DependencyGraph, strict: bool| {
+///     let options = FetchOptions {
+///         strict,
+///         ..FetchOptions::default()
+///     };
+///     let app = ("app".to_string(), "".to_string());
+///     fetch_package_info(&app, &mut HashMap::new(), graph, &registry, 2, &options)
+/// };
+///
+/// let mut graph = DependencyGraph::new();
+/// fetch(&mut graph, false).unwrap();
+/// assert!(graph.package("serde").is_some());
+/// assert_eq!(graph.errors()[0].name, "gone");
+/// assert_eq!(graph.errors()[0].reason, ErrorReason::NotFound);
+///
+/// let err = fetch(&mut DependencyGraph::new(), true).unwrap_err();
+/// assert!(matches!(err, DepthError::CrateNotFound(name) if name == "gone"));
+/// ```
+///
+/// The root crate can be resolved to an older version than its latest one:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::error::DepthError;
+/// use depth::package::{fetch_package_info, FetchOptions};
+/// use depth::registry::{CrateInfo, MemoryRegistry, VersionInfo};
+/// use depth::semver_util::parse_version_req;
+/// use std::collections::HashMap;
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("tokio", "1.40.0"), vec![]);
+/// for num in ["1.40.0", "1.38.1", "1.38.0", "0.2.25"] {
+///     registry.add_version("tokio", VersionInfo::new(num));
+/// }
+///
+/// let fetch = |version: &str| {
+///     let options = FetchOptions {
+///         version: Some(parse_version_req(version).unwrap()),
+///         ..FetchOptions::default()
+///     };
+///     fetch_package_info(
+///         &("tokio".to_string(), "".to_string()),
+///         &mut HashMap::new(),
+///         &mut DependencyGraph::new(),
+///         &registry,
+///         2,
+///         &options,
+///     )
+/// };
+///
+/// assert_eq!(fetch("~1.38").unwrap().unwrap().version, "1.38.1");
+/// assert_eq!(fetch("1.38.0").unwrap().unwrap().version, "1.38.0");
+/// let err = fetch("^2").unwrap_err();
+/// assert!(matches!(err, DepthError::NoMatchingVersion { .. }));
+/// assert_eq!(
+///     err.to_string(),
+///     "no version of 'tokio' matches '^2', available versions: 1.40.0, 1.38.1, 1.38.0, 0.2.25"
+/// );
+/// ```
+///
+/// An older version is described by its own metadata, features and license included:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{fetch_package_info, FetchOptions};
+/// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry, VersionInfo};
+/// use depth::semver_util::parse_version_req;
+/// use std::collections::HashMap;
+///
+/// let derive = DependencyInfo {
+///     optional: true,
+///     ..DependencyInfo::new("app_derive", "^1")
+/// };
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("app", "2.0.0"), vec![derive]);
+/// registry.add_crate(CrateInfo::new("app_derive", "1.0.0"), vec![]);
+/// registry.add_version("app", VersionInfo::new("2.0.0"));
+/// registry.add_version(
+///     "app",
+///     VersionInfo {
+///         license: Some("MIT".to_string()),
+///         features: HashMap::from([("derive".to_string(), vec!["dep:app_derive".to_string()])]),
+///         ..VersionInfo::new("1.0.0")
+///     },
+/// );
+///
+/// let options = FetchOptions {
+///     version: Some(parse_version_req("=1.0.0").unwrap()),
+///     features: Some(vec!["derive".to_string()]),
+///     ..FetchOptions::default()
+/// };
+/// let app = ("app".to_string(), "".to_string());
+/// let mut graph = DependencyGraph::new();
+/// let app = fetch_package_info(&app, &mut HashMap::new(), &mut graph, &registry, 2, &options)
+///     .unwrap()
+///     .unwrap();
+///
+/// assert_eq!(app.version, "1.0.0");
+/// assert_eq!(app.license.as_deref(), Some("MIT"));
+/// assert_eq!(app.dependencies, [("app_derive".to_string(), "^1".to_string())]);
+/// ```
+///
+/// The dependencies followed per crate can be capped, normal dependencies being kept first:
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{fetch_package_info, FetchOptions};
+/// use depth::registry::{CrateInfo, DependencyInfo, DependencyKind, MemoryRegistry};
+/// use std::collections::HashMap;
+///
+/// let dependency = |name: &str, kind: DependencyKind| DependencyInfo {
+///     kind,
+///     ..DependencyInfo::new(name, "^1")
+/// };
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(
+///     CrateInfo::new("app", "1.0.0"),
+///     vec![
+///         dependency("cc", DependencyKind::Build),
+///         dependency("serde", DependencyKind::Normal),
+///         dependency("rand", DependencyKind::Dev),
+///         dependency("clap", DependencyKind::Normal),
+///     ],
+/// );
+///
+/// let options = FetchOptions {
+///     max_fanout: Some(2),
+///     ..FetchOptions::default()
+/// };
+/// let mut graph = DependencyGraph::new();
+/// let app = ("app".to_string(), "".to_string());
+/// let app = fetch_package_info(&app, &mut HashMap::new(), &mut graph, &registry, 1, &options)
+///     .unwrap()
+///     .unwrap();
+///
+/// let followed: Vec<&str> = app.dependencies.iter().map(|dep| dep.0.as_str()).collect();
+/// assert_eq!(followed, ["serde", "clap"]);
+/// assert_eq!(graph.markers("app"), ["and 2 more"]);
+/// ```
 pub fn fetch_package_info(
     package_name: &(String, String),
     visited_packages: &mut HashMap<String, Package>,
     graph: &mut DependencyGraph,
-    client: &SyncClient,
+    client: &impl CrateRegistry,
     depth: usize,
-    optional: bool,
-) -> Result<Option<Package>, Box<dyn Error>> {
-    if let Some(package) = visited_packages.get(&package_name.0) {
+    options: &FetchOptions,
+) -> Result<Option<Package>, DepthError> {
+    if depth == 0 {
+        return Err(DepthError::InvalidDepth(depth));
+    }
+    graph.set_fetch_options(options);
+    let options = &with_root_version(&package_name.0, client, with_prefetch_cache(options))?;
+    let position = TreePosition { depth, level: 0 };
+    let fetched = fetch_package(
+        package_name,
+        visited_packages,
+        graph,
+        client,
+        position,
+        options,
+    );
+    if options.progress {
+        progress::finish();
+    }
+    Ok(fetched?)
+}
+
+/// Puts an in-memory cache in front of the cache of the options when crates are prefetched, so
+/// the prefetched metadata is found by the fetch even when the options cache nothing.
+fn with_prefetch_cache(options: &FetchOptions) -> Cow<'_, FetchOptions> {
+    if options.jobs <= 1 {
+        return Cow::Borrowed(options);
+    }
+    Cow::Owned(FetchOptions {
+        cache: Arc::new(MemoryCache::new(options.cache.clone())),
+        ..options.clone()
+    })
+}
+
+/// Pins the root crate to the version matching `options.version` in the lock of the options, so
+/// that its metadata and dependencies are fetched for that version.
+fn with_root_version<'a>(
+    crate_name: &str,
+    client: &impl CrateRegistry,
+    options: Cow<'a, FetchOptions>,
+) -> Result<Cow<'a, FetchOptions>, DepthError> {
+    let Some(req) = &options.version else {
+        return Ok(options);
+    };
+    let Some(version) = resolve_version(client, crate_name, req)? else {
+        let mut available: Vec<Version> = client
+            .crate_versions(crate_name)?
+            .iter()
+            .filter(|version| !version.yanked)
+            .filter_map(|version| Version::parse(&version.num).ok())
+            .collect();
+        available.sort_unstable_by(|a, b| b.cmp(a));
+        return Err(DepthError::NoMatchingVersion {
+            name: crate_name.to_string(),
+            req: req.to_string(),
+            available: available.iter().map(Version::to_string).collect(),
+        });
+    };
+
+    let mut options = options.into_owned();
+    options
+        .lock
+        .versions
+        .insert(crate_name.to_string(), version.to_string());
+    Ok(Cow::Owned(options))
+}
+
+/// Fetches the metadata and the dependencies of the given crates into the cache of the options,
+/// with `options.jobs` threads, so that fetching them in turn afterwards makes no requests.
+///
+/// Failures are ignored, the fetch requesting the crate again and reporting them.
+fn prefetch(crate_names: &[&str], client: &impl CrateRegistry, options: &FetchOptions) {
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..options.jobs.min(crate_names.len()) {
+            scope.spawn(|| {
+                while let Some(crate_name) = crate_names.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    prefetch_crate(crate_name, client, options);
+                }
+            });
+        }
+    });
+}
+
+/// Fetches the metadata of a crate and the dependencies of the version it resolves to into the
+/// cache of the options, unless they are cached already.
+fn prefetch_crate(crate_name: &str, client: &impl CrateRegistry, options: &FetchOptions) {
+    let crate_info = match options.cache.get_crate(crate_name) {
+        Some(crate_info) => crate_info,
+        None => match client.get_crate(crate_name) {
+            Ok(crate_info) => {
+                options.cache.put_crate(&crate_info);
+                crate_info
+            }
+            Err(_) => return,
+        },
+    };
+
+    let version = options
+        .lock
+        .version(crate_name)
+        .unwrap_or(&crate_info.max_version);
+    if options.cache.get(&crate_info.name, version).is_none() {
+        if let Ok(dependencies) = client.crate_dependencies(&crate_info.name, version) {
+            options.cache.put(&crate_info.name, version, &dependencies);
+        }
+    }
+}
+
+/// Fetches the metadata and the dependencies of a crate and of the crates it depends on, down
+/// to the given depth, into the cache of the options, so that fetching the tree afterwards
+/// makes no requests. Up to `options.jobs` crates are fetched at once, each in its own task.
+///
+/// Each crate is fetched once: the crates already fetched or being fetched are kept by the loop
+/// spawning the tasks, which share the fetched metadata through the cache alone. Failures are
+/// ignored, the fetch requesting the crate again and reporting them.
+#[cfg(feature = "async")]
+pub(crate) async fn prefetch_tree_async(
+    package_name: &str,
+    depth: usize,
+    client: Arc<AsyncClientPool>,
+    options: Arc<FetchOptions>,
+) {
+    let permits = Arc::new(Semaphore::new(options.jobs.max(1)));
+    let mut fetched = HashSet::from([package_name.to_string()]);
+    let mut tasks = JoinSet::new();
+    let spawn = |tasks: &mut JoinSet<_>, crate_name: String, level: usize| {
+        let (client, options, permits) = (client.clone(), options.clone(), permits.clone());
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let dependencies = prefetch_crate_async(&crate_name, level, &client, &options).await;
+            (level, dependencies)
+        });
+    };
+    spawn(&mut tasks, package_name.to_string(), 0);
+
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((level, dependencies)) = joined else {
+            continue;
+        };
+        // The crates at the last level are fetched, but not their dependencies
+        if level + 2 > depth {
+            continue;
+        }
+        for dependency in dependencies {
+            // The graph has a node per fetched crate, so the fetch would not use crates past the cap
+            if fetched.len() >= options.max_nodes {
+                break;
+            }
+            if !options.filter.is_internal(&dependency)
+                && !options.filter.is_excluded(&dependency)
+                && options.filter.allows(&dependency)
+                && fetched.insert(dependency.clone())
+            {
+                spawn(&mut tasks, dependency, level + 1);
+            }
+        }
+    }
+}
+
+/// Fetches the metadata of a crate and the dependencies of the version it resolves to into the
+/// cache of the options, unless they are cached already.
+///
+/// # Returns
+///
+/// The names of the dependencies the crate follows at the given level, empty if it could not
+/// be fetched.
+#[cfg(feature = "async")]
+async fn prefetch_crate_async(
+    crate_name: &str,
+    level: usize,
+    client: &AsyncClientPool,
+    options: &FetchOptions,
+) -> Vec<String> {
+    let mut crate_info = match options.cache.get_crate(crate_name) {
+        Some(crate_info) => crate_info,
+        None => match client.get_crate(crate_name).await {
+            Ok(crate_info) => {
+                options.cache.put_crate(&crate_info);
+                crate_info
+            }
+            Err(_) => return Vec::new(),
+        },
+    };
+    if let Some(locked) = options.lock.version(crate_name) {
+        crate_info.max_version = locked.to_string();
+    }
+
+    let declared = match options.cache.get(&crate_info.name, &crate_info.max_version) {
+        Some(declared) => declared,
+        None => match client
+            .crate_dependencies(&crate_info.name, &crate_info.max_version)
+            .await
+        {
+            Ok(declared) => {
+                options
+                    .cache
+                    .put(&crate_info.name, &crate_info.max_version, &declared);
+                declared
+            }
+            Err(_) => return Vec::new(),
+        },
+    };
+    select_dependencies(&crate_info, declared, options, level, false)
+        .dependencies
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// The position of a package being fetched in the dependency tree.
+#[derive(Debug, Clone, Copy)]
+struct TreePosition {
+    /// The number of levels left to fetch, the package's own level included.
+    depth: usize,
+    /// The distance from the root, which is at level 0.
+    level: usize,
+}
+
+impl TreePosition {
+    /// Returns the position of the dependencies of a package at this position.
+    fn child(self) -> Self {
+        TreePosition {
+            depth: self.depth - 1,
+            level: self.level + 1,
+        }
+    }
+}
+
+/// Fetches a package of the tree at the given position, see `fetch_package_info`.
+fn fetch_package(
+    package_name: &(String, String),
+    visited_packages: &mut HashMap<String, Package>,
+    graph: &mut DependencyGraph,
+    client: &impl CrateRegistry,
+    position: TreePosition,
+    options: &FetchOptions,
+) -> Result<Option<Package>, RegistryError> {
+    let cached = visited_packages.get(&package_name.0);
+    graph.record_lookup(cached.is_some());
+    if let Some(package) = cached {
+        trace!("'{}' was already fetched", package_name.0);
         return Ok(Some(package.clone()));
     }
+    if options.progress {
+        progress::tick(visited_packages.len(), &package_name.0);
+    }
+    let started = Instant::now();
 
-    let crate_info = client.get_crate(&package_name.0)?.crate_data;
+    let mut crate_info = match options.cache.get_crate(&package_name.0) {
+        Some(crate_info) => crate_info,
+        None => {
+            let crate_info = client.get_crate(&package_name.0)?;
+            options.cache.put_crate(&crate_info);
+            crate_info
+        }
+    };
+    // Crates.io reports a max version of 0.0.0 when every version is yanked
+    if crate_info.max_version == "0.0.0" && options.lock.version(&package_name.0).is_none() {
+        return Err(RegistryError::NoVersions(package_name.0.clone()));
+    }
+    if let Some(locked) = options.lock.version(&package_name.0) {
+        if locked != crate_info.max_version {
+            // The crate metadata describes the latest version, the pinned one has its own
+            let pinned = client
+                .crate_versions(&package_name.0)?
+                .into_iter()
+                .find(|version| version.num == locked)
+                .unwrap_or_else(|| VersionInfo::new(locked));
+            crate_info.max_version = pinned.num;
+            crate_info.license = pinned.license;
+            crate_info.crate_size = pinned.crate_size;
+            crate_info.rust_version = pinned.rust_version;
+            crate_info.features = pinned.features;
+        }
+    }
 
     let homepage = crate_info.clone().homepage.unwrap_or("".to_string());
-    let dependencies = list_dependencies(client, &crate_info, optional)?;
+    let ListedDependencies {
+        dependencies,
+        build_dependencies,
+        optional_dependencies,
+        kinds,
+        truncated,
+    } = list_dependencies(client, &crate_info, options, position.level)?;
+    if truncated > 0 {
+        graph.add_marker(&package_name.0, &format!("and {} more", truncated));
+    }
 
-    let internal = package_name.0.starts_with("std");
+    let internal = options.filter.is_internal(&package_name.0);
 
-    let package = Package::new(
+    let mut package = Package::new(
         package_name.0.to_string(),
         homepage,
         dependencies.clone(),
         internal,
     );
+    package.version = crate_info.max_version.clone();
+    package.license = crate_info.license.clone();
+    package.size = crate_info.crate_size;
+    package.downloads = crate_info.downloads;
+    package.features = feature_dependencies(&crate_info.features, &optional_dependencies);
+    package.build_dependencies = build_dependencies;
+    package.dependency_kinds = kinds;
+    package.max_edition = crate_info
+        .rust_version
+        .as_deref()
+        .and_then(edition_for_rust_version)
+        .map(str::to_string);
+    debug!(
+        "fetched '{}' v{} at level {} in {:.2?}",
+        package.name,
+        package.version,
+        position.level,
+        started.elapsed()
+    );
     visited_packages.insert(package_name.0.to_string(), package.clone());
 
-    let node_index = graph.add_package_to_graph(&package);
+    graph.add_package_to_graph(&package);
 
     // Add dependencies to the graph up to the specified depth
+    if position.depth > 1 {
+        graph.enter_fetch_path(&package.name);
+        let fetched = fetch_dependencies(
+            &dependencies,
+            visited_packages,
+            graph,
+            client,
+            position.child(),
+            options,
+        );
+        graph.leave_fetch_path();
+        fetched?;
+    }
+
+    Ok(Some(package))
+}
+
+/// Builds a dependency graph rooted at a package described by the content of a Cargo.toml file,
+/// such as an unpublished manifest downloaded with `--manifest-url`.
+///
+/// The root package is read from the `[package]` table and its dependencies from the
+/// `[dependencies]` table, then every dependency is resolved transitively from the registry.
+///
+/// # Arguments
+///
+/// * `cargo_toml_content` - The content of the Cargo.toml file as a string.
+/// * `visited_packages` - A mutable HashMap to store visited packages and prevent redundant fetching.
+/// * `graph` - A mutable reference to a DependencyGraph where package information will be stored.
+/// * `client` - A CrateRegistry instance, such as the Crates.io SyncClient, to fetch crates from.
+/// * `depth` - The depth up to which dependencies should be fetched and added to the graph.
+/// * `options` - The options controlling which dependencies are followed.
+///
+/// # Returns
+///
+/// A Result containing the root Package or an error if the manifest is invalid or fetching fails.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::{fetch_manifest_info, FetchOptions};
+/// use depth::registry::{CrateInfo, MemoryRegistry};
+/// use std::collections::HashMap;
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![]);
+///
+/// let manifest = r#"
+///     [package]
+///     name = "app"
+///     version = "0.2.0-dev"
+///
+///     [dependencies]
+///     serde = "1"
+/// "#;
+///
+/// let mut graph = DependencyGraph::new();
+/// let root = fetch_manifest_info(
+///     manifest,
+///     &mut HashMap::new(),
+///     &mut graph,
+///     &registry,
+///     2,
+///     &FetchOptions::default(),
+/// )
+/// .unwrap();
+///
+/// assert_eq!(root.version, "0.2.0-dev");
+/// assert_eq!(graph.package("serde").unwrap().version, "1.0.0");
+/// ```
+pub fn fetch_manifest_info(
+    cargo_toml_content: &str,
+    visited_packages: &mut HashMap<String, Package>,
+    graph: &mut DependencyGraph,
+    client: &impl CrateRegistry,
+    depth: usize,
+    options: &FetchOptions,
+) -> Result<Package, Box<dyn Error>> {
+    graph.set_fetch_options(options);
+    let cargo_toml: Value = cargo_toml_content.parse()?;
+    let mut dependencies = parse_dependencies(cargo_toml_content)?;
+    dependencies.retain(|dependency| !options.filter.omits(&dependency.0));
+
+    let package = manifest_package(&cargo_toml, dependencies.clone(), options)?;
+    visited_packages.insert(package.name.clone(), package.clone());
+
+    graph.add_package_to_graph(&package);
+
     if depth > 1 {
-        for dependency in &dependencies {
-            if let Some(child_package) = fetch_package_info(
-                dependency,
-                visited_packages,
-                graph,
-                client,
-                depth - 1,
-                optional,
-            )? {
-                let child_index = graph.add_package_to_graph(&child_package);
-                graph.add_dependency_edge(node_index, child_index);
+        graph.enter_fetch_path(&package.name);
+        let fetched = fetch_dependencies(
+            &dependencies,
+            visited_packages,
+            graph,
+            client,
+            TreePosition { depth, level: 0 }.child(),
+            options,
+        );
+        graph.leave_fetch_path();
+        if options.progress {
+            progress::finish();
+        }
+        fetched?;
+    }
+
+    Ok(package)
+}
+
+/// Builds the inverted graph of a crate: the crates depending on it, the crates depending on
+/// those, and so on up to the given depth.
+///
+/// Edges point from each dependent to the crate it depends on, so every package of the graph
+/// lists, among its `dependencies`, the crates of the inverted graph it depends on, with its
+/// requirement on them. Dependents are listed from their reverse dependencies alone, without
+/// fetching their own metadata, so their homepage is left empty.
+///
+/// # Arguments
+///
+/// * `crate_name` - The name of the crate whose dependents to fetch.
+/// * `graph` - A mutable reference to a DependencyGraph where the dependents will be stored.
+/// * `client` - A CrateRegistry instance, such as the Crates.io SyncClient, to fetch crates from.
+/// * `depth` - The number of levels of the inverted graph, the crate itself included, so the
+///   reverse dependencies of crates at the last level are never requested.
+///
+/// # Returns
+///
+/// A Result containing the package of the crate, `None` if it does not exist, or an error if it
+/// cannot be fetched.
+///
+/// # Notes
+///
+/// Crates whose reverse dependencies cannot be listed (HTTP 403/404) are recorded as
+/// unavailable on the graph and the rest of the graph is still built.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::fetch_reverse_dependencies;
+/// use depth::registry::{MemoryRegistry, TimedRegistry};
+///
+/// // Two dependents per page, so `tokio` only shows up on the second page
+/// let registry = MemoryRegistry::from_edges(&[
+///     ("libc", &[]),
+///     ("getrandom", &["libc"]),
+///     ("mio", &["libc"]),
+///     ("tokio", &["libc", "mio"]),
+///     ("app", &["tokio"]),
+/// ])
+/// .with_page_size(2);
+/// let registry = TimedRegistry::new(registry);
+///
+/// let mut graph = DependencyGraph::new();
+/// let libc = fetch_reverse_dependencies("libc", &mut graph, &registry, 2)
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(libc.version, "1.0.0");
+/// let mut names: Vec<&str> = graph.packages().iter().map(|package| package.name.as_str()).collect();
+/// names.sort_unstable();
+/// assert_eq!(names, vec!["getrandom", "libc", "mio", "tokio"]);
+/// // The crate itself, then two pages of its dependents
+/// assert_eq!(registry.request_count(), 3);
+///
+/// let mut graph = DependencyGraph::new();
+/// fetch_reverse_dependencies("libc", &mut graph, &registry, 3).unwrap();
+/// assert!(graph.is_reachable("app", "libc"));
+/// assert_eq!(
+///     graph.package("tokio").unwrap().dependencies,
+///     vec![("libc".to_string(), "^1".to_string()), ("mio".to_string(), "^1".to_string())]
+/// );
+/// assert!(fetch_reverse_dependencies("rand", &mut graph, &registry, 2)
+///     .unwrap()
+///     .is_none());
+/// ```
+pub fn fetch_reverse_dependencies(
+    crate_name: &str,
+    graph: &mut DependencyGraph,
+    client: &impl CrateRegistry,
+    depth: usize,
+) -> Result<Option<Package>, Box<dyn Error>> {
+    let crate_info = match client.get_crate(crate_name) {
+        Ok(crate_info) => crate_info,
+        Err(RegistryError::NotFound(_)) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mut root = Package::new(
+        crate_name.to_string(),
+        crate_info.homepage.unwrap_or_default(),
+        vec![],
+        false,
+    );
+    root.version = crate_info.max_version;
+
+    let mut packages = BTreeMap::from([(root.name.clone(), root)]);
+    let mut level = vec![crate_name.to_string()];
+    for _ in 1..depth {
+        let mut next_level = Vec::new();
+        for name in &level {
+            let dependents = match client.reverse_dependencies(name) {
+                Ok(dependents) => dependents,
+                Err(err) => match CrateError::new(name, &err) {
+                    Some(error) => {
+                        warn!("{}, skipping its reverse dependencies", error.message);
+                        graph.record_error(error);
+                        continue;
+                    }
+                    None => return Err(err.into()),
+                },
+            };
+            for dependent in dependents {
+                let package = packages.entry(dependent.name.clone()).or_insert_with(|| {
+                    next_level.push(dependent.name.clone());
+                    let mut package =
+                        Package::new(dependent.name.clone(), "".to_string(), vec![], false);
+                    package.version = dependent.version.clone();
+                    package
+                });
+                // A dependent listed for several targets depends on the crate once
+                if !package.dependencies.iter().any(|dep| dep.0 == *name) {
+                    package.dependencies.push((name.clone(), dependent.req));
+                }
             }
         }
+        level = next_level;
     }
 
-    Ok(Some(package))
+    // The crate comes first, so it is the first node of the graph
+    let root = packages
+        .remove(crate_name)
+        .expect("the crate was inserted first");
+    graph.add_package_to_graph(&root);
+    for package in packages.values() {
+        graph.add_package_to_graph(package);
+    }
+
+    Ok(Some(root))
 }
 
-/// Lists dependencies for a given crate from the Crates.io API.
+/// Fetches the given dependencies of a package, at the given position, into the graph. Their
+/// nodes and edges were added along with the package.
+fn fetch_dependencies(
+    dependencies: &[(String, String)],
+    visited_packages: &mut HashMap<String, Package>,
+    graph: &mut DependencyGraph,
+    client: &impl CrateRegistry,
+    position: TreePosition,
+    options: &FetchOptions,
+) -> Result<(), RegistryError> {
+    if options.jobs > 1 && graph.node_count() < options.max_nodes {
+        let pending: Vec<&str> = dependencies
+            .iter()
+            .map(|dependency| dependency.0.as_str())
+            .filter(|name| {
+                !visited_packages.contains_key(*name)
+                    && !options.filter.is_internal(name)
+                    && !options.filter.is_excluded(name)
+                    && options.filter.allows(name)
+                    && graph.fetch_cycle(name).is_none()
+            })
+            .collect();
+        prefetch(&pending, client, options);
+    }
+
+    for dependency in dependencies {
+        // Internal crates and crates rejected by the filter stay in the graph as pruned leaves
+        if options.filter.is_internal(&dependency.0) {
+            graph.add_marker(&dependency.0, "internal");
+            continue;
+        }
+        if options.filter.is_excluded(&dependency.0) {
+            graph.add_marker(&dependency.0, "excluded");
+            continue;
+        }
+        if !options.filter.allows(&dependency.0) {
+            continue;
+        }
+        if let Some(cycle) = graph.fetch_cycle(&dependency.0) {
+            warn!(
+                "dependency cycle {}, not fetching '{}' again",
+                cycle, dependency.0
+            );
+            continue;
+        }
+        if graph.node_count() >= options.max_nodes && !visited_packages.contains_key(&dependency.0)
+        {
+            if !graph.is_truncated() {
+                warn!(
+                    "the graph reached {} crates, the tree is truncated, pass a larger \
+                     --max-nodes to fetch the rest",
+                    options.max_nodes
+                );
+                graph.mark_truncated();
+            }
+            graph.add_marker(&dependency.0, "truncated");
+            continue;
+        }
+        match fetch_package(
+            dependency,
+            visited_packages,
+            graph,
+            client,
+            position,
+            options,
+        ) {
+            Ok(_) => {}
+            Err(err) if options.strict => return Err(err),
+            // Private, gone or yanked crates, and crates whose request failed, are rendered as
+            // unavailable leaves
+            Err(err) => {
+                let error = CrateError::failed(&dependency.0, &err);
+                warn!("{}, skipping its dependencies", error.message);
+                graph.record_error(error);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Fetches the given dependencies of an already fetched package, without their own
+/// dependencies, into the graph. See `DependencyGraph::expand_node`.
+pub(crate) fn fetch_children(
+    dependencies: &[(String, String)],
+    graph: &mut DependencyGraph,
+    client: &impl CrateRegistry,
+    options: &FetchOptions,
+) -> Result<(), RegistryError> {
+    let position = TreePosition { depth: 1, level: 1 };
+    fetch_dependencies(
+        dependencies,
+        &mut HashMap::new(),
+        graph,
+        client,
+        position,
+        options,
+    )
+}
+
+/// The dependencies listed for a crate by `list_dependencies`.
+struct ListedDependencies {
+    /// The dependencies to follow, as `(name, requirement)` tuples.
+    dependencies: Vec<(String, String)>,
+    /// The names of every build-dependency, whether it is followed or not, sorted.
+    build_dependencies: Vec<String>,
+    /// The names of every optional dependency, whether it is followed or not.
+    optional_dependencies: BTreeSet<String>,
+    /// The kind of the dependencies to follow that are not normal dependencies.
+    kinds: BTreeMap<String, DependencyKind>,
+    /// The number of dependencies left out by `FetchOptions::max_fanout`.
+    truncated: usize,
+}
+
+/// Lists dependencies for a given crate from a crate registry.
 ///
 /// # Arguments
 ///
-/// * `client` - A SyncClient instance for interacting with the Crates.io API.
-/// * `crate_info` - A reference to the Crate information obtained from Crates.io.
-/// * `optional` - A boolean to scan optional dependencies only.
+/// * `client` - A CrateRegistry instance to list the dependencies from.
+/// * `crate_info` - A reference to the Crate information obtained from the registry.
+/// * `options` - The options controlling which dependencies are listed.
+/// * `level` - The level of the crate in the tree, the root being at level 0.
 ///
 /// # Returns
 ///
-/// A Result containing a Vec of dependency tuples or an error if fetching fails.
+/// A Result containing the listed dependencies or an error if fetching fails.
 fn list_dependencies(
-    client: &SyncClient,
-    crate_info: &Crate,
-    optional: bool,
-) -> Result<Vec<(String, String)>, CratesIoError> {
+    client: &impl CrateRegistry,
+    crate_info: &CrateInfo,
+    options: &FetchOptions,
+    level: usize,
+) -> Result<ListedDependencies, RegistryError> {
+    let declared = match options.cache.get(&crate_info.name, &crate_info.max_version) {
+        Some(declared) => declared,
+        None => {
+            let declared = client.crate_dependencies(&crate_info.name, &crate_info.max_version)?;
+            options
+                .cache
+                .put(&crate_info.name, &crate_info.max_version, &declared);
+            declared
+        }
+    };
+    Ok(select_dependencies(
+        crate_info, declared, options, level, true,
+    ))
+}
+
+/// Selects the dependencies to follow among the ones a crate declares, see
+/// `list_dependencies`.
+///
+/// # Arguments
+///
+/// * `crate_info` - The crate declaring the dependencies.
+/// * `declared` - The dependencies declared by the resolved version of the crate.
+/// * `options` - The options controlling which dependencies are listed.
+/// * `level` - The level of the crate in the tree, the root being at level 0.
+/// * `warn` - Whether to warn about unknown features and log dependencies listed more than
+///   once, left out when a crate is only prefetched.
+fn select_dependencies(
+    crate_info: &CrateInfo,
+    declared: Vec<DependencyInfo>,
+    options: &FetchOptions,
+    level: usize,
+    warn: bool,
+) -> ListedDependencies {
     let mut dependencies = Vec::new();
+    let mut build_dependencies = Vec::new();
+    let mut kinds = BTreeMap::new();
+    let mut optional = HashSet::new();
+    let mut optional_dependencies = BTreeSet::new();
+    // Only the root lists its dev-dependencies, unless they are followed at every level
+    let include_dev = level == 0 || options.dev_recursive;
+    let enabled = match &options.features {
+        Some(features) if level == 0 => Some(enabled_dependencies(&crate_info.features, features)),
+        _ => None,
+    };
 
-    for dep in client.crate_dependencies(&crate_info.id, &crate_info.max_version)? {
-        if !dep.optional && !optional {
-            dependencies.push((dep.crate_id.clone(), dep.req.to_string()));
-        } else if optional && dep.optional {
-            dependencies.push((dep.crate_id.clone(), dep.req.to_string()));
+    if let (true, Some(features), Some(_)) = (warn, &options.features, &enabled) {
+        for feature in features {
+            let is_dependency = declared
+                .iter()
+                .any(|dep| dep.optional && dep.name == *feature);
+            if !crate_info.features.contains_key(feature) && !is_dependency {
+                warn!("'{}' has no feature '{}'", crate_info.name, feature);
+            }
         }
     }
 
-    Ok(dependencies)
+    for dep in declared {
+        if dep.kind == DependencyKind::Build && !build_dependencies.contains(&dep.name) {
+            build_dependencies.push(dep.name.clone());
+        }
+        if dep.optional {
+            optional_dependencies.insert(dep.name.clone());
+        }
+        let listed = match &enabled {
+            Some(enabled) => !dep.optional || enabled.contains(&dep.name),
+            None => dep.optional == options.optional,
+        };
+        if !listed
+            || options.filter.omits(&dep.name)
+            || (dep.kind == DependencyKind::Dev && !include_dev)
+            || !options.kind_depths.allows(dep.kind, level + 1)
+        {
+            continue;
+        }
+        // A crate may list the same dependency more than once, e.g. under several targets
+        if let Some(kind) = kinds.get_mut(&dep.name) {
+            // Built as the strongest of its kinds, e.g. normal when it is a dev-dependency too
+            *kind = dep.kind.min(*kind);
+            if warn {
+                debug!(
+                    "'{}' lists '{}' more than once, merging them",
+                    crate_info.name, dep.name
+                );
+            }
+            continue;
+        }
+        kinds.insert(dep.name.clone(), dep.kind);
+        if dep.optional {
+            optional.insert(dep.name.clone());
+        }
+        dependencies.push((dep.name.clone(), dep.req.to_string()));
+    }
+
+    let mut truncated = 0;
+    if let Some(max_fanout) = options.max_fanout.filter(|&max| dependencies.len() > max) {
+        let mut ranked: Vec<&str> = dependencies.iter().map(|dep| dep.0.as_str()).collect();
+        ranked.sort_by_key(|name| (optional.contains(*name), kinds[*name]));
+        let followed: HashSet<String> = ranked[..max_fanout]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        // The followed dependencies keep the order they are declared in
+        truncated = dependencies.len() - max_fanout;
+        dependencies.retain(|dep| followed.contains(&dep.0));
+        kinds.retain(|name, _| followed.contains(name));
+    }
+
+    build_dependencies.sort();
+    kinds.retain(|_, kind| *kind != DependencyKind::Normal);
+    ListedDependencies {
+        dependencies,
+        build_dependencies,
+        optional_dependencies,
+        kinds,
+        truncated,
+    }
 }
@@ -0,0 +1,63 @@
+//! # progress
+//!
+//! The `progress` module draws a spinner on stderr while a tree is fetched, with the number of
+//! crates fetched so far and the crate being fetched, so large trees give some feedback. It is
+//! enabled with `FetchOptions::progress`.
+//!
+//! The spinner is an `indicatif` spinner ticking on its own thread, so it keeps turning while a
+//! slow request is waited for. It stays on a single line, which is cleared once the fetch is
+//! done, leaving stdout untouched for piped output.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The frames of the spinner, the last one being shown once it is finished.
+const TICK_FRAMES: &str = "⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏ ";
+
+/// How often the spinner turns.
+const TICK_INTERVAL: Duration = Duration::from_millis(80);
+
+/// The spinner of the fetch in progress, if any.
+static SPINNER: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Shows the number of crates fetched and the crate being fetched, starting the spinner if it
+/// is not shown yet.
+pub(crate) fn tick(fetched: usize, crate_name: &str) {
+    let mut spinner = SPINNER.lock().unwrap_or_else(|err| err.into_inner());
+    let spinner = spinner.get_or_insert_with(|| {
+        let spinner = ProgressBar::new_spinner();
+        spinner.set_style(
+            ProgressStyle::with_template("{spinner} {msg}")
+                .expect("the template is valid")
+                .tick_chars(TICK_FRAMES),
+        );
+        spinner.enable_steady_tick(TICK_INTERVAL);
+        spinner
+    });
+    spinner.set_message(format!(
+        "Fetched {} crates, fetching {}",
+        fetched, crate_name
+    ));
+}
+
+/// Clears the spinner.
+pub(crate) fn finish() {
+    let spinner = SPINNER.lock().unwrap_or_else(|err| err.into_inner()).take();
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+}
+
+/// Runs `f` with the spinner hidden, so what it writes to stderr, e.g. a warning, is not drawn
+/// over by the spinner.
+pub(crate) fn suspend<T>(f: impl FnOnce() -> T) -> T {
+    let spinner = SPINNER
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+    match spinner {
+        Some(spinner) => spinner.suspend(f),
+        None => f(),
+    }
+}
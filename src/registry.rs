@@ -0,0 +1,1933 @@
+//! # registry
+//!
+//! The `registry` module abstracts the crate registry `depth` resolves packages against.
+//! The fetching logic in the `package` module only talks to a [`CrateRegistry`], which is
+//! implemented for the Crates.io `SyncClient`, for [`AlternateRegistry`], a registry serving the
+//! same API from another URL such as a private mirror, and for the in-memory
+//! [`MemoryRegistry`].
+//!
+//! # Quick Start
+//!
+//! Build an in-memory registry and fetch a dependency tree from it, without any network access.
+//! Crates that are missing from the registry (like `gone` below) or fully yanked are rendered as
+//! `(unavailable)` while the rest of the tree still renders, and are listed with the reason by
+//! `DependencyGraph::errors`:
+//!
+//! ```rust
+//! use depth::dependency_graph::DependencyGraph;
+//! use depth::package::FetchOptions;
+//! use depth::registry::{CrateInfo, DependencyInfo, ErrorReason, MemoryRegistry};
+//!
+//! let mut registry = MemoryRegistry::new();
+//! registry.add_crate(
+//!     CrateInfo::new("app", "1.0.0"),
+//!     vec![
+//!         DependencyInfo::new("gone", "^1"),
+//!         DependencyInfo::new("serde", "^1"),
+//!         DependencyInfo::new("yanked", "^1"),
+//!     ],
+//! );
+//! // Crates.io reports a max version of 0.0.0 for crates whose every version is yanked
+//! registry.add_crate(CrateInfo::new("yanked", "0.0.0"), vec![]);
+//! registry.add_crate(
+//!     CrateInfo::new("serde", "1.0.0"),
+//!     vec![DependencyInfo::new("serde_derive", "^1")],
+//! );
+//! registry.add_crate(CrateInfo::new("serde_derive", "1.0.0"), vec![]);
+//!
+//! let mut graph = DependencyGraph::new();
+//! let root = graph
+//!     .fetch_dependency_tree_with_registry("app", 3, &FetchOptions::default(), &registry)
+//!     .unwrap()
+//!     .unwrap();
+//!
+//! let mut output = Vec::new();
+//! graph.write_dependencies_at_level(&root, 0, 3, &mut output).unwrap();
+//! let output = String::from_utf8(output).unwrap();
+//!
+//! assert!(output.contains("gone - (unavailable)"));
+//! assert!(output.contains("serde_derive"));
+//! assert!(graph.unavailable().contains_key("gone"));
+//! let reasons: Vec<ErrorReason> = graph.errors().iter().map(|error| error.reason).collect();
+//! assert_eq!(reasons, vec![ErrorReason::NotFound, ErrorReason::NoVersions]);
+//! ```
+
+#[cfg(feature = "async")]
+use crates_io_api::AsyncClient;
+use crates_io_api::{
+    CrateResponse, CratesPage, CratesQuery, Dependencies, Dependency, Error as CratesIoError, Meta,
+    Owners, SyncClient, Version as CratesIoVersion,
+};
+use log::warn;
+use reqwest::blocking::Client as HttpClient;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::{StatusCode, Url};
+use semver::{Version, VersionReq};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The metadata `depth` needs about a crate published on a registry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateInfo {
+    pub name: String,
+    pub homepage: Option<String>,
+    pub max_version: String,
+    /// The SPDX license expression of the latest version, if known.
+    pub license: Option<String>,
+    /// The size in bytes of the published tarball of the latest version, if known.
+    pub crate_size: Option<u64>,
+    /// The downloads of every version of the crate, if known.
+    #[serde(default)]
+    pub downloads: Option<u64>,
+    /// The minimum supported Rust version declared by the latest version, if any.
+    pub rust_version: Option<String>,
+    /// The features declared by the latest version, with the features and dependencies each
+    /// one enables.
+    pub features: HashMap<String, Vec<String>>,
+}
+
+impl CrateInfo {
+    /// Creates a new CrateInfo instance with the given name and latest version.
+    pub fn new(name: &str, max_version: &str) -> Self {
+        CrateInfo {
+            name: name.to_string(),
+            homepage: None,
+            max_version: max_version.to_string(),
+            license: None,
+            crate_size: None,
+            downloads: None,
+            rust_version: None,
+            features: HashMap::new(),
+        }
+    }
+}
+
+/// A dependency declared by a specific version of a crate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyInfo {
+    pub name: String,
+    pub req: String,
+    pub optional: bool,
+    pub kind: DependencyKind,
+}
+
+impl DependencyInfo {
+    /// Creates a new, non-optional, normal DependencyInfo instance with the given name and
+    /// requirement.
+    pub fn new(name: &str, req: &str) -> Self {
+        DependencyInfo {
+            name: name.to_string(),
+            req: req.to_string(),
+            optional: false,
+            kind: DependencyKind::Normal,
+        }
+    }
+}
+
+/// The section of the manifest a dependency is declared in.
+///
+/// Kinds are ordered by how Cargo builds a dependency listed as several of them: a normal
+/// dependency before a build-dependency before a dev-dependency.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    /// `[dependencies]`
+    #[default]
+    Normal,
+    /// `[build-dependencies]`
+    Build,
+    /// `[dev-dependencies]`
+    Dev,
+}
+
+impl DependencyKind {
+    /// Returns the name of the kind, e.g. `dev`.
+    pub fn label(self) -> &'static str {
+        match self {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Build => "build",
+            DependencyKind::Dev => "dev",
+        }
+    }
+
+    /// Parses the kind reported by crates.io, unknown kinds being treated as normal.
+    fn from_crates_io(kind: &str) -> Self {
+        match kind {
+            "build" => DependencyKind::Build,
+            "dev" => DependencyKind::Dev,
+            _ => DependencyKind::Normal,
+        }
+    }
+}
+
+/// A published version of a crate.
+#[derive(Debug, Clone)]
+pub struct VersionInfo {
+    pub num: String,
+    pub yanked: bool,
+    /// The login of the user who published the version, if known.
+    pub published_by: Option<String>,
+    /// The SPDX license expression of the version, if known.
+    pub license: Option<String>,
+    /// The size in bytes of the published tarball of the version, if known.
+    pub crate_size: Option<u64>,
+    /// The minimum supported Rust version declared by the version, if any.
+    pub rust_version: Option<String>,
+    /// The features declared by the version, with the features and dependencies each one
+    /// enables.
+    pub features: HashMap<String, Vec<String>>,
+}
+
+impl VersionInfo {
+    /// Creates a new, non-yanked VersionInfo instance with the given version number.
+    pub fn new(num: &str) -> Self {
+        VersionInfo {
+            num: num.to_string(),
+            yanked: false,
+            published_by: None,
+            license: None,
+            crate_size: None,
+            rust_version: None,
+            features: HashMap::new(),
+        }
+    }
+}
+
+/// One page of the published versions of a crate.
+#[derive(Debug, Clone)]
+pub struct VersionsPage {
+    pub versions: Vec<VersionInfo>,
+    /// The number of the next page, `None` if this is the last one.
+    pub next_page: Option<usize>,
+}
+
+/// A crate depending on another crate, as listed by its reverse dependencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependentInfo {
+    /// The name of the dependent crate.
+    pub name: String,
+    /// The version of the dependent crate declaring the dependency.
+    pub version: String,
+    /// The requirement the dependent declares on the crate.
+    pub req: String,
+}
+
+/// One page of the reverse dependencies of a crate.
+#[derive(Debug, Clone)]
+pub struct DependentsPage {
+    pub dependents: Vec<DependentInfo>,
+    /// The number of the next page, `None` if this is the last one.
+    pub next_page: Option<usize>,
+}
+
+/// The number of reverse dependencies Crates.io lists per page.
+const REVERSE_DEPENDENCIES_PER_PAGE: usize = 100;
+
+/// An error returned by a `CrateRegistry`.
+#[derive(Debug)]
+pub enum RegistryError {
+    /// The crate does not exist on the registry (HTTP 404).
+    NotFound(String),
+    /// The registry refused access to the crate (HTTP 403).
+    PermissionDenied(String),
+    /// Every published version of the crate is yanked.
+    NoVersions(String),
+    /// Any other error reported by the Crates.io client.
+    Api(CratesIoError),
+}
+
+impl RegistryError {
+    /// Converts a Crates.io client error for the given crate into a `RegistryError`.
+    fn from_crates_io(crate_name: &str, err: CratesIoError) -> Self {
+        match err {
+            CratesIoError::NotFound(_) => RegistryError::NotFound(crate_name.to_string()),
+            CratesIoError::PermissionDenied(_) => {
+                RegistryError::PermissionDenied(crate_name.to_string())
+            }
+            CratesIoError::Http(err) if err.status() == Some(StatusCode::TOO_MANY_REQUESTS) => {
+                warn!(
+                    "Crates.io is rate limiting the requests (HTTP 429) while fetching \
+                     '{}', pass a larger --rate-limit-ms",
+                    crate_name
+                );
+                RegistryError::Api(CratesIoError::Http(err))
+            }
+            err => RegistryError::Api(err),
+        }
+    }
+
+    /// Returns `true` if the request may succeed when sent again: on network errors, such as
+    /// timeouts and refused connections, and on server errors (HTTP 5xx). Missing and private
+    /// crates are never retried.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            RegistryError::Api(CratesIoError::Http(err)) => match err.status() {
+                Some(status) => status.is_server_error(),
+                None => err.is_timeout() || err.is_connect() || err.is_request() || err.is_body(),
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the crate is private, gone or fully yanked, as opposed to a failing
+    /// registry.
+    pub fn is_unavailable(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// Returns why the crate is unavailable, `None` if the registry itself failed.
+    pub fn reason(&self) -> Option<ErrorReason> {
+        match self {
+            RegistryError::NotFound(_) => Some(ErrorReason::NotFound),
+            RegistryError::PermissionDenied(_) => Some(ErrorReason::PermissionDenied),
+            RegistryError::NoVersions(_) => Some(ErrorReason::NoVersions),
+            RegistryError::Api(_) => None,
+        }
+    }
+}
+
+/// Why a crate of the tree could not be resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorReason {
+    /// The crate does not exist on the registry.
+    NotFound,
+    /// The registry refused access to the crate.
+    PermissionDenied,
+    /// Every published version of the crate is yanked.
+    NoVersions,
+    /// The request for the crate failed, e.g. on a network or server error.
+    RequestFailed,
+}
+
+impl fmt::Display for ErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorReason::NotFound => "not found",
+            ErrorReason::PermissionDenied => "permission denied",
+            ErrorReason::NoVersions => "no versions",
+            ErrorReason::RequestFailed => "request failed",
+        })
+    }
+}
+
+/// A crate of the tree that could not be resolved, rendered as an unavailable leaf.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateError {
+    /// The name of the crate.
+    pub name: String,
+    /// Why the crate could not be resolved.
+    pub reason: ErrorReason,
+    /// The error reported by the registry.
+    pub message: String,
+}
+
+impl CrateError {
+    /// Creates a new CrateError instance from the error of fetching a crate, `None` if the
+    /// registry itself failed rather than the crate being unavailable.
+    pub fn new(crate_name: &str, err: &RegistryError) -> Option<Self> {
+        err.reason()?;
+        Some(CrateError::failed(crate_name, err))
+    }
+
+    /// Creates a new CrateError instance from any error of fetching a crate, a failing registry
+    /// being recorded as `ErrorReason::RequestFailed`.
+    pub fn failed(crate_name: &str, err: &RegistryError) -> Self {
+        CrateError {
+            name: crate_name.to_string(),
+            reason: err.reason().unwrap_or(ErrorReason::RequestFailed),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::NotFound(name) => write!(f, "crate '{}' was not found", name),
+            RegistryError::PermissionDenied(name) => {
+                write!(f, "access to crate '{}' was denied", name)
+            }
+            RegistryError::NoVersions(name) => {
+                write!(f, "every version of crate '{}' is yanked", name)
+            }
+            RegistryError::Api(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for RegistryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RegistryError::Api(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// The user agent `depth` identifies itself with by default, as the crawler policy of Crates.io
+/// asks every client to.
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "depth/",
+    env!("CARGO_PKG_VERSION"),
+    " (https://github.com/wiseaidev/depth)"
+);
+
+/// The smallest delay between two requests to Crates.io in milliseconds, its crawler policy
+/// allowing at most one request per second.
+pub const MIN_RATE_LIMIT_MS: u64 = 1000;
+
+/// The number of times a request failing on a network or server error is sent again by default.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// The delay before the first retry of a request, doubled before each next retry.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Returns the delay before retrying a failed request for the given time, counting from 0:
+/// a random delay between half and all of `RETRY_BASE_DELAY` doubled on each retry, so that
+/// concurrent fetches failing together do not retry together.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::registry::retry_delay;
+/// use std::time::Duration;
+///
+/// let delay = retry_delay(2);
+/// assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+/// ```
+pub fn retry_delay(retry: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY * 2u32.saturating_pow(retry.min(16));
+    // The hasher is seeded randomly on every call, which is random enough for a jitter
+    let random = RandomState::new().build_hasher().finish();
+    let fraction = 0.5 + (random % 1000) as f64 / 2000.0;
+    delay.mul_f64(fraction)
+}
+
+/// Sends a request until it succeeds, fails with an error that is not transient or the retries
+/// run out, waiting `retry_delay` before each retry.
+fn with_retries<T>(
+    retries: u32,
+    crate_name: &str,
+    mut request: impl FnMut() -> Result<T, RegistryError>,
+) -> Result<T, RegistryError> {
+    let mut retry = 0;
+    loop {
+        match request() {
+            Err(err) if err.is_transient() && retry < retries => {
+                let delay = retry_delay(retry);
+                warn_retry(crate_name, &err, delay, retry, retries);
+                std::thread::sleep(delay);
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Sends a request like `with_retries`, sleeping on the async runtime between retries.
+#[cfg(feature = "async")]
+async fn with_retries_async<T, F: std::future::Future<Output = Result<T, RegistryError>>>(
+    retries: u32,
+    crate_name: &str,
+    mut request: impl FnMut() -> F,
+) -> Result<T, RegistryError> {
+    let mut retry = 0;
+    loop {
+        match request().await {
+            Err(err) if err.is_transient() && retry < retries => {
+                let delay = retry_delay(retry);
+                warn_retry(crate_name, &err, delay, retry, retries);
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Warns that a request for a crate failed and is about to be retried.
+fn warn_retry(crate_name: &str, err: &RegistryError, delay: Duration, retry: u32, retries: u32) {
+    warn!(
+        "fetching '{}' failed: {}, retrying in {:.1}s ({}/{})",
+        crate_name,
+        err,
+        delay.as_secs_f64(),
+        retry + 1,
+        retries
+    );
+}
+
+/// The settings of the Crates.io clients `depth` creates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientOptions {
+    /// The user agent sent with every request, which should name a way to contact the user.
+    pub user_agent: String,
+    /// The delay between two requests, at least `MIN_RATE_LIMIT_MS`.
+    pub rate_limit: Duration,
+    /// The number of times a request failing on a network or server error is sent again, see
+    /// `RegistryError::is_transient`.
+    pub retries: u32,
+    /// The registry to fetch crates from instead of Crates.io, see `AlternateRegistry`.
+    pub registry: Option<Url>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        ClientOptions {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            rate_limit: Duration::from_millis(MIN_RATE_LIMIT_MS),
+            retries: DEFAULT_RETRIES,
+            registry: None,
+        }
+    }
+}
+
+/// Creates the client `depth` fetches packages with, for Crates.io unless
+/// `ClientOptions::registry` is set.
+///
+/// Every request of the client waits for the rate limit of the options, and is sent again on
+/// network and server errors up to `ClientOptions::retries` times.
+///
+/// # Returns
+///
+/// The client, or an error if the user agent is not a valid header value or the rate limit is
+/// below `MIN_RATE_LIMIT_MS`.
+pub fn crates_io_client(options: &ClientOptions) -> Result<RegistryClient, Box<dyn Error>> {
+    check_rate_limit(options)?;
+    new_client(options, Arc::new(RateLimiter::new(options.rate_limit)))
+}
+
+/// Fails if the rate limit of the options is below what Crates.io allows.
+fn check_rate_limit(options: &ClientOptions) -> Result<(), Box<dyn Error>> {
+    if options.rate_limit < Duration::from_millis(MIN_RATE_LIMIT_MS) {
+        return Err(format!(
+            "a rate limit of {}ms is below the {}ms between requests Crates.io allows",
+            options.rate_limit.as_millis(),
+            MIN_RATE_LIMIT_MS
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Creates a client for the registry of the options, with their user agent and retries, waiting
+/// for the given rate limiter before each request.
+fn new_client(
+    options: &ClientOptions,
+    limiter: Arc<RateLimiter>,
+) -> Result<RegistryClient, Box<dyn Error>> {
+    let invalid_user_agent = |err| format!("invalid user agent '{}': {}", options.user_agent, err);
+    // The rate limiter spaces the requests, the underlying client sends as soon as it is asked to
+    let registry = match &options.registry {
+        Some(url) => Registry::Alternate(
+            AlternateRegistry::new(url, &options.user_agent, Duration::ZERO)
+                .map_err(invalid_user_agent)?,
+        ),
+        None => Registry::CratesIo(
+            SyncClient::new(&options.user_agent, Duration::ZERO).map_err(invalid_user_agent)?,
+        ),
+    };
+    Ok(RegistryClient {
+        registry,
+        retries: options.retries,
+        limiter,
+    })
+}
+
+/// Spaces the start of the requests of the clients sharing it by a rate limit.
+#[derive(Debug)]
+struct RateLimiter {
+    rate_limit: Duration,
+    /// The earliest time the next request may start at.
+    next_start: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(rate_limit: Duration) -> Self {
+        RateLimiter {
+            rate_limit,
+            next_start: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits for the turn of the next request.
+    fn wait(&self) {
+        let wait = {
+            let mut next_start = self.next_start.lock().unwrap();
+            let now = Instant::now();
+            let start = (*next_start).max(now);
+            *next_start = start + self.rate_limit;
+            start - now
+        };
+        std::thread::sleep(wait);
+    }
+}
+
+/// Parses the URL of a registry serving the Crates.io web API, either its root such as
+/// `https://crates.example.com` or its API root ending in `/api/v1`.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::registry::parse_registry_url;
+///
+/// let url = parse_registry_url("https://crates.example.com").unwrap();
+/// assert_eq!(url.as_str(), "https://crates.example.com/");
+/// assert!(parse_registry_url("crates.example.com").is_err());
+/// assert!(parse_registry_url("ftp://crates.example.com").is_err());
+/// ```
+pub fn parse_registry_url(input: &str) -> Result<Url, String> {
+    let url =
+        Url::parse(input).map_err(|err| format!("invalid registry URL '{}': {}", input, err))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(format!(
+            "invalid registry URL '{}', expected an http or https URL such as \
+             https://crates.example.com",
+            input
+        ));
+    }
+    Ok(url)
+}
+
+/// Returns the root of the Crates.io web API of a registry, `<url>/api/v1/` unless the URL
+/// already ends in `/api/v1`.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::registry::{api_url, parse_registry_url};
+///
+/// let url = parse_registry_url("https://crates.example.com/mirror").unwrap();
+/// assert_eq!(api_url(&url).as_str(), "https://crates.example.com/mirror/api/v1/");
+///
+/// let url = parse_registry_url("https://crates.example.com/api/v1/").unwrap();
+/// assert_eq!(api_url(&url).as_str(), "https://crates.example.com/api/v1/");
+/// ```
+pub fn api_url(registry: &Url) -> Url {
+    let mut url = registry.clone();
+    let path = registry.path().trim_end_matches('/');
+    if path.ends_with("/api/v1") {
+        url.set_path(&format!("{}/", path));
+    } else {
+        url.set_path(&format!("{}/api/v1/", path));
+    }
+    url
+}
+
+/// The client of the registry crates are fetched from, see `crates_io_client`.
+///
+/// Every request waits for the rate limit, then is retried on network and server errors.
+pub struct RegistryClient {
+    registry: Registry,
+    retries: u32,
+    limiter: Arc<RateLimiter>,
+}
+
+/// The registry behind a `RegistryClient`.
+enum Registry {
+    /// Crates.io, through `crates_io_api`.
+    CratesIo(SyncClient),
+    /// A registry serving the Crates.io web API from another URL.
+    Alternate(AlternateRegistry),
+}
+
+impl Registry {
+    /// Returns the response of the registry for a crate, as `ApiDump` writes it.
+    fn crate_response(&self, crate_name: &str) -> Result<CrateResponse, RegistryError> {
+        match self {
+            Registry::CratesIo(client) => SyncClient::get_crate(client, crate_name)
+                .map_err(|err| RegistryError::from_crates_io(crate_name, err)),
+            Registry::Alternate(registry) => registry.crate_response(crate_name),
+        }
+    }
+
+    /// Returns the response of the registry for the dependencies of a crate version.
+    fn dependencies_response(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<Dependency>, RegistryError> {
+        match self {
+            Registry::CratesIo(client) => {
+                SyncClient::crate_dependencies(client, crate_name, version)
+                    .map_err(|err| RegistryError::from_crates_io(crate_name, err))
+            }
+            Registry::Alternate(registry) => registry.dependencies_response(crate_name, version),
+        }
+    }
+
+    /// Returns the registry as a `CrateRegistry`.
+    fn as_registry(&self) -> &dyn CrateRegistry {
+        match self {
+            Registry::CratesIo(client) => client,
+            Registry::Alternate(registry) => registry,
+        }
+    }
+}
+
+impl RegistryClient {
+    /// Sends a request to the registry once the rate limit allows it, again on network and
+    /// server errors until the retries run out.
+    fn send<T>(
+        &self,
+        crate_name: &str,
+        request: impl Fn(&Registry) -> Result<T, RegistryError>,
+    ) -> Result<T, RegistryError> {
+        with_retries(self.retries, crate_name, || {
+            self.limiter.wait();
+            request(&self.registry)
+        })
+    }
+
+    /// Returns the response of the registry for a crate, as `ApiDump` writes it.
+    fn crate_response(&self, crate_name: &str) -> Result<CrateResponse, RegistryError> {
+        self.send(crate_name, |registry| registry.crate_response(crate_name))
+    }
+
+    /// Returns the response of the registry for the dependencies of a crate version.
+    fn dependencies_response(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<Dependency>, RegistryError> {
+        self.send(crate_name, |registry| {
+            registry.dependencies_response(crate_name, version)
+        })
+    }
+}
+
+impl CrateRegistry for RegistryClient {
+    fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError> {
+        self.crate_response(crate_name)
+            .map(crate_info_from_response)
+    }
+
+    fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError> {
+        self.dependencies_response(crate_name, version)
+            .map(dependency_infos_from_response)
+    }
+
+    fn crate_versions_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<VersionsPage, RegistryError> {
+        self.send(crate_name, |registry| {
+            registry.as_registry().crate_versions_page(crate_name, page)
+        })
+    }
+
+    fn reverse_dependency_count(&self, crate_name: &str) -> Result<u64, RegistryError> {
+        self.send(crate_name, |registry| {
+            registry.as_registry().reverse_dependency_count(crate_name)
+        })
+    }
+
+    fn reverse_dependencies_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<DependentsPage, RegistryError> {
+        self.send(crate_name, |registry| {
+            registry
+                .as_registry()
+                .reverse_dependencies_page(crate_name, page)
+        })
+    }
+
+    fn search_crates(&self, query: &str, limit: usize) -> Result<Vec<String>, RegistryError> {
+        self.send(query, |registry| {
+            registry.as_registry().search_crates(query, limit)
+        })
+    }
+
+    fn crate_owners(&self, crate_name: &str) -> Result<Vec<String>, RegistryError> {
+        self.send(crate_name, |registry| {
+            registry.as_registry().crate_owners(crate_name)
+        })
+    }
+}
+
+/// A pool of Crates.io clients sharing a single rate limit, so that concurrent fetches overlap
+/// their requests while still starting at most one request per rate limit interval.
+///
+/// A `SyncClient` holds its rate limiter while its request is in flight, so several threads
+/// sharing one client wait on each other's responses. The clients of the pool instead share a
+/// single rate limiter, which spaces the start of their requests, and each request is sent on
+/// the next client in turn, which retries it like any `RegistryClient`.
+pub struct ClientPool {
+    clients: Vec<RegistryClient>,
+    next_client: AtomicUsize,
+}
+
+impl ClientPool {
+    /// Creates a pool of the given number of clients, at least one.
+    ///
+    /// # Returns
+    ///
+    /// The pool, or an error if the user agent is not a valid header value or the rate limit is
+    /// below `MIN_RATE_LIMIT_MS`.
+    pub fn new(options: &ClientOptions, size: usize) -> Result<Self, Box<dyn Error>> {
+        check_rate_limit(options)?;
+        let limiter = Arc::new(RateLimiter::new(options.rate_limit));
+        let clients = (0..size.max(1))
+            .map(|_| new_client(options, limiter.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok(ClientPool {
+            clients,
+            next_client: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns the client to send the next request on.
+    fn client(&self) -> &RegistryClient {
+        let index = self.next_client.fetch_add(1, Ordering::Relaxed);
+        &self.clients[index % self.clients.len()]
+    }
+}
+
+/// A pool of async Crates.io clients sharing a single rate limit, the async counterpart of
+/// `ClientPool` used by `DependencyGraph::fetch_dependency_tree_async`.
+///
+/// An `AsyncClient` too sends a single request at a time, so the pool spaces the start of the
+/// requests across all of its clients and sends each request on the next client in turn.
+#[cfg(feature = "async")]
+pub struct AsyncClientPool {
+    clients: Vec<AsyncClient>,
+    next_client: AtomicUsize,
+    rate_limit: Duration,
+    retries: u32,
+    /// The earliest time the next request may start at.
+    next_start: Mutex<Instant>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncClientPool {
+    /// Creates a pool of the given number of clients, at least one.
+    ///
+    /// # Returns
+    ///
+    /// The pool, or an error if the user agent is not a valid header value, the rate limit is
+    /// below `MIN_RATE_LIMIT_MS` or `ClientOptions::registry` is set.
+    pub fn new(options: &ClientOptions, size: usize) -> Result<Self, Box<dyn Error>> {
+        check_rate_limit(options)?;
+        if let Some(url) = &options.registry {
+            return Err(format!(
+                "the async fetch only supports Crates.io, not the registry at {}",
+                url
+            )
+            .into());
+        }
+        let clients = (0..size.max(1))
+            .map(|_| {
+                AsyncClient::new(&options.user_agent, Duration::ZERO)
+                    .map_err(|err| format!("invalid user agent '{}': {}", options.user_agent, err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(AsyncClientPool {
+            clients,
+            next_client: AtomicUsize::new(0),
+            rate_limit: options.rate_limit,
+            retries: options.retries,
+            next_start: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Waits for the turn of the next request, then returns the client to send it on.
+    async fn client(&self) -> &AsyncClient {
+        let wait = {
+            let mut next_start = self.next_start.lock().unwrap();
+            let now = Instant::now();
+            let start = (*next_start).max(now);
+            *next_start = start + self.rate_limit;
+            start - now
+        };
+        tokio::time::sleep(wait).await;
+        let index = self.next_client.fetch_add(1, Ordering::Relaxed);
+        &self.clients[index % self.clients.len()]
+    }
+
+    /// Returns the metadata of a crate, see `CrateRegistry::get_crate`.
+    pub async fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError> {
+        with_retries_async(self.retries, crate_name, || async {
+            let response = self
+                .client()
+                .await
+                .get_crate(crate_name)
+                .await
+                .map_err(|err| RegistryError::from_crates_io(crate_name, err))?;
+            Ok(crate_info_from_response(response))
+        })
+        .await
+    }
+
+    /// Returns the dependencies of a crate version, see `CrateRegistry::crate_dependencies`.
+    pub async fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError> {
+        with_retries_async(self.retries, crate_name, || async {
+            let dependencies = self
+                .client()
+                .await
+                .crate_dependencies(crate_name, version)
+                .await
+                .map_err(|err| RegistryError::from_crates_io(crate_name, err))?;
+            Ok(dependency_infos_from_response(dependencies))
+        })
+        .await
+    }
+}
+
+impl CrateRegistry for ClientPool {
+    fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError> {
+        CrateRegistry::get_crate(self.client(), crate_name)
+    }
+
+    fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError> {
+        CrateRegistry::crate_dependencies(self.client(), crate_name, version)
+    }
+
+    fn crate_versions_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<VersionsPage, RegistryError> {
+        CrateRegistry::crate_versions_page(self.client(), crate_name, page)
+    }
+
+    fn reverse_dependency_count(&self, crate_name: &str) -> Result<u64, RegistryError> {
+        CrateRegistry::reverse_dependency_count(self.client(), crate_name)
+    }
+
+    fn reverse_dependencies_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<DependentsPage, RegistryError> {
+        CrateRegistry::reverse_dependencies_page(self.client(), crate_name, page)
+    }
+
+    fn search_crates(&self, query: &str, limit: usize) -> Result<Vec<String>, RegistryError> {
+        CrateRegistry::search_crates(self.client(), query, limit)
+    }
+
+    fn crate_owners(&self, crate_name: &str) -> Result<Vec<String>, RegistryError> {
+        CrateRegistry::crate_owners(self.client(), crate_name)
+    }
+}
+
+/// The root crate of a tree does not exist on the registry, with the names of similar crates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateNotFound {
+    /// The name of the missing crate.
+    pub name: String,
+    /// Existing crates with a similar name, most similar first.
+    pub suggestions: Vec<String>,
+}
+
+impl fmt::Display for CrateNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "crate '{}' not found on the registry", self.name)?;
+        if !self.suggestions.is_empty() {
+            let suggestions: Vec<String> = self
+                .suggestions
+                .iter()
+                .map(|name| format!("'{}'", name))
+                .collect();
+            write!(f, ", did you mean {}?", suggestions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for CrateNotFound {}
+
+/// The number of suggestions offered for a missing crate.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Builds the error of a missing root crate, suggesting the registry's crates with the closest
+/// names. A failing search only costs the suggestions.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::FetchOptions;
+/// use depth::registry::{crate_not_found, CrateInfo, MemoryRegistry};
+///
+/// let mut registry = MemoryRegistry::new();
+/// for name in ["serde", "serde_json", "tokio"] {
+///     registry.add_crate(CrateInfo::new(name, "1.0.0"), vec![]);
+/// }
+///
+/// // Fetching a missing root crate finds no tree rather than failing
+/// let mut graph = DependencyGraph::new();
+/// let root = graph
+///     .fetch_dependency_tree_with_registry("serde_jsno", 2, &FetchOptions::default(), &registry)
+///     .unwrap();
+/// assert!(root.is_none());
+///
+/// let err = crate_not_found(&registry, "serde_jsno");
+/// assert_eq!(err.suggestions, vec!["serde_json", "serde"]);
+/// assert_eq!(
+///     err.to_string(),
+///     "crate 'serde_jsno' not found on the registry, did you mean 'serde_json', 'serde'?"
+/// );
+/// ```
+pub fn crate_not_found(registry: &impl CrateRegistry, crate_name: &str) -> CrateNotFound {
+    let mut suggestions = registry.search_crates(crate_name, 10).unwrap_or_default();
+    suggestions.retain(|name| name != crate_name);
+    suggestions.sort_by_key(|name| edit_distance(name, crate_name));
+    suggestions.truncate(MAX_SUGGESTIONS);
+
+    CrateNotFound {
+        name: crate_name.to_string(),
+        suggestions,
+    }
+}
+
+/// Counts the single character insertions, deletions and substitutions between two strings.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A source of crate metadata that dependency trees are resolved against.
+///
+/// Registries are shared with the threads fetching sibling crates concurrently, so they must be
+/// `Sync`.
+pub trait CrateRegistry: Sync {
+    /// Fetches the metadata of the crate with the given name.
+    fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError>;
+
+    /// Lists the dependencies declared by the given version of a crate.
+    fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError>;
+
+    /// Lists one page of the published versions of a crate, pages being numbered from 1.
+    fn crate_versions_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<VersionsPage, RegistryError>;
+
+    /// Counts the crates depending on any version of a crate.
+    fn reverse_dependency_count(&self, crate_name: &str) -> Result<u64, RegistryError>;
+
+    /// Lists one page of the crates depending on a crate, pages being numbered from 1.
+    fn reverse_dependencies_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<DependentsPage, RegistryError>;
+
+    /// Searches the registry, returning the names of at most `limit` matching crates.
+    fn search_crates(&self, query: &str, limit: usize) -> Result<Vec<String>, RegistryError>;
+
+    /// Lists the logins of the users and teams owning a crate.
+    fn crate_owners(&self, crate_name: &str) -> Result<Vec<String>, RegistryError>;
+
+    /// Lists every published version of a crate, following the pagination to the last page.
+    fn crate_versions(&self, crate_name: &str) -> Result<Vec<VersionInfo>, RegistryError> {
+        let mut versions = Vec::new();
+        let mut page = Some(1);
+        while let Some(number) = page {
+            let versions_page = self.crate_versions_page(crate_name, number)?;
+            versions.extend(versions_page.versions);
+            page = versions_page.next_page;
+        }
+        Ok(versions)
+    }
+
+    /// Lists every crate depending on a crate, following the pagination to the last page.
+    fn reverse_dependencies(&self, crate_name: &str) -> Result<Vec<DependentInfo>, RegistryError> {
+        let mut dependents = Vec::new();
+        let mut page = Some(1);
+        while let Some(number) = page {
+            let dependents_page = self.reverse_dependencies_page(crate_name, number)?;
+            dependents.extend(dependents_page.dependents);
+            page = dependents_page.next_page;
+        }
+        Ok(dependents)
+    }
+}
+
+/// Resolves the highest published, non-yanked version of a crate matching a requirement.
+///
+/// Every page of the crate's versions is fetched before matching, so crates with hundreds of
+/// releases resolve correctly. Pre-releases only match requirements that opt into them.
+///
+/// # Arguments
+///
+/// * `registry` - The registry to list the versions from.
+/// * `crate_name` - The name of the crate to resolve.
+/// * `req` - The semver requirement the version must satisfy.
+///
+/// # Returns
+///
+/// A Result containing the matching version, `None` if no version satisfies the requirement.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::registry::{resolve_version, CrateInfo, MemoryRegistry, VersionInfo};
+/// use semver::VersionReq;
+///
+/// // Three versions per page, so `0.2.9` only shows up on the second page
+/// let mut registry = MemoryRegistry::new().with_page_size(3);
+/// registry.add_crate(CrateInfo::new("libc", "0.3.0"), vec![]);
+/// for num in ["0.3.0", "0.2.1", "0.2.0", "0.1.0", "0.2.9"] {
+///     registry.add_version("libc", VersionInfo::new(num));
+/// }
+///
+/// let req = VersionReq::parse("^0.2").unwrap();
+/// let version = resolve_version(&registry, "libc", &req).unwrap();
+/// assert_eq!(version.unwrap().to_string(), "0.2.9");
+/// ```
+pub fn resolve_version(
+    registry: &impl CrateRegistry,
+    crate_name: &str,
+    req: &VersionReq,
+) -> Result<Option<Version>, RegistryError> {
+    Ok(registry
+        .crate_versions(crate_name)?
+        .iter()
+        .filter(|version| !version.yanked)
+        .filter_map(|version| Version::parse(&version.num).ok())
+        .filter(|version| req.matches(version))
+        .max())
+}
+
+/// A version of a crate published by someone else than the previous version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnerChange {
+    /// The login of the publisher of the previous version.
+    pub previous: String,
+    /// The login of the publisher of the checked version.
+    pub latest: String,
+}
+
+/// Checks whether a version of a crate was published by someone else than the version before it.
+///
+/// Registries do not expose owner history, so this is a best-effort heuristic: the publisher of
+/// the checked version is compared to the publisher of the closest earlier version whose
+/// publisher is known. Versions without a known publisher, like those published before crates.io
+/// recorded it, are ignored.
+///
+/// # Arguments
+///
+/// * `registry` - The registry to list the versions from.
+/// * `crate_name` - The name of the crate to check.
+/// * `version` - The version to check, usually the resolved one.
+///
+/// # Returns
+///
+/// A Result containing the change of publisher, `None` if none was detected.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::registry::{detect_owner_change, CrateInfo, MemoryRegistry, VersionInfo};
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("event-stream", "3.3.6"), vec![]);
+/// for (num, publisher) in [("3.3.4", "dominictarr"), ("3.3.5", "dominictarr"), ("3.3.6", "right9ctrl")] {
+///     let mut version = VersionInfo::new(num);
+///     version.published_by = Some(publisher.to_string());
+///     registry.add_version("event-stream", version);
+/// }
+///
+/// let change = detect_owner_change(&registry, "event-stream", "3.3.6").unwrap().unwrap();
+/// assert_eq!((change.previous.as_str(), change.latest.as_str()), ("dominictarr", "right9ctrl"));
+/// assert_eq!(detect_owner_change(&registry, "event-stream", "3.3.5").unwrap(), None);
+/// ```
+pub fn detect_owner_change(
+    registry: &impl CrateRegistry,
+    crate_name: &str,
+    version: &str,
+) -> Result<Option<OwnerChange>, RegistryError> {
+    let Ok(checked) = Version::parse(version) else {
+        return Ok(None);
+    };
+    let mut versions: Vec<(Version, String)> = registry
+        .crate_versions(crate_name)?
+        .into_iter()
+        .filter_map(|info| Some((Version::parse(&info.num).ok()?, info.published_by?)))
+        .collect();
+    versions.sort();
+
+    let Some(latest) = versions
+        .iter()
+        .find(|(num, _)| *num == checked)
+        .map(|(_, publisher)| publisher)
+    else {
+        return Ok(None);
+    };
+    let previous = versions
+        .iter()
+        .rev()
+        .find(|(num, _)| *num < checked)
+        .map(|(_, publisher)| publisher);
+
+    Ok(previous
+        .filter(|previous| *previous != latest)
+        .map(|previous| OwnerChange {
+            previous: previous.clone(),
+            latest: latest.clone(),
+        }))
+}
+
+/// Converts the Crates.io response of a crate into the metadata `depth` needs.
+fn crate_info_from_response(response: CrateResponse) -> CrateInfo {
+    let crate_data = response.crate_data;
+    // The license and the size are only published per version
+    let latest = response
+        .versions
+        .into_iter()
+        .find(|version| version.num == crate_data.max_version);
+
+    CrateInfo {
+        name: crate_data.id,
+        homepage: crate_data.homepage,
+        max_version: crate_data.max_version,
+        downloads: Some(crate_data.downloads),
+        license: latest.as_ref().and_then(|version| version.license.clone()),
+        crate_size: latest.as_ref().and_then(|version| version.crate_size),
+        rust_version: latest
+            .as_ref()
+            .and_then(|version| version.rust_version.clone()),
+        features: latest.map_or_else(HashMap::new, |version| version.features),
+    }
+}
+
+/// Converts the Crates.io response of the dependencies of a crate version.
+fn dependency_infos_from_response(dependencies: Vec<Dependency>) -> Vec<DependencyInfo> {
+    dependencies
+        .into_iter()
+        .map(|dep| DependencyInfo {
+            name: dep.crate_id,
+            req: dep.req,
+            optional: dep.optional,
+            kind: DependencyKind::from_crates_io(&dep.kind),
+        })
+        .collect()
+}
+
+/// Converts the published versions of a crate response into a single page of versions, the
+/// crate endpoint returning every version at once.
+fn versions_page_from_response(versions: Vec<CratesIoVersion>) -> VersionsPage {
+    VersionsPage {
+        versions: versions
+            .into_iter()
+            .map(|version| VersionInfo {
+                num: version.num,
+                yanked: version.yanked,
+                published_by: version.published_by.map(|user| user.login),
+                license: version.license,
+                crate_size: version.crate_size,
+                rust_version: version.rust_version,
+                features: version.features,
+            })
+            .collect(),
+        next_page: None,
+    }
+}
+
+/// Builds a page of the dependents of a crate, the last one once `total` dependents are listed.
+fn dependents_page(page: usize, total: u64, dependents: Vec<DependentInfo>) -> DependentsPage {
+    let listed = page * REVERSE_DEPENDENCIES_PER_PAGE;
+    let last = dependents.is_empty() || listed as u64 >= total;
+    DependentsPage {
+        dependents,
+        next_page: (!last).then_some(page + 1),
+    }
+}
+
+impl CrateRegistry for SyncClient {
+    fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError> {
+        let response = SyncClient::get_crate(self, crate_name)
+            .map_err(|err| RegistryError::from_crates_io(crate_name, err))?;
+        Ok(crate_info_from_response(response))
+    }
+
+    fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError> {
+        let dependencies = SyncClient::crate_dependencies(self, crate_name, version)
+            .map_err(|err| RegistryError::from_crates_io(crate_name, err))?;
+        Ok(dependency_infos_from_response(dependencies))
+    }
+
+    fn crate_versions_page(
+        &self,
+        crate_name: &str,
+        _page: usize,
+    ) -> Result<VersionsPage, RegistryError> {
+        let versions = SyncClient::get_crate(self, crate_name)
+            .map_err(|err| RegistryError::from_crates_io(crate_name, err))?
+            .versions;
+        Ok(versions_page_from_response(versions))
+    }
+
+    fn reverse_dependency_count(&self, crate_name: &str) -> Result<u64, RegistryError> {
+        SyncClient::crate_reverse_dependency_count(self, crate_name)
+            .map_err(|err| RegistryError::from_crates_io(crate_name, err))
+    }
+
+    fn reverse_dependencies_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<DependentsPage, RegistryError> {
+        let response = SyncClient::crate_reverse_dependencies_page(self, crate_name, page as u64)
+            .map_err(|err| RegistryError::from_crates_io(crate_name, err))?;
+
+        let dependents = response
+            .dependencies
+            .into_iter()
+            .map(|dependent| DependentInfo {
+                name: dependent.crate_version.crate_name,
+                version: dependent.crate_version.num,
+                req: dependent.dependency.req,
+            })
+            .collect();
+        Ok(dependents_page(page, response.meta.total, dependents))
+    }
+
+    fn search_crates(&self, query: &str, limit: usize) -> Result<Vec<String>, RegistryError> {
+        let query = CratesQuery::builder()
+            .search(query)
+            .page_size(limit as u64)
+            .build();
+        let page = SyncClient::crates(self, query).map_err(RegistryError::Api)?;
+        Ok(page.crates.into_iter().map(|crate_| crate_.id).collect())
+    }
+
+    fn crate_owners(&self, crate_name: &str) -> Result<Vec<String>, RegistryError> {
+        let owners = SyncClient::crate_owners(self, crate_name)
+            .map_err(|err| RegistryError::from_crates_io(crate_name, err))?;
+        Ok(owners.into_iter().map(|owner| owner.login).collect())
+    }
+}
+
+/// A registry serving the Crates.io web API from another URL, such as a private mirror of
+/// Crates.io. `crates_io_api` always sends its requests to Crates.io, so this client sends the
+/// requests `depth` needs itself.
+///
+/// At most one request starts per rate limit interval, like with `SyncClient`.
+pub struct AlternateRegistry {
+    client: HttpClient,
+    /// The root of the API, see `api_url`.
+    api_url: Url,
+    rate_limit: Duration,
+    /// When the last request started.
+    last_request: Mutex<Option<Instant>>,
+}
+
+/// The response of the registry for a page of the dependents of a crate, listing the dependency
+/// of each dependent and, separately, the dependent versions.
+#[derive(Deserialize)]
+struct ReverseDependenciesResponse {
+    dependencies: Vec<Dependency>,
+    versions: Vec<CratesIoVersion>,
+    meta: Meta,
+}
+
+impl AlternateRegistry {
+    /// Creates a client for the registry at the given URL, see `api_url`.
+    ///
+    /// # Returns
+    ///
+    /// The client, or an error if the user agent is not a valid header value.
+    pub fn new(
+        url: &Url,
+        user_agent: &str,
+        rate_limit: Duration,
+    ) -> Result<Self, reqwest::header::InvalidHeaderValue> {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_str(user_agent)?);
+        Ok(AlternateRegistry {
+            client: HttpClient::builder()
+                .default_headers(headers)
+                .build()
+                .expect("a client without TLS options builds"),
+            api_url: api_url(url),
+            rate_limit,
+            last_request: Mutex::new(None),
+        })
+    }
+
+    /// Returns the URL of an endpoint, relative to the root of the API.
+    fn endpoint(&self, path: &str) -> Url {
+        // Crate names and versions only hold URL-safe characters
+        self.api_url
+            .join(path)
+            .expect("endpoints are relative paths")
+    }
+
+    /// Sends a request for a crate once the rate limit allows it, then decodes its response.
+    fn get<T: DeserializeOwned>(&self, crate_name: &str, url: Url) -> Result<T, RegistryError> {
+        {
+            let mut last_request = self.last_request.lock().unwrap();
+            if let Some(elapsed) = last_request.map(|start| start.elapsed()) {
+                std::thread::sleep(self.rate_limit.saturating_sub(elapsed));
+            }
+            *last_request = Some(Instant::now());
+        }
+
+        let http_error = |err| RegistryError::from_crates_io(crate_name, CratesIoError::Http(err));
+        let response = self.client.get(url).send().map_err(http_error)?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Err(RegistryError::NotFound(crate_name.to_string())),
+            StatusCode::FORBIDDEN => Err(RegistryError::PermissionDenied(crate_name.to_string())),
+            _ => response
+                .error_for_status()
+                .and_then(|response| response.json())
+                .map_err(http_error),
+        }
+    }
+
+    /// Returns the response of the registry for a crate.
+    fn crate_response(&self, crate_name: &str) -> Result<CrateResponse, RegistryError> {
+        self.get(crate_name, self.endpoint(&format!("crates/{}", crate_name)))
+    }
+
+    /// Returns the dependencies of a crate version, as the registry lists them.
+    fn dependencies_response(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<Dependency>, RegistryError> {
+        let path = format!("crates/{}/{}/dependencies", crate_name, version);
+        let response: Dependencies = self.get(crate_name, self.endpoint(&path))?;
+        Ok(response.dependencies)
+    }
+
+    /// Returns a page of the dependents of a crate, pages being numbered from 1.
+    fn reverse_dependencies_response(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<ReverseDependenciesResponse, RegistryError> {
+        let mut url = self.endpoint(&format!("crates/{}/reverse_dependencies", crate_name));
+        url.query_pairs_mut()
+            .append_pair("page", &page.max(1).to_string())
+            .append_pair("per_page", &REVERSE_DEPENDENCIES_PER_PAGE.to_string());
+        self.get(crate_name, url)
+    }
+}
+
+impl CrateRegistry for AlternateRegistry {
+    fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError> {
+        self.crate_response(crate_name)
+            .map(crate_info_from_response)
+    }
+
+    fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError> {
+        self.dependencies_response(crate_name, version)
+            .map(dependency_infos_from_response)
+    }
+
+    fn crate_versions_page(
+        &self,
+        crate_name: &str,
+        _page: usize,
+    ) -> Result<VersionsPage, RegistryError> {
+        let versions = self.crate_response(crate_name)?.versions;
+        Ok(versions_page_from_response(versions))
+    }
+
+    fn reverse_dependency_count(&self, crate_name: &str) -> Result<u64, RegistryError> {
+        Ok(self
+            .reverse_dependencies_response(crate_name, 1)?
+            .meta
+            .total)
+    }
+
+    fn reverse_dependencies_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<DependentsPage, RegistryError> {
+        let response = self.reverse_dependencies_response(crate_name, page)?;
+        let dependents = response
+            .dependencies
+            .into_iter()
+            .filter_map(|dependency| {
+                let version = response
+                    .versions
+                    .iter()
+                    .find(|version| version.id == dependency.version_id)?;
+                Some(DependentInfo {
+                    name: version.crate_name.clone(),
+                    version: version.num.clone(),
+                    req: dependency.req,
+                })
+            })
+            .collect();
+        Ok(dependents_page(page, response.meta.total, dependents))
+    }
+
+    fn search_crates(&self, query: &str, limit: usize) -> Result<Vec<String>, RegistryError> {
+        let mut url = self.endpoint("crates");
+        url.query_pairs_mut()
+            .append_pair("q", query)
+            .append_pair("per_page", &limit.to_string());
+        let page: CratesPage = self.get(query, url)?;
+        Ok(page.crates.into_iter().map(|crate_| crate_.id).collect())
+    }
+
+    fn crate_owners(&self, crate_name: &str) -> Result<Vec<String>, RegistryError> {
+        let path = format!("crates/{}/owners", crate_name);
+        let owners: Owners = self.get(crate_name, self.endpoint(&path))?;
+        Ok(owners.users.into_iter().map(|owner| owner.login).collect())
+    }
+}
+
+/// An in-memory registry, useful for offline graphs and tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryRegistry {
+    crates: HashMap<String, (CrateInfo, Vec<DependencyInfo>)>,
+    versions: HashMap<String, Vec<VersionInfo>>,
+    owners: HashMap<String, Vec<String>>,
+    /// The number of versions per page, all versions are on one page if `None`.
+    page_size: Option<usize>,
+}
+
+impl MemoryRegistry {
+    /// Creates a new, empty MemoryRegistry instance.
+    pub fn new() -> Self {
+        MemoryRegistry {
+            crates: HashMap::new(),
+            versions: HashMap::new(),
+            owners: HashMap::new(),
+            page_size: None,
+        }
+    }
+
+    /// Creates a registry of the given crates, each with the names of its dependencies, the
+    /// counterpart of `DependencyGraph::from_edges`. Every crate is at version `1.0.0` and
+    /// every dependency requires `^1`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::registry::{CrateRegistry, MemoryRegistry};
+    ///
+    /// let registry = MemoryRegistry::from_edges(&[("app", &["serde"]), ("serde", &[])]);
+    /// assert_eq!(registry.get_crate("serde").unwrap().max_version, "1.0.0");
+    /// assert_eq!(registry.crate_dependencies("app", "1.0.0").unwrap()[0].req, "^1");
+    /// ```
+    pub fn from_edges(edges: &[(&str, &[&str])]) -> Self {
+        let mut registry = MemoryRegistry::new();
+        for (name, dependencies) in edges {
+            let dependencies = dependencies
+                .iter()
+                .map(|dependency| DependencyInfo::new(dependency, "^1"))
+                .collect();
+            registry.add_crate(CrateInfo::new(name, "1.0.0"), dependencies);
+        }
+        registry
+    }
+
+    /// Splits the versions of each crate into pages of the given size.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = Some(page_size.max(1));
+        self
+    }
+
+    /// Adds a crate and the dependencies of its latest version to the registry.
+    pub fn add_crate(&mut self, info: CrateInfo, dependencies: Vec<DependencyInfo>) {
+        self.crates.insert(info.name.clone(), (info, dependencies));
+    }
+
+    /// Adds a published version of a crate. Crates without added versions only list their
+    /// latest version.
+    pub fn add_version(&mut self, crate_name: &str, version: VersionInfo) {
+        self.versions
+            .entry(crate_name.to_string())
+            .or_default()
+            .push(version);
+    }
+
+    /// Adds an owner, by login, to a crate. Crates without added owners have none.
+    pub fn add_owner(&mut self, crate_name: &str, login: &str) {
+        self.owners
+            .entry(crate_name.to_string())
+            .or_default()
+            .push(login.to_string());
+    }
+}
+
+impl CrateRegistry for MemoryRegistry {
+    fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError> {
+        self.crates
+            .get(crate_name)
+            .map(|(info, _)| info.clone())
+            .ok_or_else(|| RegistryError::NotFound(crate_name.to_string()))
+    }
+
+    fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        _version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError> {
+        self.crates
+            .get(crate_name)
+            .map(|(_, dependencies)| dependencies.clone())
+            .ok_or_else(|| RegistryError::NotFound(crate_name.to_string()))
+    }
+
+    fn crate_versions_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<VersionsPage, RegistryError> {
+        let info = self.get_crate(crate_name)?;
+        let versions = self
+            .versions
+            .get(crate_name)
+            .cloned()
+            .unwrap_or_else(|| vec![VersionInfo::new(&info.max_version)]);
+
+        let Some(page_size) = self.page_size else {
+            return Ok(VersionsPage {
+                versions,
+                next_page: None,
+            });
+        };
+
+        let start = page.saturating_sub(1) * page_size;
+        let end = (start + page_size).min(versions.len());
+        Ok(VersionsPage {
+            versions: versions.get(start..end).unwrap_or_default().to_vec(),
+            next_page: (end < versions.len()).then_some(page + 1),
+        })
+    }
+
+    fn reverse_dependency_count(&self, crate_name: &str) -> Result<u64, RegistryError> {
+        self.get_crate(crate_name)?;
+        let count = self
+            .crates
+            .values()
+            .filter(|(_, dependencies)| dependencies.iter().any(|dep| dep.name == crate_name))
+            .count();
+        Ok(count as u64)
+    }
+
+    fn reverse_dependencies_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<DependentsPage, RegistryError> {
+        self.get_crate(crate_name)?;
+        let mut dependents: Vec<DependentInfo> = self
+            .crates
+            .values()
+            .filter_map(|(info, dependencies)| {
+                let dependency = dependencies.iter().find(|dep| dep.name == crate_name)?;
+                Some(DependentInfo {
+                    name: info.name.clone(),
+                    version: info.max_version.clone(),
+                    req: dependency.req.clone(),
+                })
+            })
+            .collect();
+        dependents.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let Some(page_size) = self.page_size else {
+            return Ok(DependentsPage {
+                dependents,
+                next_page: None,
+            });
+        };
+
+        let start = page.saturating_sub(1) * page_size;
+        let end = (start + page_size).min(dependents.len());
+        Ok(DependentsPage {
+            dependents: dependents.get(start..end).unwrap_or_default().to_vec(),
+            next_page: (end < dependents.len()).then_some(page + 1),
+        })
+    }
+
+    fn search_crates(&self, query: &str, limit: usize) -> Result<Vec<String>, RegistryError> {
+        // Crates.io treats `-` and `_` as the same character
+        let normalize = |name: &str| name.to_lowercase().replace('-', "_");
+        let query = normalize(query);
+        let mut names: Vec<String> = self
+            .crates
+            .keys()
+            .filter(|name| {
+                let name = normalize(name);
+                name.contains(&query) || query.contains(&name) || edit_distance(&name, &query) <= 2
+            })
+            .cloned()
+            .collect();
+        names.sort();
+        names.truncate(limit);
+        Ok(names)
+    }
+
+    fn crate_owners(&self, crate_name: &str) -> Result<Vec<String>, RegistryError> {
+        self.get_crate(crate_name)?;
+        Ok(self.owners.get(crate_name).cloned().unwrap_or_default())
+    }
+}
+
+/// A Crates.io client writing every crate and dependencies response it receives to a directory,
+/// to see what Crates.io actually returned when a crate resolves oddly.
+///
+/// The response of a crate is written to `<dir>/<crate>.json` and the dependencies of a version
+/// to `<dir>/<crate>-<version>.dependencies.json`, as JSON. A crate response lists every
+/// published version, so crates with a long history take hundreds of KiB each and a deep tree
+/// can fill tens of MiB. Other requests are not written.
+pub struct ApiDump {
+    client: RegistryClient,
+    dir: PathBuf,
+}
+
+impl ApiDump {
+    /// Creates a new ApiDump instance writing the responses of the client to the given
+    /// directory, which is created if needed.
+    pub fn new(client: RegistryClient, dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(ApiDump {
+            client,
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// Writes a response to a file of the dump directory, only warning if it fails since the
+    /// dump is a debugging aid.
+    fn write(&self, file_name: &str, response: &impl Serialize) {
+        let path = self.dir.join(file_name);
+        let written = serde_json::to_vec_pretty(response)
+            .map_err(io::Error::from)
+            .and_then(|json| fs::write(&path, json));
+        if let Err(err) = written {
+            warn!("failed to dump '{}': {}", path.display(), err);
+        }
+    }
+}
+
+impl CrateRegistry for ApiDump {
+    fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError> {
+        let response = self.client.crate_response(crate_name)?;
+        self.write(&format!("{}.json", crate_name), &response);
+        Ok(crate_info_from_response(response))
+    }
+
+    fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError> {
+        let dependencies = self.client.dependencies_response(crate_name, version)?;
+        self.write(
+            &format!("{}-{}.dependencies.json", crate_name, version),
+            &dependencies,
+        );
+        Ok(dependency_infos_from_response(dependencies))
+    }
+
+    fn crate_versions_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<VersionsPage, RegistryError> {
+        CrateRegistry::crate_versions_page(&self.client, crate_name, page)
+    }
+
+    fn reverse_dependency_count(&self, crate_name: &str) -> Result<u64, RegistryError> {
+        CrateRegistry::reverse_dependency_count(&self.client, crate_name)
+    }
+
+    fn reverse_dependencies_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<DependentsPage, RegistryError> {
+        CrateRegistry::reverse_dependencies_page(&self.client, crate_name, page)
+    }
+
+    fn search_crates(&self, query: &str, limit: usize) -> Result<Vec<String>, RegistryError> {
+        CrateRegistry::search_crates(&self.client, query, limit)
+    }
+
+    fn crate_owners(&self, crate_name: &str) -> Result<Vec<String>, RegistryError> {
+        CrateRegistry::crate_owners(&self.client, crate_name)
+    }
+}
+
+/// A registry wrapper that measures how long fetching each crate takes.
+///
+/// The time of every request made for a crate (its metadata and its dependencies) is summed
+/// per crate. With the Crates.io client, this includes the time spent waiting on its rate limit.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::registry::{CrateInfo, CrateRegistry, MemoryRegistry, TimedRegistry};
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![]);
+///
+/// let registry = TimedRegistry::new(registry);
+/// registry.get_crate("serde").unwrap();
+/// registry.crate_dependencies("serde", "1.0.0").unwrap();
+///
+/// assert_eq!(registry.request_count(), 2);
+/// assert_eq!(registry.slowest(5)[0].0, "serde");
+/// ```
+#[derive(Debug)]
+pub struct TimedRegistry<R> {
+    inner: R,
+    timings: Mutex<HashMap<String, Duration>>,
+    requests: AtomicUsize,
+}
+
+impl<R: CrateRegistry> TimedRegistry<R> {
+    /// Creates a new TimedRegistry instance wrapping the given registry.
+    pub fn new(inner: R) -> Self {
+        TimedRegistry {
+            inner,
+            timings: Mutex::new(HashMap::new()),
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the total number of requests made to the wrapped registry.
+    pub fn request_count(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Returns the `n` crates that took the longest to fetch, slowest first.
+    pub fn slowest(&self, n: usize) -> Vec<(String, Duration)> {
+        let mut timings: Vec<(String, Duration)> = self
+            .timings
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, duration)| (name.clone(), *duration))
+            .collect();
+        timings.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        timings.truncate(n);
+        timings
+    }
+
+    /// Adds the time elapsed since `start` to the total of the given crate.
+    fn record(&self, crate_name: &str, start: Instant) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        *self
+            .timings
+            .lock()
+            .unwrap()
+            .entry(crate_name.to_string())
+            .or_default() += start.elapsed();
+    }
+}
+
+impl<R: CrateRegistry> CrateRegistry for TimedRegistry<R> {
+    fn get_crate(&self, crate_name: &str) -> Result<CrateInfo, RegistryError> {
+        let start = Instant::now();
+        let result = self.inner.get_crate(crate_name);
+        self.record(crate_name, start);
+        result
+    }
+
+    fn crate_dependencies(
+        &self,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Vec<DependencyInfo>, RegistryError> {
+        let start = Instant::now();
+        let result = self.inner.crate_dependencies(crate_name, version);
+        self.record(crate_name, start);
+        result
+    }
+
+    fn crate_versions_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<VersionsPage, RegistryError> {
+        let start = Instant::now();
+        let result = self.inner.crate_versions_page(crate_name, page);
+        self.record(crate_name, start);
+        result
+    }
+
+    fn reverse_dependency_count(&self, crate_name: &str) -> Result<u64, RegistryError> {
+        let start = Instant::now();
+        let result = self.inner.reverse_dependency_count(crate_name);
+        self.record(crate_name, start);
+        result
+    }
+
+    fn reverse_dependencies_page(
+        &self,
+        crate_name: &str,
+        page: usize,
+    ) -> Result<DependentsPage, RegistryError> {
+        let start = Instant::now();
+        let result = self.inner.reverse_dependencies_page(crate_name, page);
+        self.record(crate_name, start);
+        result
+    }
+
+    fn search_crates(&self, query: &str, limit: usize) -> Result<Vec<String>, RegistryError> {
+        let start = Instant::now();
+        let result = self.inner.search_crates(query, limit);
+        self.record(query, start);
+        result
+    }
+
+    fn crate_owners(&self, crate_name: &str) -> Result<Vec<String>, RegistryError> {
+        let start = Instant::now();
+        let result = self.inner.crate_owners(crate_name);
+        self.record(crate_name, start);
+        result
+    }
+}
@@ -0,0 +1,649 @@
+//! # render
+//!
+//! The `render` module turns a fetched dependency graph into the output of one of the
+//! [`OutputFormat`]s. Every format implements the [`Renderer`] trait, so adding a format is a
+//! matter of a new implementation, a new arm in [`renderer`] and a new entry in [`FORMATS`].
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::dependency_graph::{DependencyGraph, TreeOptions};
+//! use depth::package::Package;
+//! use depth::render::{CargoTreeRenderer, Renderer};
+//!
+//! let mut root = Package::new("app".to_string(), "".to_string(), vec![], false);
+//! root.version = "1.0.0".to_string();
+//! let mut graph = DependencyGraph::new();
+//! graph.add_package_to_graph(&root);
+//!
+//! let renderer = CargoTreeRenderer {
+//!     max_depth: 2,
+//!     options: TreeOptions::default(),
+//! };
+//! let mut output = Vec::new();
+//! renderer.render(&graph, &root, &mut output).unwrap();
+//! assert_eq!(String::from_utf8(output).unwrap(), "app v1.0.0\n");
+//! ```
+
+use crate::dependency_graph::{DependencyGraph, EdgeStyle, TreeOptions};
+use crate::package::Package;
+use crate::report::{CrateRecord, Report};
+use crate::{OutputFormat, VisualizeOptions};
+use log::warn;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+
+/// Where the text summary of the report is written after the rendered output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryStream {
+    /// Below the output, for formats meant to be read by humans.
+    Stdout,
+    /// To stderr, keeping stdout parseable or pipeable.
+    Stderr,
+    /// Nowhere, the output already contains the whole report.
+    None,
+}
+
+/// A format a dependency graph can be rendered in.
+pub trait Renderer {
+    /// Renders the tree of `root` to `out`.
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()>;
+
+    /// Returns where the text summary of the report goes after this output.
+    fn summary_stream(&self) -> SummaryStream {
+        SummaryStream::Stderr
+    }
+}
+
+/// The colored tree printed by default.
+#[derive(Debug, Clone, Copy)]
+pub struct TreeRenderer {
+    /// The maximum depth to render, the root being at depth 0.
+    pub max_depth: usize,
+}
+
+impl Renderer for TreeRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        writeln!(out, "Dependencies for package '{}':", root.name)?;
+        graph.write_dependencies_at_level(root, 0, self.max_depth, out)
+    }
+
+    fn summary_stream(&self) -> SummaryStream {
+        SummaryStream::Stdout
+    }
+}
+
+/// The text layout of `cargo tree`.
+#[derive(Debug, Clone, Copy)]
+pub struct CargoTreeRenderer {
+    /// The maximum depth to render, the root being at depth 0.
+    pub max_depth: usize,
+    /// The options controlling how the tree is laid out.
+    pub options: TreeOptions,
+}
+
+impl Renderer for CargoTreeRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        graph.write_cargo_tree(root, self.max_depth, &self.options, out)
+    }
+}
+
+/// The leaf crates of the tree, one per line.
+#[derive(Debug, Clone, Copy)]
+pub struct LeavesRenderer {
+    /// The depth up to which dependencies were fetched, the root being at depth 0.
+    pub max_depth: usize,
+}
+
+impl Renderer for LeavesRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        for leaf in graph.leaves(root, self.max_depth) {
+            writeln!(out, "{}", leaf)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every fetched crate and its version, one per line, sorted by name.
+#[derive(Debug, Clone, Copy)]
+pub struct ListRenderer;
+
+impl Renderer for ListRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        _root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        for (name, version) in graph.unique_crates() {
+            writeln!(out, "{} {}", name, version)?;
+        }
+        Ok(())
+    }
+}
+
+/// A flat, sorted JSON array with one record per fetched crate.
+#[derive(Debug, Clone, Copy)]
+pub struct FlatJsonRenderer;
+
+impl Renderer for FlatJsonRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        _root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        CrateRecord::write_json(&CrateRecord::collect(graph), out)
+    }
+}
+
+/// The whole graph as JSON, every node and every edge with its kind and requirement.
+#[derive(Debug, Clone, Copy)]
+pub struct GraphJsonRenderer;
+
+impl Renderer for GraphJsonRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        _root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        writeln!(out, "{}", graph.to_json())
+    }
+}
+
+/// The whole graph in the DOT language of Graphviz.
+#[derive(Debug, Clone, Copy)]
+pub struct DotRenderer {
+    /// How the edges are drawn.
+    pub edge_style: EdgeStyle,
+}
+
+impl Renderer for DotRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        _root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        writeln!(out, "digraph {{")?;
+        write!(out, "{}", graph.to_dot_with_style(self.edge_style))?;
+        writeln!(out, "}}")
+    }
+}
+
+/// The whole graph as an SVG image, laid out by the `dot` binary of Graphviz.
+#[derive(Debug, Clone, Copy)]
+pub struct SvgRenderer {
+    /// How the edges are drawn.
+    pub edge_style: EdgeStyle,
+}
+
+impl Renderer for SvgRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let Some(dot) = graphviz_dot() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the svg format needs the `dot` binary of Graphviz, which was not found on PATH; \
+                 install Graphviz (https://graphviz.org/download/) or use --format dot",
+            ));
+        };
+        let mut source = Vec::new();
+        DotRenderer {
+            edge_style: self.edge_style,
+        }
+        .render(graph, root, &mut source)?;
+
+        let mut child = Command::new(dot)
+            .arg("-Tsvg")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // Written from another thread, so a large graph cannot fill both pipes and deadlock
+        let writer = thread::spawn(move || stdin.write_all(&source));
+        let output = child.wait_with_output()?;
+        writer.join().expect("the dot writer does not panic")?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "dot failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        out.write_all(&output.stdout)
+    }
+}
+
+/// Returns the path of the `dot` binary of Graphviz, looked up on PATH once.
+fn graphviz_dot() -> Option<&'static Path> {
+    static DOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+    DOT.get_or_init(|| {
+        let name = if cfg!(windows) { "dot.exe" } else { "dot" };
+        env::split_paths(&env::var_os("PATH")?)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    })
+    .as_deref()
+}
+
+/// The whole graph as a Mermaid flowchart, to embed in Markdown.
+#[derive(Debug, Clone, Copy)]
+pub struct MermaidRenderer {
+    /// How the edges are drawn.
+    pub edge_style: EdgeStyle,
+}
+
+impl Renderer for MermaidRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        _root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(graph.to_mermaid_with_style(self.edge_style).as_bytes())
+    }
+}
+
+/// The whole graph as GraphML, to import in graph analysis tools.
+#[derive(Debug, Clone, Copy)]
+pub struct GraphmlRenderer;
+
+impl Renderer for GraphmlRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        _root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        out.write_all(graph.to_graphml().as_bytes())
+    }
+}
+
+/// The analysis report as pretty-printed JSON.
+#[derive(Debug, Clone, Copy)]
+pub struct JsonRenderer<'a> {
+    /// The report of the rendered graph.
+    pub report: &'a Report,
+}
+
+impl Renderer for JsonRenderer<'_> {
+    fn render(
+        &self,
+        _graph: &DependencyGraph,
+        _root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.report.write_json(out)
+    }
+
+    fn summary_stream(&self) -> SummaryStream {
+        SummaryStream::None
+    }
+}
+
+/// A self-contained HTML page exploring the tree, collapsible in the browser.
+///
+/// The tree is baked into the page as JSON and rendered by a few lines of inline JavaScript,
+/// so the page works offline and can be shared as a single file. Crates seen earlier in the
+/// tree are not expanded again, as in the `cargo-tree` format.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::dependency_graph::DependencyGraph;
+/// use depth::package::Package;
+/// use depth::render::{HtmlRenderer, Renderer};
+///
+/// let dependencies = vec![("serde".to_string(), "^1".to_string())];
+/// let root = Package::new("app".to_string(), "".to_string(), dependencies, false);
+/// let mut graph = DependencyGraph::new();
+/// graph.add_package_to_graph(&root);
+///
+/// let mut output = Vec::new();
+/// HtmlRenderer { max_depth: 2 }.render(&graph, &root, &mut output).unwrap();
+/// let page = String::from_utf8(output).unwrap();
+/// assert!(page.starts_with("<!DOCTYPE html>"));
+/// assert!(page.contains(r#""url":"https://crates.io/crates/serde""#));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct HtmlRenderer {
+    /// The maximum depth to render, the root being at depth 0.
+    pub max_depth: usize,
+}
+
+/// A crate of the tree embedded in the HTML page.
+#[derive(Debug, Serialize)]
+struct HtmlNode {
+    name: String,
+    /// The resolved version, or the requirement of crates that were not fetched.
+    version: String,
+    url: String,
+    markers: Vec<String>,
+    /// `true` if the crate is expanded earlier in the tree.
+    repeated: bool,
+    children: Vec<HtmlNode>,
+}
+
+impl HtmlRenderer {
+    /// Builds the node of a crate and, up to the maximum depth, of its dependencies.
+    fn node(
+        &self,
+        graph: &DependencyGraph,
+        dependency: &(String, String),
+        depth: usize,
+        expanded: &mut HashSet<String>,
+    ) -> HtmlNode {
+        let package = graph.package(&dependency.0);
+        let mut node = HtmlNode {
+            name: graph.display_name(&dependency.0).to_string(),
+            version: package.map_or_else(|| dependency.1.clone(), |p| p.version.clone()),
+            url: format!("https://crates.io/crates/{}", dependency.0),
+            markers: graph.markers(&dependency.0).to_vec(),
+            repeated: false,
+            children: Vec::new(),
+        };
+        if graph.unavailable().contains_key(&dependency.0) {
+            node.markers.push("unavailable".to_string());
+        }
+
+        let Some(package) = package else {
+            return node;
+        };
+        if depth + 1 < self.max_depth && !package.dependencies.is_empty() {
+            if expanded.insert(package.name.clone()) {
+                node.children = package
+                    .dependencies
+                    .iter()
+                    .map(|child| self.node(graph, child, depth + 1, expanded))
+                    .collect();
+            } else {
+                node.repeated = true;
+            }
+        }
+        node
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn render(
+        &self,
+        graph: &DependencyGraph,
+        root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let root_dependency = (root.name.clone(), root.version.clone());
+        let mut tree = vec![self.node(graph, &root_dependency, 0, &mut HashSet::new())];
+        if graph.is_hidden_root(&root.name) {
+            tree = tree.remove(0).children;
+        }
+        // A `</script>` inside a crate name or marker must not end the script early
+        let tree = serde_json::to_string(&tree)?.replace("</", "<\\/");
+
+        let page = HTML_PAGE
+            .replace("{title}", &html_escape(&root.name))
+            .replace("{tree}", &tree);
+        out.write_all(page.as_bytes())
+    }
+}
+
+/// Escapes the characters with a meaning in HTML text.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The page of the `html` format, `{title}` and `{tree}` being replaced by the root crate's
+/// name and the JSON array of the top-level nodes, the root or, when hidden, its dependencies.
+const HTML_PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Dependencies of {title}</title>
+<style>
+  body { font-family: ui-monospace, monospace; margin: 2em; }
+  ul { list-style: none; padding-left: 1.5em; margin: 0; }
+  li { margin: 0.15em 0; }
+  .toggle { cursor: pointer; display: inline-block; width: 1em; user-select: none; }
+  .version, .repeated { color: #777; }
+  .marker { color: #b35900; }
+  .collapsed > ul { display: none; }
+</style>
+</head>
+<body>
+<h1>Dependencies of {title}</h1>
+<p><button id="expand">Expand all</button> <button id="collapse">Collapse all</button></p>
+<ul id="tree"></ul>
+<script>
+const roots = {tree};
+
+function render(node) {
+  const item = document.createElement("li");
+  const toggle = document.createElement("span");
+  toggle.className = "toggle";
+  item.appendChild(toggle);
+
+  const link = document.createElement("a");
+  link.href = node.url;
+  link.textContent = node.name;
+  item.appendChild(link);
+
+  const version = document.createElement("span");
+  version.className = "version";
+  version.textContent = " " + node.version;
+  item.appendChild(version);
+  for (const marker of node.markers) {
+    const flag = document.createElement("span");
+    flag.className = "marker";
+    flag.textContent = " [" + marker + "]";
+    item.appendChild(flag);
+  }
+  if (node.repeated) {
+    const repeated = document.createElement("span");
+    repeated.className = "repeated";
+    repeated.textContent = " (*)";
+    item.appendChild(repeated);
+  }
+
+  if (node.children.length > 0) {
+    toggle.textContent = "▾";
+    toggle.onclick = () => setCollapsed(item, !item.classList.contains("collapsed"));
+    const children = document.createElement("ul");
+    node.children.forEach((child) => children.appendChild(render(child)));
+    item.appendChild(children);
+  }
+  return item;
+}
+
+function setCollapsed(item, collapsed) {
+  item.classList.toggle("collapsed", collapsed);
+  item.querySelector(".toggle").textContent = collapsed ? "▸" : "▾";
+}
+
+function setAll(collapsed) {
+  document.querySelectorAll("#tree li").forEach((item) => {
+    if (item.querySelector("ul")) {
+      setCollapsed(item, collapsed);
+    }
+  });
+}
+
+roots.forEach((root) => document.getElementById("tree").appendChild(render(root)));
+document.getElementById("expand").onclick = () => setAll(false);
+document.getElementById("collapse").onclick = () => setAll(true);
+</script>
+</body>
+</html>
+"##;
+
+/// The analysis report as MessagePack.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy)]
+pub struct MsgpackRenderer<'a> {
+    /// The report of the rendered graph.
+    pub report: &'a Report,
+}
+
+#[cfg(feature = "msgpack")]
+impl Renderer for MsgpackRenderer<'_> {
+    fn render(
+        &self,
+        _graph: &DependencyGraph,
+        _root: &Package,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.report.write_msgpack(out)
+    }
+
+    fn summary_stream(&self) -> SummaryStream {
+        SummaryStream::None
+    }
+}
+
+/// Every output format [`renderer`] can select, in the order `--list-formats` prints them.
+pub const FORMATS: &[OutputFormat] = &[
+    OutputFormat::Tree,
+    OutputFormat::CargoTree,
+    OutputFormat::Json,
+    OutputFormat::FlatJson,
+    OutputFormat::GraphJson,
+    OutputFormat::List,
+    OutputFormat::Html,
+    OutputFormat::Dot,
+    OutputFormat::Svg,
+    OutputFormat::Mermaid,
+    OutputFormat::Graphml,
+    #[cfg(feature = "msgpack")]
+    OutputFormat::Msgpack,
+];
+
+/// Writes every output format with its description, one per line, the default marked as such.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::render::list_formats;
+///
+/// let mut output = Vec::new();
+/// list_formats(&mut output).unwrap();
+/// let output = String::from_utf8(output).unwrap();
+/// assert!(output.starts_with("tree        The colored tree printed by default (default)\n"));
+/// assert!(output.contains("\nflat-json   A flat, sorted JSON array with one record per crate\n"));
+/// ```
+pub fn list_formats(out: &mut dyn Write) -> io::Result<()> {
+    let width = FORMATS
+        .iter()
+        .map(|format| format.name().len())
+        .max()
+        .unwrap_or(0);
+    for &format in FORMATS {
+        write!(out, "{:<width$}  {}", format.name(), format.description())?;
+        if format == OutputFormat::default() {
+            write!(out, " (default)")?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Selects the renderer of the format chosen in the visualize options.
+///
+/// # Arguments
+///
+/// * `visualize_options` - The options controlling how the dependency tree is printed.
+/// * `max_depth` - The depth up to which dependencies were fetched, the root being at depth 0.
+/// * `report` - The report of the graph, rendered by the report formats.
+///
+/// # Returns
+///
+/// The renderer of the chosen format.
+pub fn renderer<'a>(
+    visualize_options: &VisualizeOptions,
+    max_depth: usize,
+    report: &'a Report,
+) -> Box<dyn Renderer + 'a> {
+    let tree = visualize_options.tree;
+    if visualize_options.edge_style != EdgeStyle::default()
+        && !matches!(
+            visualize_options.format,
+            OutputFormat::Dot | OutputFormat::Svg | OutputFormat::Mermaid
+        )
+    {
+        warn!("--edge-style and --edge-labels only apply to the dot, svg and mermaid formats");
+    }
+
+    match visualize_options.format {
+        OutputFormat::Tree | OutputFormat::CargoTree if visualize_options.leaves_only => {
+            Box::new(LeavesRenderer { max_depth })
+        }
+        OutputFormat::Tree => {
+            if tree.collapse_chains {
+                warn!("--collapse-chains only applies to the cargo-tree format");
+            }
+            if tree.show_size {
+                warn!("--show-size only applies to the cargo-tree format");
+            }
+            Box::new(TreeRenderer { max_depth })
+        }
+        OutputFormat::CargoTree => {
+            if tree.compact || tree.max_width.is_some() {
+                warn!("--compact and --max-width only apply to the default tree");
+            }
+            Box::new(CargoTreeRenderer {
+                max_depth,
+                options: tree,
+            })
+        }
+        OutputFormat::FlatJson => Box::new(FlatJsonRenderer),
+        OutputFormat::GraphJson => Box::new(GraphJsonRenderer),
+        OutputFormat::List => Box::new(ListRenderer),
+        OutputFormat::Html => Box::new(HtmlRenderer { max_depth }),
+        OutputFormat::Dot => Box::new(DotRenderer {
+            edge_style: visualize_options.edge_style,
+        }),
+        OutputFormat::Svg => Box::new(SvgRenderer {
+            edge_style: visualize_options.edge_style,
+        }),
+        OutputFormat::Mermaid => Box::new(MermaidRenderer {
+            edge_style: visualize_options.edge_style,
+        }),
+        OutputFormat::Graphml => Box::new(GraphmlRenderer),
+        OutputFormat::Json => Box::new(JsonRenderer { report }),
+        #[cfg(feature = "msgpack")]
+        OutputFormat::Msgpack => Box::new(MsgpackRenderer { report }),
+    }
+}
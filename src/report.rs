@@ -0,0 +1,909 @@
+//! # report
+//!
+//! The `report` module collects the results of the analyses `depth` runs over a fetched
+//! dependency graph into a single [`Report`]. The same report is rendered as the human readable
+//! summary below the tree and, with `--format json`, serialized as JSON so CI can parse it.
+//!
+//! With `--format flat-json`, the fetched crates are instead written as a flat, sorted array of
+//! [`CrateRecord`]s, a lockfile-like record of the resolved dependency set. The whole graph, with
+//! its edges and unfetched crates, is written by `--format graph-json` instead, see
+//! `DependencyGraph::to_json`.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::dependency_graph::DependencyGraph;
+//! use depth::package::Package;
+//! use depth::report::Report;
+//!
+//! let graph = DependencyGraph::new();
+//! let root = Package::new("app".to_string(), "".to_string(), vec![], false);
+//! let report = Report::new(&graph, &root, 2);
+//!
+//! assert_eq!(
+//!     serde_json::to_string(&report).unwrap(),
+//!     r#"{"summary":{"direct":0,"transitive":0},"unavailable":{},"errors":[],"packages":[]}"#
+//! );
+//! ```
+
+use crate::audit::Advisory;
+use crate::dependency_graph::{DependencyGraph, DuplicateVersions, GraphStats, SharedCrate};
+use crate::package::{format_size, Package};
+use crate::registry::{CrateError, CrateRegistry, OwnerChange};
+use crate::text::{display_width, pad_to_width};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// The counts of dependencies of the root package.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Summary {
+    /// The number of unique direct dependencies.
+    pub direct: usize,
+    /// The number of other unique crates reachable from the root.
+    pub transitive: usize,
+}
+
+/// The direct dependencies solely responsible for pulling a crate into the tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blame {
+    /// The name of the blamed crate.
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Whether the crate is in the tree at all.
+    pub found: bool,
+    /// The direct dependencies without which the crate would disappear from the tree.
+    pub responsible: Vec<String>,
+}
+
+impl Blame {
+    /// Blames the direct dependencies of the root package for pulling in the given crate.
+    pub fn new(graph: &DependencyGraph, root_package: &Package, crate_name: &str) -> Self {
+        let responsible = graph.blame(root_package, crate_name);
+        Blame {
+            crate_name: crate_name.to_string(),
+            found: responsible.is_some(),
+            responsible: responsible.unwrap_or_default(),
+        }
+    }
+}
+
+/// The distinct owners of the crates of a tree, the people a build trusts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Contributors {
+    /// The number of distinct owners across the crates.
+    pub owners: usize,
+    /// The number of crates whose owners were looked up.
+    pub crates: usize,
+    /// The owners of the most crates, most first.
+    pub top: Vec<OwnerCrates>,
+}
+
+/// An owner with the number of crates of the tree they own.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnerCrates {
+    /// The login of the user or team.
+    pub login: String,
+    /// The number of crates of the tree they own.
+    pub crates: usize,
+}
+
+impl Contributors {
+    /// Looks up the owners of every fetched crate and aggregates them.
+    ///
+    /// This costs one request per crate, each crate being looked up once however many times it
+    /// appears in the tree. Crates whose owners cannot be listed are skipped with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The fetched dependency graph.
+    /// * `client` - The registry to list the owners from.
+    /// * `top` - The number of owners of the most crates to keep.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    /// use depth::report::{Contributors, OwnerCrates};
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(CrateInfo::new("app", "1.0.0"), vec![DependencyInfo::new("serde", "^1")]);
+    /// registry.add_crate(CrateInfo::new("serde", "1.0.0"), vec![]);
+    /// registry.add_owner("app", "alice");
+    /// registry.add_owner("app", "dtolnay");
+    /// registry.add_owner("serde", "dtolnay");
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// graph
+    ///     .fetch_dependency_tree_with_registry("app", 2, &Default::default(), &registry)
+    ///     .unwrap();
+    ///
+    /// let contributors = Contributors::collect(&graph, &registry, 1);
+    /// assert_eq!(contributors.owners, 2);
+    /// assert_eq!(
+    ///     contributors.top,
+    ///     vec![OwnerCrates { login: "dtolnay".to_string(), crates: 2 }]
+    /// );
+    /// ```
+    pub fn collect(graph: &DependencyGraph, client: &impl CrateRegistry, top: usize) -> Self {
+        let mut owned: BTreeMap<String, usize> = BTreeMap::new();
+        let mut crates = 0;
+        for package in graph.packages() {
+            match client.crate_owners(&package.name) {
+                Ok(owners) => {
+                    crates += 1;
+                    for login in owners {
+                        *owned.entry(login).or_default() += 1;
+                    }
+                }
+                Err(err) => warn!("{}, skipping its owners", err),
+            }
+        }
+
+        let mut ranked: Vec<OwnerCrates> = owned
+            .into_iter()
+            .map(|(login, crates)| OwnerCrates { login, crates })
+            .collect();
+        // Stable, so owners of as many crates stay sorted by login
+        ranked.sort_by_key(|owner| Reverse(owner.crates));
+        let owners = ranked.len();
+        ranked.truncate(top);
+
+        Contributors {
+            owners,
+            crates,
+            top: ranked,
+        }
+    }
+}
+
+/// One crate of the flat, lockfile-like record of a resolved dependency set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateRecord {
+    /// The name of the crate.
+    pub name: String,
+    /// The resolved version of the crate.
+    pub version: String,
+    /// The license of the resolved version, if known.
+    pub license: Option<String>,
+    /// The homepage of the crate, empty if it has none.
+    pub url: String,
+    /// `true` if the crate is internal, and its dependencies were not fetched.
+    #[serde(default)]
+    pub internal: bool,
+    /// The names of the direct dependencies of the crate, sorted.
+    pub dependencies: Vec<String>,
+}
+
+impl CrateRecord {
+    /// Collects one record per package fetched into the graph, sorted by name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{CrateInfo, DependencyInfo, MemoryRegistry};
+    /// use depth::report::CrateRecord;
+    ///
+    /// let mut serde = CrateInfo::new("serde", "1.0.0");
+    /// serde.license = Some("MIT OR Apache-2.0".to_string());
+    ///
+    /// let mut registry = MemoryRegistry::new();
+    /// registry.add_crate(
+    ///     CrateInfo::new("app", "0.1.0"),
+    ///     vec![DependencyInfo::new("serde", "^1")],
+    /// );
+    /// registry.add_crate(serde, vec![]);
+    ///
+    /// let mut graph = DependencyGraph::new();
+    /// graph
+    ///     .fetch_dependency_tree_with_registry("app", 2, &FetchOptions::default(), &registry)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     serde_json::to_string(&CrateRecord::collect(&graph)).unwrap(),
+    ///     concat!(
+    ///         r#"[{"name":"app","version":"0.1.0","license":null,"url":"","internal":false,"dependencies":["serde"]},"#,
+    ///         r#"{"name":"serde","version":"1.0.0","license":"MIT OR Apache-2.0","url":"","internal":false,"dependencies":[]}]"#,
+    ///     )
+    /// );
+    /// ```
+    pub fn collect(graph: &DependencyGraph) -> Vec<CrateRecord> {
+        graph
+            .packages()
+            .into_iter()
+            .map(|package| {
+                let mut dependencies: Vec<String> = package
+                    .dependencies
+                    .iter()
+                    .map(|dependency| dependency.0.clone())
+                    .collect();
+                dependencies.sort();
+                dependencies.dedup();
+
+                CrateRecord {
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                    license: package.license.clone(),
+                    url: package.url.clone(),
+                    internal: package.internal,
+                    dependencies,
+                }
+            })
+            .collect()
+    }
+
+    /// Writes records as a pretty-printed JSON array.
+    pub fn write_json(records: &[CrateRecord], out: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, records)?;
+        writeln!(out)
+    }
+}
+
+/// The throughput of a crawl, printed by `--benchmark` instead of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Benchmark {
+    /// The time the crawl took.
+    pub wall_time: Duration,
+    /// The number of unique crates fetched.
+    pub crates: usize,
+    /// The number of requests sent to the registry.
+    pub requests: usize,
+    /// The number of package lookups, once per edge of the tree plus the root.
+    pub lookups: usize,
+    /// The lookups answered by an already fetched crate, without requests.
+    pub cache_hits: usize,
+}
+
+impl Benchmark {
+    /// Collects the throughput of the crawl that fetched the graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The fetched dependency graph.
+    /// * `requests` - The number of requests sent to the registry.
+    /// * `wall_time` - The time the crawl took.
+    pub fn new(graph: &DependencyGraph, requests: usize, wall_time: Duration) -> Self {
+        let stats = graph.fetch_stats();
+        Benchmark {
+            wall_time,
+            crates: graph.packages().len(),
+            requests,
+            lookups: stats.lookups,
+            cache_hits: stats.cache_hits,
+        }
+    }
+
+    /// Returns the number of crates fetched per second.
+    pub fn crates_per_second(&self) -> f64 {
+        self.crates as f64 / self.wall_time.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Returns the share of lookups answered without requests, between 0 and 1.
+    pub fn cache_hit_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            return 0.0;
+        }
+        self.cache_hits as f64 / self.lookups as f64
+    }
+
+    /// Writes the benchmark as a summary table.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::report::Benchmark;
+    /// use std::time::Duration;
+    ///
+    /// let benchmark = Benchmark {
+    ///     wall_time: Duration::from_secs(4),
+    ///     crates: 10,
+    ///     requests: 20,
+    ///     lookups: 16,
+    ///     cache_hits: 6,
+    /// };
+    /// let mut text = Vec::new();
+    /// benchmark.write_text(&mut text).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(text).unwrap(),
+    ///     "\
+    /// wall time         4.00s
+    /// crates            10
+    /// crates/second     2.50
+    /// requests          20
+    /// cache hit rate    37.5% (6 of 16 lookups)
+    /// "
+    /// );
+    /// ```
+    pub fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{:<18}{:.2?}", "wall time", self.wall_time)?;
+        writeln!(out, "{:<18}{}", "crates", self.crates)?;
+        writeln!(
+            out,
+            "{:<18}{:.2}",
+            "crates/second",
+            self.crates_per_second()
+        )?;
+        writeln!(out, "{:<18}{}", "requests", self.requests)?;
+        writeln!(
+            out,
+            "{:<18}{:.1}% ({} of {} lookups)",
+            "cache hit rate",
+            self.cache_hit_rate() * 100.0,
+            self.cache_hits,
+            self.lookups
+        )
+    }
+}
+
+/// The number of estimated requests above which `--dry-run` warns about the crawl.
+pub const LARGE_CRAWL_REQUESTS: usize = 1000;
+
+/// The requests a crawl would send, printed by `--dry-run` instead of the tree.
+///
+/// The estimate is measured on the root and its direct dependencies alone: every crate these
+/// list is another crate to fetch, at the same number of requests per crate. Deeper levels
+/// are not fetched, so beyond the level below the direct dependencies the estimate is only a
+/// lower bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrawlEstimate {
+    /// The depth of the estimated crawl, the root counting as depth 1.
+    pub depth: usize,
+    /// The number of crates fetched to estimate the crawl, the root included.
+    pub fetched: usize,
+    /// The number of requests sent to fetch them.
+    pub requests: usize,
+    /// The number of crates listed by the fetched crates without being fetched.
+    pub unfetched: usize,
+}
+
+impl CrawlEstimate {
+    /// Estimates a crawl from a graph fetched at a depth of at most 2.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The graph of the root and its direct dependencies.
+    /// * `depth` - The depth of the estimated crawl.
+    /// * `requests` - The number of requests sent to fetch the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::FetchOptions;
+    /// use depth::registry::{MemoryRegistry, TimedRegistry};
+    /// use depth::report::CrawlEstimate;
+    ///
+    /// let registry = MemoryRegistry::from_edges(&[
+    ///     ("app", &["clap", "serde"]),
+    ///     ("clap", &["clap_lex", "serde"]),
+    ///     ("serde", &["serde_derive"]),
+    /// ]);
+    ///
+    /// let client = TimedRegistry::new(registry);
+    /// let mut graph = DependencyGraph::new();
+    /// graph
+    ///     .fetch_dependency_tree_with_registry("app", 2, &FetchOptions::default(), &client)
+    ///     .unwrap();
+    /// let estimate = CrawlEstimate::new(&graph, 3, client.request_count());
+    ///
+    /// assert_eq!((estimate.fetched, estimate.unfetched), (3, 2));
+    /// assert_eq!(estimate.estimated_requests(), estimate.requests / 3 * 5);
+    /// ```
+    pub fn new(graph: &DependencyGraph, depth: usize, requests: usize) -> Self {
+        let packages = graph.packages();
+        let mut unfetched: Vec<&str> = packages
+            .iter()
+            .flat_map(|package| &package.dependencies)
+            .map(|(name, _)| name.as_str())
+            .filter(|name| graph.package(name).is_none())
+            .collect();
+        unfetched.sort_unstable();
+        unfetched.dedup();
+        CrawlEstimate {
+            depth,
+            fetched: packages.len(),
+            requests,
+            unfetched: unfetched.len(),
+        }
+    }
+
+    /// Returns whether the estimate only covers part of the crawl, which goes deeper than the
+    /// crates listed by the direct dependencies.
+    pub fn is_lower_bound(&self) -> bool {
+        self.depth > 3
+    }
+
+    /// Returns the estimated number of requests of the crawl.
+    pub fn estimated_requests(&self) -> usize {
+        if self.depth <= 2 {
+            return self.requests;
+        }
+        let per_crate = self.requests.div_ceil(self.fetched.max(1));
+        self.requests
+            .saturating_add(self.unfetched.saturating_mul(per_crate))
+    }
+
+    /// Writes the estimate as a summary table, followed by a warning when the crawl would send
+    /// more than `LARGE_CRAWL_REQUESTS` requests.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::report::CrawlEstimate;
+    ///
+    /// let estimate = CrawlEstimate {
+    ///     depth: 4,
+    ///     fetched: 31,
+    ///     requests: 62,
+    ///     unfetched: 540,
+    /// };
+    /// let mut text = Vec::new();
+    /// estimate.write_text(&mut text).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(text).unwrap(),
+    ///     "\
+    /// crates fetched    31 (62 requests)
+    /// crates listed     540 more
+    /// estimate          at least 1142 requests for 3 levels
+    /// Warning: this crawl may send more than 1000 requests, consider fewer levels
+    /// "
+    /// );
+    /// ```
+    pub fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "{:<18}{} ({} requests)",
+            "crates fetched", self.fetched, self.requests
+        )?;
+        writeln!(out, "{:<18}{} more", "crates listed", self.unfetched)?;
+        let levels = match self.depth {
+            usize::MAX => "every level".to_string(),
+            2 => "1 level".to_string(),
+            depth => format!("{} levels", depth.saturating_sub(1)),
+        };
+        writeln!(
+            out,
+            "{:<18}{}{} requests for {}",
+            "estimate",
+            if self.is_lower_bound() {
+                "at least "
+            } else {
+                ""
+            },
+            self.estimated_requests(),
+            levels
+        )?;
+        if self.estimated_requests() > LARGE_CRAWL_REQUESTS {
+            writeln!(
+                out,
+                "Warning: this crawl may send more than {} requests, consider fewer levels",
+                LARGE_CRAWL_REQUESTS
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The results of every analysis run over a dependency graph.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Report {
+    /// The dependency counts of the root package.
+    pub summary: Summary,
+    /// Crates that could not be fetched, with the reason.
+    pub unavailable: BTreeMap<String, String>,
+    /// The crates that could not be resolved, sorted by name, with a structured reason.
+    #[serde(default)]
+    pub errors: Vec<CrateError>,
+    /// Every package fetched into the graph, sorted by name.
+    pub packages: Vec<Package>,
+    /// The result of `--blame`, if requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blame: Option<Blame>,
+    /// The leaf crates of the tree, if `--leaves-only` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaves: Option<Vec<String>>,
+    /// The summed tarball size of the fetched crates in bytes, if `--total-size` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    /// The crates published by someone else than their previous version, if `--owner-changes`
+    /// was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_changes: Option<BTreeMap<String, OwnerChange>>,
+    /// The number of crates depending on each fetched crate, if `--show-rdeps` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverse_dependencies: Option<BTreeMap<String, u64>>,
+    /// The downloads of each fetched crate, if `--show-downloads` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<BTreeMap<String, u64>>,
+    /// The build-dependencies of the crates that likely run a build script, if
+    /// `--flag-build-scripts` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_scripts: Option<BTreeMap<String, Vec<String>>>,
+    /// The crates most other crates of the tree depend on, most shared first, if `--most-shared`
+    /// was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub most_shared: Option<Vec<SharedCrate>>,
+    /// The dependency cycles of the graph, each closing on its first crate, if `--show-cycles`
+    /// was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<Vec<Vec<String>>>,
+    /// The longest dependency chain of the graph, if `--longest-path` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longest_path: Option<Vec<String>>,
+    /// The crates built in more than one version, with the crates requiring each version, if
+    /// `--show-duplicates` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<Vec<DuplicateVersions>>,
+    /// The crates shared by the trees of several crates visualized together, each with the
+    /// crates whose trees it is in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared: Option<BTreeMap<String, Vec<String>>>,
+    /// The fetched crates grouped by their declared edition, or else by the newest edition their
+    /// `rust-version` allows, `unknown` for crates declaring neither, if `--editions` was
+    /// requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editions: Option<BTreeMap<String, Vec<String>>>,
+    /// The RustSec advisories affecting the fetched crates, most severe first, if `--audit` was
+    /// requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub advisories: Option<Vec<Advisory>>,
+    /// The distinct owners of the fetched crates, if `--contributors` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contributors: Option<Contributors>,
+    /// The size and the depth reached of the graph, unless `--quiet` was set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<GraphStats>,
+    /// Color the advisories by severity in the text summary, see `ColorScheme::enabled_if`.
+    #[serde(skip)]
+    pub colored: bool,
+}
+
+impl Report {
+    /// Runs the analyses over a fetched dependency graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The fetched dependency graph.
+    /// * `root_package` - The root package of the graph.
+    /// * `depth` - The depth up to which dependencies were fetched, the root being at depth 0.
+    pub fn new(graph: &DependencyGraph, root_package: &Package, depth: usize) -> Self {
+        let (direct, transitive) = graph.dependency_counts(root_package, depth);
+
+        Report {
+            summary: Summary { direct, transitive },
+            unavailable: graph.unavailable().clone(),
+            errors: graph.errors().into_iter().cloned().collect(),
+            packages: graph.packages().into_iter().cloned().collect(),
+            blame: None,
+            leaves: None,
+            total_size: None,
+            owner_changes: None,
+            reverse_dependencies: None,
+            downloads: None,
+            build_scripts: None,
+            most_shared: None,
+            cycles: None,
+            longest_path: None,
+            duplicates: None,
+            shared: None,
+            editions: None,
+            advisories: None,
+            contributors: None,
+            stats: None,
+            colored: false,
+        }
+    }
+
+    /// Writes the report as the human readable summary printed below a tree.
+    pub fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "{} direct dependencies, {} transitive.",
+            self.summary.direct, self.summary.transitive
+        )?;
+
+        if !self.errors.is_empty() {
+            writeln!(out, "Failed to fetch:")?;
+            for error in &self.errors {
+                writeln!(
+                    out,
+                    " - {} ({}): {}",
+                    error.name, error.reason, error.message
+                )?;
+            }
+        }
+        // Crates marked unavailable without a structured error, e.g. by library users
+        let unstructured: Vec<_> = self
+            .unavailable
+            .iter()
+            .filter(|(name, _)| !self.errors.iter().any(|error| error.name == **name))
+            .collect();
+        if !unstructured.is_empty() {
+            writeln!(out, "Unavailable crates:")?;
+            for (name, reason) in unstructured {
+                writeln!(out, " - {}: {}", name, reason)?;
+            }
+        }
+
+        if let Some(total_size) = self.total_size {
+            writeln!(
+                out,
+                "Total size: {} across {} crates.",
+                format_size(total_size),
+                self.packages.len()
+            )?;
+        }
+
+        if let Some(owner_changes) = &self.owner_changes {
+            if !owner_changes.is_empty() {
+                writeln!(out, "Crates published by a new owner:")?;
+                let width = label_width(owner_changes.keys());
+                for (name, change) in owner_changes {
+                    writeln!(
+                        out,
+                        " - {} published by '{}', previously by '{}'",
+                        pad_to_width(&format!("{}:", name), width),
+                        change.latest,
+                        change.previous
+                    )?;
+                }
+            }
+        }
+
+        if let Some(build_scripts) = &self.build_scripts {
+            if !build_scripts.is_empty() {
+                writeln!(out, "Crates likely running a build script:")?;
+                let width = label_width(build_scripts.keys());
+                for (name, build_dependencies) in build_scripts {
+                    writeln!(
+                        out,
+                        " - {} builds with {}",
+                        pad_to_width(&format!("{}:", name), width),
+                        build_dependencies.join(", ")
+                    )?;
+                }
+            }
+        }
+
+        if let Some(most_shared) = &self.most_shared {
+            if !most_shared.is_empty() {
+                writeln!(out, "Most shared crates:")?;
+                let width = label_width(most_shared.iter().map(|shared| &shared.name));
+                for shared in most_shared {
+                    writeln!(
+                        out,
+                        " - {} {} dependents",
+                        pad_to_width(&format!("{}:", shared.name), width),
+                        shared.dependents
+                    )?;
+                }
+            }
+        }
+
+        if let Some(cycles) = &self.cycles {
+            if cycles.is_empty() {
+                writeln!(out, "No dependency cycles.")?;
+            } else {
+                writeln!(out, "Dependency cycles:")?;
+                for cycle in cycles {
+                    writeln!(out, " - {}", cycle.join(" -> "))?;
+                }
+            }
+        }
+
+        if let Some(path) = &self.longest_path {
+            writeln!(
+                out,
+                "Longest dependency chain, {} levels: {}",
+                path.len().saturating_sub(1),
+                path.join(" -> ")
+            )?;
+        }
+
+        if let Some(shared) = &self.shared {
+            writeln!(out, "{} crates shared across trees.", shared.len())?;
+            if !shared.is_empty() {
+                let width = label_width(shared.keys());
+                for (name, roots) in shared {
+                    writeln!(
+                        out,
+                        " - {} {}",
+                        pad_to_width(&format!("{}:", name), width),
+                        roots.join(", ")
+                    )?;
+                }
+            }
+        }
+
+        if let Some(duplicates) = &self.duplicates {
+            if duplicates.is_empty() {
+                writeln!(out, "No crates built in several versions.")?;
+            } else {
+                writeln!(out, "Crates built in several versions:")?;
+                let width = label_width(duplicates.iter().map(|duplicate| &duplicate.name));
+                for duplicate in duplicates {
+                    let versions: Vec<String> = duplicate
+                        .versions
+                        .iter()
+                        .map(|version| {
+                            format!("{} ({})", version.version, version.required_by.join(", "))
+                        })
+                        .collect();
+                    writeln!(
+                        out,
+                        " - {} {}",
+                        pad_to_width(&format!("{}:", duplicate.name), width),
+                        versions.join(", ")
+                    )?;
+                }
+            }
+        }
+
+        if let Some(editions) = &self.editions {
+            writeln!(out, "Editions (declared, or the newest rust-version allows):")?;
+            let width = label_width(editions.keys());
+            for (edition, crates) in editions {
+                writeln!(
+                    out,
+                    " - {} {} crates",
+                    pad_to_width(&format!("{}:", edition), width),
+                    crates.len()
+                )?;
+            }
+            if let Some(crates) = editions.get("2015") {
+                writeln!(out, "Crates still on edition 2015: {}", crates.join(", "))?;
+            }
+        }
+
+        if let Some(advisories) = &self.advisories {
+            if advisories.is_empty() {
+                writeln!(out, "No advisories found.")?;
+            } else {
+                writeln!(out, "{} advisories found:", advisories.len())?;
+                for advisory in advisories {
+                    let severity = advisory
+                        .severity
+                        .map_or("unrated".to_string(), |severity| severity.to_string());
+                    let line = format!(
+                        " - {} [{}] {} v{}: {}",
+                        advisory.id,
+                        severity,
+                        advisory.crate_name,
+                        advisory.version,
+                        advisory.title
+                    );
+                    match advisory.severity.filter(|_| self.colored) {
+                        Some(severity) => {
+                            writeln!(out, "\x1b[{}m{}\x1b[0m", severity.color_code(), line)?
+                        }
+                        None => writeln!(out, "{}", line)?,
+                    }
+                }
+            }
+        }
+
+        if let Some(contributors) = &self.contributors {
+            writeln!(
+                out,
+                "{} unique owners across {} crates.",
+                contributors.owners, contributors.crates
+            )?;
+            if !contributors.top.is_empty() {
+                writeln!(out, "Owners of the most crates:")?;
+                let width = label_width(contributors.top.iter().map(|owner| &owner.login));
+                for owner in &contributors.top {
+                    writeln!(
+                        out,
+                        " - {} {} crates",
+                        pad_to_width(&format!("{}:", owner.login), width),
+                        owner.crates
+                    )?;
+                }
+            }
+        }
+
+        if let Some(blame) = &self.blame {
+            if !blame.found {
+                writeln!(out, "'{}' is not in the dependency tree.", blame.crate_name)?;
+            } else if blame.responsible.is_empty() {
+                writeln!(
+                    out,
+                    "No single direct dependency is solely responsible for '{}'.",
+                    blame.crate_name
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "'{}' is only pulled in by: {}",
+                    blame.crate_name,
+                    blame.responsible.join(", ")
+                )?;
+            }
+        }
+
+        if let Some(stats) = &self.stats {
+            writeln!(
+                out,
+                "{} crates ({} fetched), {} edges, {} levels deep.",
+                stats.node_count, stats.unique_crates, stats.edge_count, stats.max_depth
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Sums the tarball sizes of the fetched crates, crates of unknown size counting as 0.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::Package;
+    /// use depth::report::Report;
+    ///
+    /// let mut root = Package::new("app".to_string(), "".to_string(), vec![], false);
+    /// root.size = Some(2048);
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&root);
+    ///
+    /// let mut report = Report::new(&graph, &root, 2);
+    /// report.total_size = Some(report.sum_sizes());
+    ///
+    /// let mut text = Vec::new();
+    /// report.write_text(&mut text).unwrap();
+    /// assert!(String::from_utf8(text)
+    ///     .unwrap()
+    ///     .contains("Total size: 2.0 KiB across 1 crates."));
+    /// ```
+    pub fn sum_sizes(&self) -> u64 {
+        self.packages
+            .iter()
+            .filter_map(|package| package.size)
+            .sum()
+    }
+
+    /// Writes the report as pretty-printed JSON.
+    pub fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, self)?;
+        writeln!(out)
+    }
+
+    /// Writes the report as MessagePack, with field names so it can be read back with
+    /// `rmp_serde::from_slice`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depth::dependency_graph::DependencyGraph;
+    /// use depth::package::Package;
+    /// use depth::report::Report;
+    ///
+    /// let root = Package::new("app".to_string(), "".to_string(), vec![], false);
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add_package_to_graph(&root);
+    /// let report = Report::new(&graph, &root, 2);
+    ///
+    /// let mut bytes = Vec::new();
+    /// report.write_msgpack(&mut bytes).unwrap();
+    ///
+    /// let decoded: Report = rmp_serde::from_slice(&bytes).unwrap();
+    /// assert_eq!(decoded.packages[0].name, "app");
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn write_msgpack(&self, out: &mut dyn Write) -> io::Result<()> {
+        rmp_serde::encode::write_named(out, self).map_err(io::Error::other)
+    }
+}
+
+/// Returns the display width of the widest `name:` label, to align the lists of the summary.
+fn label_width<'a>(names: impl Iterator<Item = &'a String>) -> usize {
+    names.map(|name| display_width(name) + 1).max().unwrap_or(0)
+}
@@ -0,0 +1,136 @@
+//! # semver_util
+//!
+//! The `semver_util` module answers whether several version requirements on the same crate can
+//! be unified, i.e. whether one published version satisfies all of them. Cargo can only build a
+//! single version of a crate per semver-compatible range, so two crates requiring `^1.2` and
+//! `<1.1` of a dependency force a duplicate or a conflict. This is the core of the duplicate
+//! and conflict reports.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::semver_util::intersect;
+//! use semver::{Version, VersionReq};
+//!
+//! let versions: Vec<Version> = ["1.0.0", "1.2.0", "1.4.3", "2.0.0"]
+//!     .iter()
+//!     .map(|num| Version::parse(num).unwrap())
+//!     .collect();
+//! let reqs = |reqs: &[&str]| -> Vec<VersionReq> {
+//!     reqs.iter().map(|req| VersionReq::parse(req).unwrap()).collect()
+//! };
+//!
+//! // Intersecting ranges unify on the highest version in both
+//! assert_eq!(
+//!     intersect(&reqs(&["^1.2", ">=1.0, <1.4"]), &versions),
+//!     Some(Version::parse("1.2.0").unwrap())
+//! );
+//! assert_eq!(
+//!     intersect(&reqs(&["^1", "~1.4"]), &versions),
+//!     Some(Version::parse("1.4.3").unwrap())
+//! );
+//!
+//! // Disjoint ranges, or ranges meeting between published versions, do not
+//! assert_eq!(intersect(&reqs(&["^1.2", "^2"]), &versions), None);
+//! assert_eq!(intersect(&reqs(&[">1.2.0", "<1.4.0"]), &versions), None);
+//!
+//! // No requirement at all is satisfied by any version
+//! assert_eq!(intersect(&[], &versions), Some(Version::parse("2.0.0").unwrap()));
+//! ```
+
+use crate::registry::{CrateRegistry, RegistryError};
+use semver::{Version, VersionReq};
+
+/// Returns the highest of the given versions satisfying every requirement, if any.
+///
+/// Pre-releases follow the rules of `semver`: they only satisfy requirements that opt into them.
+///
+/// # Arguments
+///
+/// * `reqs` - The requirements to intersect.
+/// * `versions` - The candidate versions, e.g. the published versions of the crate.
+pub fn intersect(reqs: &[VersionReq], versions: &[Version]) -> Option<Version> {
+    versions
+        .iter()
+        .filter(|version| reqs.iter().all(|req| req.matches(version)))
+        .max()
+        .cloned()
+}
+
+/// Returns `true` if one of the given versions satisfies every requirement.
+pub fn can_unify(reqs: &[VersionReq], versions: &[Version]) -> bool {
+    intersect(reqs, versions).is_some()
+}
+
+/// Parses a version requirement, an exact version such as `1.0.100` only matching that version
+/// rather than the versions compatible with it like it would in a manifest.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::semver_util::parse_version_req;
+/// use semver::Version;
+///
+/// let exact = parse_version_req("1.0.100").unwrap();
+/// assert!(exact.matches(&Version::parse("1.0.100").unwrap()));
+/// assert!(!exact.matches(&Version::parse("1.0.101").unwrap()));
+///
+/// let compatible = parse_version_req("^1.0").unwrap();
+/// assert!(compatible.matches(&Version::parse("1.0.101").unwrap()));
+/// assert!(parse_version_req("latest").is_err());
+/// ```
+pub fn parse_version_req(input: &str) -> Result<VersionReq, semver::Error> {
+    match Version::parse(input.trim()) {
+        Ok(version) => VersionReq::parse(&format!("={}", version)),
+        Err(_) => VersionReq::parse(input),
+    }
+}
+
+/// Returns the highest published, non-yanked version of a crate satisfying every requirement.
+///
+/// # Arguments
+///
+/// * `registry` - The registry to list the versions from.
+/// * `crate_name` - The name of the crate the requirements are on.
+/// * `reqs` - The requirements to intersect.
+///
+/// # Returns
+///
+/// A Result containing the common version, `None` if the requirements cannot be unified.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::registry::{CrateInfo, MemoryRegistry, VersionInfo};
+/// use depth::semver_util::common_version;
+/// use semver::VersionReq;
+///
+/// let mut registry = MemoryRegistry::new();
+/// registry.add_crate(CrateInfo::new("syn", "2.0.0"), vec![]);
+/// for num in ["1.0.109", "2.0.0"] {
+///     registry.add_version("syn", VersionInfo::new(num));
+/// }
+/// let mut yanked = VersionInfo::new("1.0.110");
+/// yanked.yanked = true;
+/// registry.add_version("syn", yanked);
+///
+/// let reqs = [VersionReq::parse("^1").unwrap(), VersionReq::parse(">=1.0.100").unwrap()];
+/// let version = common_version(&registry, "syn", &reqs).unwrap();
+/// assert_eq!(version.unwrap().to_string(), "1.0.109");
+///
+/// let reqs = [VersionReq::parse("^1").unwrap(), VersionReq::parse("^2").unwrap()];
+/// assert_eq!(common_version(&registry, "syn", &reqs).unwrap(), None);
+/// ```
+pub fn common_version(
+    registry: &impl CrateRegistry,
+    crate_name: &str,
+    reqs: &[VersionReq],
+) -> Result<Option<Version>, RegistryError> {
+    let versions: Vec<Version> = registry
+        .crate_versions(crate_name)?
+        .iter()
+        .filter(|version| !version.yanked)
+        .filter_map(|version| Version::parse(&version.num).ok())
+        .collect();
+    Ok(intersect(reqs, &versions))
+}
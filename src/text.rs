@@ -0,0 +1,72 @@
+//! # text
+//!
+//! The `text` module pads and truncates strings by their display width rather than their length
+//! in bytes or chars, so the columns of annotated output stay aligned when metadata such as
+//! descriptions or owner names contain wide (CJK, emoji) or combining characters.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::text::{display_width, pad_to_width};
+//!
+//! // Each CJK character takes two columns in a terminal
+//! assert_eq!(display_width("日本"), 4);
+//! assert_eq!(pad_to_width("日本", 6), "日本  ");
+//! ```
+
+use terminal_size::{terminal_size, Width};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Returns the number of terminal columns a string takes.
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Pads a string with spaces up to the given display width, leaving wider strings untouched.
+pub fn pad_to_width(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+/// Truncates a string to the given display width, ending it with `…` if it was cut.
+///
+/// Wide characters are never split, so the result may be one column narrower than `width`.
+///
+/// # Examples
+///
+/// ```rust
+/// use depth::text::{display_width, truncate_to_width};
+///
+/// let description = "🦀 高速なシリアライザ for Rust";
+/// let truncated = truncate_to_width(description, 12);
+/// assert_eq!(truncated, "🦀 高速なシ…");
+/// assert!(display_width(&truncated) <= 12);
+///
+/// assert_eq!(truncate_to_width("serde", 12), "serde");
+/// ```
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+
+    // Keep one column for the ellipsis
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Returns the number of columns of the terminal stdout is written to, `None` if stdout is not
+/// a terminal.
+pub fn terminal_width() -> Option<usize> {
+    let (Width(columns), _) = terminal_size()?;
+    Some(usize::from(columns)).filter(|&columns| columns > 0)
+}
@@ -0,0 +1,404 @@
+//! # tui
+//!
+//! The `tui` module explores a fetched `DependencyGraph` interactively in the terminal, as a
+//! collapsible tree: the arrow keys move the selection, Enter expands or collapses the children
+//! of the selected crate, `/` searches a crate by name and `q` quits. Only the crates already in
+//! the graph are shown, nothing is fetched while exploring.
+//!
+//! The tree itself is a [`TreeView`], which can be driven without a terminal. The terminal is
+//! driven with `crossterm`, so exploring works on Windows too, and is redrawn when it is resized.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use depth::dependency_graph::DependencyGraph;
+//! use depth::tui::TreeView;
+//!
+//! let graph = DependencyGraph::from_edges(&[("app", &["tokio"]), ("tokio", &["mio"]), ("mio", &[])]);
+//!
+//! let mut view = TreeView::new(&graph, "app");
+//! let names = |view: &TreeView| -> Vec<String> {
+//!     view.rows().iter().map(|row| row.name().to_string()).collect()
+//! };
+//! assert_eq!(names(&view), vec!["app", "tokio"]);
+//!
+//! view.move_down();
+//! view.toggle();
+//! assert_eq!(names(&view), vec!["app", "tokio", "mio"]);
+//!
+//! // Searching expands the path to the first crate matching, and selects it
+//! view.toggle();
+//! assert!(view.search("mi"));
+//! assert_eq!(view.rows()[view.selected()].path(), ["app", "tokio", "mio"]);
+//! assert!(!view.search("serde"));
+//!
+//! // With `--no-root`, the dependencies of the hidden root are the top rows
+//! let mut graph = DependencyGraph::from_edges(&[("app", &["tokio", "serde"]), ("tokio", &["mio"])]);
+//! graph.hide_root("app");
+//! let view = TreeView::new(&graph, "app");
+//! assert_eq!(names(&view), vec!["tokio", "serde"]);
+//! assert_eq!(view.rows()[0].depth(), 0);
+//! ```
+
+use crate::dependency_graph::DependencyGraph;
+use crate::text::truncate_to_width;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, IsTerminal, Write};
+
+/// The help line printed at the bottom of the screen.
+const HELP: &str = "↑/↓ move  Enter expand/collapse  / search  q quit";
+
+/// A line of the tree, a crate reached through the crates above it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// The crates from the top row down to this one.
+    path: Vec<String>,
+    /// Whether the crate has dependencies in the graph that can be shown below it.
+    expandable: bool,
+    /// Whether its dependencies are shown below it.
+    expanded: bool,
+}
+
+impl Row {
+    /// Returns the name of the crate.
+    pub fn name(&self) -> &str {
+        self.path.last().expect("a row has at least a top crate")
+    }
+
+    /// Returns the crates from the top row down to this one.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// Returns the distance from the top rows, which are at depth 0.
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+
+    /// Returns `true` if the crate has dependencies that can be shown below it.
+    pub fn is_expandable(&self) -> bool {
+        self.expandable
+    }
+
+    /// Returns `true` if the dependencies of the crate are shown below it.
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+}
+
+/// A collapsible tree over a fetched graph, with a selected row.
+///
+/// The root is the top row and starts expanded, unless it is hidden with `--no-root`, its
+/// dependencies then being the top rows, collapsed.
+#[derive(Debug)]
+pub struct TreeView<'a> {
+    graph: &'a DependencyGraph,
+    /// The crates of the top rows.
+    roots: Vec<String>,
+    /// The paths of the expanded rows, a crate being expandable once under each of its parents.
+    expanded: HashSet<Vec<String>>,
+    selected: usize,
+}
+
+impl<'a> TreeView<'a> {
+    /// Creates a tree of the given root crate of the graph, with the first row selected.
+    pub fn new(graph: &'a DependencyGraph, root: &str) -> Self {
+        let mut view = TreeView {
+            graph,
+            roots: vec![root.to_string()],
+            expanded: HashSet::new(),
+            selected: 0,
+        };
+        if graph.is_hidden_root(root) {
+            view.roots = view
+                .children(root)
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+        } else {
+            view.expanded.insert(vec![root.to_string()]);
+        }
+        view
+    }
+
+    /// Returns the dependencies of a crate in the graph, in the order they are listed.
+    fn children(&self, crate_name: &str) -> Vec<&'a str> {
+        self.graph
+            .package(crate_name)
+            .map(|package| {
+                package
+                    .dependencies
+                    .iter()
+                    .map(|dependency| dependency.0.as_str())
+                    .filter(|name| !self.graph.is_hidden_root(name))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the crate at the end of the path has dependencies to show, a crate
+    /// already in the path closing a cycle instead.
+    fn is_expandable(&self, path: &[String]) -> bool {
+        let (name, ancestors) = path.split_last().expect("a path has at least a top crate");
+        !ancestors.contains(name) && !self.children(name).is_empty()
+    }
+
+    /// Returns the visible rows, the dependencies of the expanded rows below them.
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        let mut stack: Vec<Vec<String>> = self
+            .roots
+            .iter()
+            .rev()
+            .map(|root| vec![root.clone()])
+            .collect();
+        while let Some(path) = stack.pop() {
+            let expandable = self.is_expandable(&path);
+            let expanded = expandable && self.expanded.contains(&path);
+            if expanded {
+                let name = path.last().expect("a path has at least a top crate");
+                for child in self.children(name).into_iter().rev() {
+                    let mut child_path = path.clone();
+                    child_path.push(child.to_string());
+                    stack.push(child_path);
+                }
+            }
+            rows.push(Row {
+                path,
+                expandable,
+                expanded,
+            });
+        }
+        rows
+    }
+
+    /// Returns the index of the selected row in `rows`.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects the row above the selected one, if any.
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Selects the row below the selected one, if any.
+    pub fn move_down(&mut self) {
+        self.selected = (self.selected + 1).min(self.rows().len().saturating_sub(1));
+    }
+
+    /// Expands the selected row if it is collapsed, or collapses it if it is expanded.
+    pub fn toggle(&mut self) {
+        let rows = self.rows();
+        let Some(row) = rows.get(self.selected) else {
+            return;
+        };
+        if !row.expandable {
+            return;
+        }
+        if !self.expanded.remove(&row.path) {
+            self.expanded.insert(row.path.clone());
+        }
+    }
+
+    /// Selects the first crate, closest to the top rows, whose name contains the query, ignoring
+    /// case, and expands the rows leading to it.
+    ///
+    /// # Returns
+    ///
+    /// `true` if a crate matched, `false` leaving the tree unchanged otherwise.
+    pub fn search(&mut self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let mut seen: HashSet<&str> = self.roots.iter().map(String::as_str).collect();
+        let mut queue: VecDeque<Vec<String>> =
+            self.roots.iter().map(|root| vec![root.clone()]).collect();
+        while let Some(path) = queue.pop_front() {
+            let name = path.last().expect("a path has at least a top crate");
+            if name.to_lowercase().contains(&query) {
+                for len in 1..path.len() {
+                    self.expanded.insert(path[..len].to_vec());
+                }
+                self.selected = self
+                    .rows()
+                    .iter()
+                    .position(|row| row.path == path)
+                    .expect("the path to the crate is expanded");
+                return true;
+            }
+            for child in self.children(name) {
+                if seen.insert(child) {
+                    let mut child_path = path.clone();
+                    child_path.push(child.to_string());
+                    queue.push_back(child_path);
+                }
+            }
+        }
+        false
+    }
+
+    /// Queues the rows from `offset` on, at most `height` of them cut to `width` columns, the
+    /// selected row in reverse video.
+    fn draw_rows(
+        &self,
+        offset: usize,
+        height: usize,
+        width: usize,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let rows = self.rows();
+        for (line_index, (index, row)) in rows
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(height)
+            .enumerate()
+        {
+            let marker = match (row.expandable, row.expanded) {
+                (false, _) => ' ',
+                (true, false) => '▸',
+                (true, true) => '▾',
+            };
+            let version = self
+                .graph
+                .package(row.name())
+                .map(|package| package.version.as_str())
+                .filter(|version| !version.is_empty())
+                .map(|version| format!(" v{}", version))
+                .unwrap_or_default();
+            let line = format!(
+                "{:indent$}{} {}{}",
+                "",
+                marker,
+                self.graph.display_name(row.name()),
+                version,
+                indent = row.depth() * 2
+            );
+            queue!(out, MoveTo(0, line_index as u16))?;
+            if index == self.selected {
+                queue!(
+                    out,
+                    SetAttribute(Attribute::Reverse),
+                    Print(truncate_to_width(&line, width)),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(out, Print(truncate_to_width(&line, width)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keys are read one at a time without echo on the alternate screen, without the cursor, while
+/// this is alive. The terminal is restored when it is dropped, including while a panic unwinds.
+/// Ctrl-C is read as a key rather than sent as a signal, which would end the process before the
+/// terminal is restored.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enable(out: &mut impl Write) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = TerminalGuard;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Explores the tree of the given root crate of the graph interactively, until `q` or Ctrl-C
+/// is pressed. A hidden root is left out, its dependencies being the top rows.
+///
+/// The tree is drawn on the alternate screen of the terminal, which is restored on exit. Both
+/// stdin and stdout must be terminals.
+pub fn explore(graph: &DependencyGraph, root: &str) -> io::Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(io::Error::other("--tui needs an interactive terminal"));
+    }
+    let mut stdout = io::stdout();
+    let _guard = TerminalGuard::enable(&mut stdout)?;
+    explore_loop(&mut TreeView::new(graph, root), &mut stdout)
+}
+
+/// Draws the tree and handles the keys until `q` or Ctrl-C is pressed.
+fn explore_loop(view: &mut TreeView, out: &mut impl Write) -> io::Result<()> {
+    let (mut width, mut height) = terminal::size()?;
+    let mut offset = 0;
+    let mut query: Option<String> = None;
+    let mut status = HELP.to_string();
+    loop {
+        // The last line of the screen is the status line
+        let rows = usize::from(height).saturating_sub(1).max(1);
+        if view.selected() < offset {
+            offset = view.selected();
+        } else if view.selected() >= offset + rows {
+            offset = view.selected() + 1 - rows;
+        }
+        queue!(out, Clear(ClearType::All))?;
+        view.draw_rows(offset, rows, usize::from(width), out)?;
+        let status_line = match &query {
+            Some(query) => format!("/{}", query),
+            None => status.clone(),
+        };
+        queue!(
+            out,
+            MoveTo(0, rows as u16),
+            Print(truncate_to_width(&status_line, usize::from(width)))
+        )?;
+        out.flush()?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            Event::Resize(columns, lines) => {
+                (width, height) = (columns, lines);
+                continue;
+            }
+            _ => continue,
+        };
+        if is_quit(&key) {
+            return Ok(());
+        }
+        if let Some(typed) = &mut query {
+            match key.code {
+                KeyCode::Enter => {
+                    if !typed.is_empty() && !view.search(typed) {
+                        status = format!("No crate matching '{}'", typed);
+                    } else {
+                        status = HELP.to_string();
+                    }
+                    query = None;
+                }
+                KeyCode::Backspace => {
+                    typed.pop();
+                }
+                KeyCode::Esc => query = None,
+                KeyCode::Char(c) => typed.push(c),
+                _ => {}
+            }
+            continue;
+        }
+        match key.code {
+            KeyCode::Up => view.move_up(),
+            KeyCode::Down => view.move_down(),
+            KeyCode::Enter => view.toggle(),
+            KeyCode::Char('/') => query = Some(String::new()),
+            KeyCode::Char('q') => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Returns `true` for Ctrl-C, which quits like `q`, even while typing a search.
+fn is_quit(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}